@@ -0,0 +1,84 @@
+/*
+ * Read vector datasets through the Arrow C Stream Interface.
+ *
+ * Link against the ogr2arrow cdylib. Geometry columns are exported with
+ * their storage types (a fixed size list of two doubles for points,
+ * binary WKB otherwise); the GeoArrow extension metadata is not yet
+ * carried across the interface.
+ */
+#ifndef OGR2ARROW_H
+#define OGR2ARROW_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#ifndef ARROW_C_DATA_INTERFACE
+#define ARROW_C_DATA_INTERFACE
+
+#define ARROW_FLAG_DICTIONARY_ORDERED 1
+#define ARROW_FLAG_NULLABLE 2
+#define ARROW_FLAG_MAP_KEYS_SORTED 4
+
+struct ArrowSchema {
+  const char* format;
+  const char* name;
+  const char* metadata;
+  int64_t flags;
+  int64_t n_children;
+  struct ArrowSchema** children;
+  struct ArrowSchema* dictionary;
+  void (*release)(struct ArrowSchema*);
+  void* private_data;
+};
+
+struct ArrowArray {
+  int64_t length;
+  int64_t null_count;
+  int64_t offset;
+  int64_t n_buffers;
+  int64_t n_children;
+  const void** buffers;
+  struct ArrowArray** children;
+  struct ArrowArray* dictionary;
+  void (*release)(struct ArrowArray*);
+  void* private_data;
+};
+
+#endif  /* ARROW_C_DATA_INTERFACE */
+
+#ifndef ARROW_C_STREAM_INTERFACE
+#define ARROW_C_STREAM_INTERFACE
+
+struct ArrowArrayStream {
+  int (*get_schema)(struct ArrowArrayStream*, struct ArrowSchema* out);
+  int (*get_next)(struct ArrowArrayStream*, struct ArrowArray* out);
+  const char* (*get_last_error)(struct ArrowArrayStream*);
+  void (*release)(struct ArrowArrayStream*);
+  void* private_data;
+};
+
+#endif  /* ARROW_C_STREAM_INTERFACE */
+
+/*
+ * Open the dataset at `path` and export the layer `layer_name`, or its only
+ * layer if NULL, into `out`. Returns 0 on success, EINVAL for invalid
+ * arguments or EIO if the layer cannot be read, including when reading it
+ * panicked. The caller must release `out` once done with it.
+ */
+int ogr2arrow_open_layer_stream(const char* path, const char* layer_name,
+                                struct ArrowArrayStream* out);
+
+/*
+ * The message of the last error on the calling thread, or NULL. Owned by
+ * the library and valid until the next call on the same thread.
+ */
+const char* ogr2arrow_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif  /* OGR2ARROW_H */
@@ -0,0 +1,375 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Context;
+use arrow::record_batch::RecordBatch;
+use rusqlite::Connection;
+
+use crate::{
+    dataset::{Dataset, Format, ReadOptions},
+    driver::{self, DriverRegistry},
+    fgb,
+    geoarrow::{self, ExtensionMetadata},
+    geojson::{self, GeoJsonFormat},
+    geoparquet, gpkg,
+    ipc::{self, IpcFormat},
+    mbtiles, tiles,
+};
+
+/// The GeoPackage `srs_id` for the CRS of a geometry column, which is only
+/// known for EPSG codes and OGC:CRS84, stored as EPSG:4326.
+fn get_srs_id(metadata: Option<ExtensionMetadata>) -> i32 {
+    let metadata = match metadata {
+        Some(metadata) if metadata.is_crs84() => return 4326,
+        Some(metadata) => metadata,
+        None => return -1,
+    };
+    match metadata.authority_code() {
+        Some(("EPSG", code)) => code,
+        _ => -1,
+    }
+}
+
+/// Write `record_batch` to a new file at `path` in `format`, using each
+/// format's default write options. GeoPackages may already exist, in which
+/// case the layer is added to them. `crs_definitions`, as returned by
+/// [`Dataset::crs_definitions`], describe the CRSs to formats that need more
+/// than an authority code.
+pub fn write_layer(
+    path: &Path,
+    format: Format,
+    layer_name: &str,
+    record_batch: &RecordBatch,
+    crs_definitions: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let record_batches = std::slice::from_ref(record_batch);
+    match format {
+        Format::GeoPackage => {
+            let schema = record_batch.schema();
+            let geometry_field = geoarrow::primary_geometry_column(&schema)
+                .map(|index| schema.field(index))
+                .context("GeoPackage layers need a geometry column")?;
+            let metadata = geoarrow::get_extension_metadata(geometry_field);
+            let options = gpkg::WriteOptions {
+                geometry_column: geometry_field.name().clone(),
+                srs_definition: metadata
+                    .as_ref()
+                    .and_then(|metadata| crs_definitions.get(metadata.crs.as_ref()?))
+                    .cloned(),
+                srs_id: get_srs_id(metadata),
+                ..gpkg::WriteOptions::default()
+            };
+            let connection = Connection::open(path)?;
+            gpkg::write_layer(&connection, layer_name, record_batch, &options)
+        }
+        Format::GeoParquet => geoparquet::write_layer(
+            BufWriter::new(File::create(path)?),
+            record_batches,
+            &geoparquet::WriteOptions {
+                crs_definitions: crs_definitions.clone(),
+                ..geoparquet::WriteOptions::default()
+            },
+        ),
+        Format::Ipc(IpcFormat::File) => ipc::write_file(
+            BufWriter::new(File::create(path)?),
+            record_batches,
+            &ipc::WriteOptions {
+                crs_definitions: crs_definitions.clone(),
+                ..ipc::WriteOptions::default()
+            },
+        ),
+        Format::Ipc(IpcFormat::Stream) => ipc::write_stream(
+            BufWriter::new(File::create(path)?),
+            record_batches,
+            &ipc::WriteOptions {
+                crs_definitions: crs_definitions.clone(),
+                ..ipc::WriteOptions::default()
+            },
+        ),
+        Format::FlatGeobuf => fgb::write_layer(
+            BufWriter::new(File::create(path)?),
+            layer_name,
+            record_batches,
+            &fgb::WriteOptions::default(),
+        ),
+        Format::GeoJson(GeoJsonFormat::FeatureCollection) => geojson::write_feature_collection(
+            BufWriter::new(File::create(path)?),
+            record_batches,
+            &geojson::WriteOptions::default(),
+        ),
+        Format::GeoJson(GeoJsonFormat::Sequence) => geojson::write_sequence(
+            BufWriter::new(File::create(path)?),
+            record_batches,
+            &geojson::WriteOptions::default(),
+        ),
+        format => anyhow::bail!("Writing {:?} is not supported", format),
+    }
+    .context(format!("Failed to write {}", path.display()))
+}
+
+/// Copy the tile pyramid `layer_name` between GeoPackage and MBTiles files,
+/// adding it to `output` as a tile table of the same name if that is a
+/// GeoPackage. The metadata of an MBTiles input is kept for an MBTiles
+/// output.
+pub fn convert_tiles(
+    input: &Path,
+    layer_name: &str,
+    output: &Path,
+    format: Format,
+) -> anyhow::Result<()> {
+    let connection = Connection::open(input)?;
+    let (pyramid, mut metadata) = match Format::detect(input)? {
+        Format::GeoPackage => (
+            tiles::read_tile_pyramid(&connection, layer_name)?,
+            HashMap::new(),
+        ),
+        Format::MbTiles => {
+            driver::ensure_layer(input, layer_name, mbtiles::LAYER_NAME)?;
+            (
+                mbtiles::read_tile_pyramid(&connection)?,
+                mbtiles::get_metadata(&connection)?,
+            )
+        }
+        format => anyhow::bail!("{:?} has no tile pyramids", format),
+    };
+    let connection = Connection::open(output)?;
+    match format {
+        Format::GeoPackage => tiles::write_tile_pyramid(&connection, layer_name, &pyramid),
+        Format::MbTiles => {
+            for (name, value) in mbtiles::default_metadata(layer_name, &pyramid) {
+                metadata.entry(name).or_insert(value);
+            }
+            mbtiles::write_tile_pyramid(&connection, &metadata, &pyramid)
+        }
+        format => anyhow::bail!("Writing tiles to {:?} is not supported", format),
+    }
+    .context(format!("Failed to write {}", output.display()))
+}
+
+/// How [`convert_all`] lays out the converted layers in its output
+/// directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// A file per layer, named `<layer>.<extension>`.
+    #[default]
+    Files,
+    /// A directory per layer, with the layer written to
+    /// `<layer>/part-0.<extension>`. The directories are not Hive partitions,
+    /// as layers have different schemas and no `layer` column.
+    Partitioned,
+}
+
+/// The state of a [`convert_all`] run, reported as each layer finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    pub layer_name: &'a str,
+    /// Whether the layer was written successfully.
+    pub succeeded: bool,
+    /// The number of layers finished so far, including this one.
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// The path `layout` gives a layer in `directory`.
+pub fn layer_path(directory: &Path, layer_name: &str, format: Format, layout: Layout) -> PathBuf {
+    match layout {
+        Layout::Files => directory.join(format!("{}.{}", layer_name, format.extension())),
+        Layout::Partitioned => directory
+            .join(layer_name)
+            .join(format!("part-0.{}", format.extension())),
+    }
+}
+
+fn convert_layer(
+    dataset: &Dataset,
+    layer_name: &str,
+    path: &Path,
+    format: Format,
+    options: &ReadOptions,
+) -> anyhow::Result<()> {
+    let record_batch = dataset.get_layer_with_options(layer_name, options)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let crs_definitions = dataset.crs_definitions(&record_batch.schema())?;
+    write_layer(path, format, layer_name, &record_batch, &crs_definitions)
+}
+
+/// Convert every layer of the dataset at `input` into `directory`, reading
+/// each with `options`. Layers are converted in parallel, each thread with
+/// its own handle on the dataset, and `progress` is called as each one
+/// finishes. Returns the written paths in layer order, or the first error.
+pub fn convert_all<F>(
+    input: &Path,
+    directory: &Path,
+    format: Format,
+    layout: Layout,
+    options: &ReadOptions,
+    progress: F,
+) -> anyhow::Result<Vec<PathBuf>>
+where
+    F: Fn(&Progress) + Sync,
+{
+    let registry = DriverRegistry::default();
+    let layer_names = Dataset::open_with_registry(input, &registry)?.list_layers()?;
+    let paths: Vec<PathBuf> = layer_names
+        .iter()
+        .map(|layer_name| layer_path(directory, layer_name, format, layout))
+        .collect();
+
+    let threads = std::thread::available_parallelism()
+        .map_or(1, usize::from)
+        .min(layer_names.len());
+    let next = AtomicUsize::new(0);
+    let completed = AtomicUsize::new(0);
+    let mut results: Vec<(usize, anyhow::Result<()>)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let dataset = Dataset::open_with_registry(input, &registry);
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let layer_name = match layer_names.get(index) {
+                            Some(layer_name) => layer_name,
+                            None => break,
+                        };
+                        let result = match &dataset {
+                            Ok(dataset) => {
+                                convert_layer(dataset, layer_name, &paths[index], format, options)
+                                    .context(format!("Failed to convert {}", layer_name))
+                            }
+                            Err(error) => Err(anyhow::anyhow!("{:#}", error)),
+                        };
+                        progress(&Progress {
+                            layer_name,
+                            succeeded: result.is_ok(),
+                            completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                            total: layer_names.len(),
+                        });
+                        results.push((index, result));
+                    }
+                    results
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Conversion thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(index, _result)| *index);
+    for (_index, result) in results {
+        result?;
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{projjson, test_util::TempPath};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_write_layer() {
+        let dataset = Dataset::open("Data/point.gpkg").unwrap();
+        let layer = dataset.get_layer("point").unwrap();
+        let crs_definitions = dataset.crs_definitions(&layer.schema()).unwrap();
+
+        for extension in ["gpkg", "parquet", "arrow", "arrows", "fgb", "geojson"] {
+            let path = TempPath::new(&format!("ogr2arrow-convert.{}", extension));
+            let format = Format::from_extension(extension).unwrap();
+            write_layer(&path, format, "point", &layer, &crs_definitions).unwrap();
+
+            let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+            let layer_name = &dataset.list_layers().unwrap()[0];
+            let written = dataset.get_layer(layer_name).unwrap();
+            assert_eq!(layer.num_rows(), written.num_rows(), "{}", extension);
+        }
+
+        let path = TempPath::new("ogr2arrow-convert.shp");
+        assert!(write_layer(&path, Format::Shapefile, "point", &layer, &crs_definitions).is_err());
+    }
+
+    #[test]
+    fn test_convert_tiles() {
+        let geopackage = TempPath::new("ogr2arrow-convert-tiles.gpkg");
+        let mbtiles = TempPath::new("ogr2arrow-convert-tiles.mbtiles");
+
+        let input = Path::new("Data/tiles.mbtiles");
+        convert_tiles(input, "tiles", &geopackage, Format::GeoPackage).unwrap();
+        convert_tiles(&geopackage, "tiles", &mbtiles, Format::MbTiles).unwrap();
+
+        let expected = Dataset::open("Data/tiles.mbtiles")
+            .unwrap()
+            .get_layer("tiles")
+            .unwrap();
+        for path in [&geopackage, &mbtiles] {
+            let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+            assert_eq!(expected, dataset.get_layer("tiles").unwrap());
+        }
+        let connection = Connection::open(&mbtiles).unwrap();
+        assert_eq!("tiles", mbtiles::get_metadata(&connection).unwrap()["name"]);
+        let connection = Connection::open(&geopackage).unwrap();
+        let definition = gpkg::get_crs_definition(&connection, "EPSG", 3857)
+            .unwrap()
+            .unwrap();
+        assert!(definition.starts_with(r#"PROJCS["WGS 84 / Pseudo-Mercator""#));
+        assert!(projjson::from_wkt(&definition).is_ok());
+    }
+
+    #[test]
+    fn test_convert_all() {
+        let input = Path::new("Data/spatialite.sqlite");
+        let directory = TempPath::new("ogr2arrow-convert-all");
+        let finished = Mutex::new(Vec::new());
+
+        let paths = convert_all(
+            input,
+            &directory,
+            Format::GeoParquet,
+            Layout::Partitioned,
+            &ReadOptions::default(),
+            |progress| {
+                assert!(progress.succeeded);
+                assert_eq!(2, progress.total);
+                finished
+                    .lock()
+                    .unwrap()
+                    .push(progress.layer_name.to_string());
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                directory.join("point").join("part-0.parquet"),
+                directory.join("polygons").join("part-0.parquet"),
+            ],
+            paths
+        );
+        let mut finished = finished.into_inner().unwrap();
+        finished.sort();
+        assert_eq!(vec!["point", "polygons"], finished);
+        let polygons = Dataset::open(paths[1].to_str().unwrap()).unwrap();
+        assert_eq!(2, polygons.get_layer("part-0").unwrap().num_rows());
+
+        let paths = convert_all(
+            input,
+            &directory,
+            Format::FlatGeobuf,
+            Layout::Files,
+            &ReadOptions::default(),
+            |_progress| {},
+        )
+        .unwrap();
+        assert_eq!(directory.join("point.fgb"), paths[0]);
+        assert!(paths.iter().all(|path| path.exists()));
+    }
+}
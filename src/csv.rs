@@ -0,0 +1,344 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use arrow::{
+    array::{as_primitive_array, as_string_array, Array, ArrayRef, BinaryArray},
+    csv::{reader::infer_file_schema, ReaderBuilder},
+    datatypes::{DataType, Field, Float64Type, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver},
+    geoarrow::{self, ExtensionMetadata},
+    wkt,
+};
+
+/// The name given to the point column built from X/Y columns.
+const GEOMETRY_COLUMN: &str = "geom";
+
+/// Column names, compared case-insensitively, that hold WKT geometries.
+const WKT_COLUMNS: [&str; 5] = ["wkt", "geometry", "geom", "the_geom", "wkt_geom"];
+
+/// The number of records read to infer the column types.
+const MAX_READ_RECORDS: usize = 1000;
+
+/// Options controlling how the geometry of a CSV file is found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    /// The column holding WKT geometries. When unset, a column with a
+    /// well-known name or whose first value parses as WKT is used.
+    pub wkt_column: Option<String>,
+    /// `(x, y)` column name pairs, compared case-insensitively and tried in
+    /// order when there is no WKT column.
+    pub xy_columns: Vec<(String, String)>,
+    /// The CRS of the geometries.
+    pub crs: ExtensionMetadata,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        let xy_columns = [
+            ("x", "y"),
+            ("lon", "lat"),
+            ("lng", "lat"),
+            ("long", "lat"),
+            ("longitude", "latitude"),
+            ("easting", "northing"),
+        ];
+        CsvOptions {
+            delimiter: b',',
+            wkt_column: None,
+            xy_columns: xy_columns
+                .iter()
+                .map(|(x, y)| (x.to_string(), y.to_string()))
+                .collect(),
+            crs: ExtensionMetadata::default(),
+        }
+    }
+}
+
+/// Where the geometry of each row comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeometrySource {
+    Wkt(usize),
+    Xy(usize, usize),
+}
+
+fn find_column(schema: &Schema, name: &str) -> Option<usize> {
+    schema
+        .fields()
+        .iter()
+        .position(|field| field.name().eq_ignore_ascii_case(name))
+}
+
+/// The first string column whose first non-empty value parses as WKT.
+fn sniff_wkt_column(record_batch: &RecordBatch) -> Option<usize> {
+    (0..record_batch.num_columns()).find(|&index| {
+        let column = record_batch.column(index);
+        if column.data_type() != &DataType::Utf8 {
+            return false;
+        }
+        let values = as_string_array(column);
+        (0..values.len())
+            .find(|&row| values.is_valid(row) && !values.value(row).trim().is_empty())
+            .is_some_and(|row| wkt::parse_wkt(values.value(row)).is_ok())
+    })
+}
+
+/// A CSV file with a WKT or X/Y geometry, exposed as a single layer named
+/// after the file stem.
+pub struct CsvFile {
+    path: PathBuf,
+    delimiter: u8,
+    csv_schema: SchemaRef,
+    geometry: GeometrySource,
+    schema: SchemaRef,
+}
+
+impl CsvFile {
+    pub fn open(path: &Path, options: &CsvOptions) -> anyhow::Result<Self> {
+        let (inferred_schema, _) = infer_file_schema(
+            File::open(path)?,
+            options.delimiter,
+            Some(MAX_READ_RECORDS),
+            true,
+        )?;
+
+        let wkt_column = match &options.wkt_column {
+            Some(name) => Some(inferred_schema.index_of(name)?),
+            None => WKT_COLUMNS
+                .iter()
+                .filter_map(|name| find_column(&inferred_schema, name))
+                .find(|&index| inferred_schema.field(index).data_type() == &DataType::Utf8),
+        };
+        let wkt_column = match wkt_column {
+            Some(index) => Some(index),
+            None => {
+                let mut reader = ReaderBuilder::new()
+                    .with_schema(Arc::new(inferred_schema.clone()))
+                    .with_delimiter(options.delimiter)
+                    .has_header(true)
+                    .with_batch_size(1)
+                    .build(File::open(path)?)?;
+                match reader.next() {
+                    Some(record_batch) => sniff_wkt_column(&record_batch?),
+                    None => None,
+                }
+            }
+        };
+        let geometry = match wkt_column {
+            Some(index) => GeometrySource::Wkt(index),
+            None => options
+                .xy_columns
+                .iter()
+                .find_map(|(x, y)| {
+                    Some(GeometrySource::Xy(
+                        find_column(&inferred_schema, x)?,
+                        find_column(&inferred_schema, y)?,
+                    ))
+                })
+                .context("No WKT or X/Y geometry columns found")?,
+        };
+
+        // Read X/Y as floats even when every value is a whole number, and
+        // WKT as strings.
+        let mut csv_fields = inferred_schema.fields().clone();
+        let mut fields = inferred_schema.fields().clone();
+        match geometry {
+            GeometrySource::Wkt(index) => {
+                let name = csv_fields[index].name().clone();
+                csv_fields[index] = Field::new(&name, DataType::Utf8, true);
+                fields[index] = geoarrow::geometry_field(&name, DataType::Binary, &options.crs);
+            }
+            GeometrySource::Xy(x, y) => {
+                for index in [x, y] {
+                    let name = csv_fields[index].name().clone();
+                    csv_fields[index] = Field::new(&name, DataType::Float64, true);
+                    fields[index] = csv_fields[index].clone();
+                }
+                fields.push(geoarrow::geometry_field(
+                    GEOMETRY_COLUMN,
+                    geoarrow::point_data_type(),
+                    &options.crs,
+                ));
+            }
+        }
+
+        Ok(CsvFile {
+            path: path.to_path_buf(),
+            delimiter: options.delimiter,
+            csv_schema: Arc::new(Schema::new(csv_fields)),
+            geometry,
+            schema: Arc::new(Schema::new(fields)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn layer_name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn read(&self, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        let reader = ReaderBuilder::new()
+            .with_schema(self.csv_schema.clone())
+            .with_delimiter(self.delimiter)
+            .has_header(true)
+            .build(File::open(&self.path)?)?;
+        let record_batches = reader.collect::<Result<Vec<_>, _>>()?;
+        let record_batch = RecordBatch::concat(&self.csv_schema, &record_batches)?;
+
+        let mut columns = record_batch.columns().to_vec();
+        match self.geometry {
+            GeometrySource::Wkt(index) => {
+                let values = as_string_array(&columns[index]);
+                let wkb = (0..values.len())
+                    .map(
+                        |row| match values.is_valid(row) && !values.value(row).is_empty() {
+                            true => Ok(Some(wkt::parse_wkt(values.value(row))?.to_wkb())),
+                            false => Ok(None),
+                        },
+                    )
+                    .collect::<anyhow::Result<Vec<Option<Vec<u8>>>>>()?;
+                columns[index] = Arc::new(BinaryArray::from_opt_vec(
+                    wkb.iter().map(|wkb| wkb.as_deref()).collect(),
+                )) as ArrayRef;
+            }
+            GeometrySource::Xy(x, y) => {
+                let x = as_primitive_array::<Float64Type>(&columns[x]);
+                let y = as_primitive_array::<Float64Type>(&columns[y]);
+                let points = (0..x.len())
+                    .map(|row| match x.is_valid(row) && y.is_valid(row) {
+                        true => Some([x.value(row), y.value(row)]),
+                        false => None,
+                    })
+                    .collect();
+                columns.push(geoarrow::point_array(points));
+            }
+        }
+
+        let record_batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        options.apply(record_batch)
+    }
+}
+
+impl Driver for CsvFile {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| matches!(format, Format::Csv { .. }))
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let delimiter = match Format::detect(path)? {
+            Format::Csv { delimiter } => delimiter,
+            _ => b',',
+        };
+        let options = CsvOptions {
+            delimiter,
+            ..CsvOptions::default()
+        };
+        CsvFile::open(path, &options)
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(vec![self.layer_name()])
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        Ok(self.schema())
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        self.read(options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{dataset::Dataset, test_util::TempPath};
+    use arrow::datatypes::Int64Type;
+    use std::io::Write;
+
+    fn write_csv(file_name: &str, contents: &str) -> TempPath {
+        let path = TempPath::new(file_name);
+        File::create(&path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_xy() {
+        let path = write_csv(
+            "ogr2arrow-lonlat.csv",
+            "id,name,Lon,Lat\n1,a,0,0\n2,b,1.5,1\n3,c,,\n",
+        );
+
+        let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+        let layer = dataset.get_layer("ogr2arrow-lonlat").unwrap();
+
+        let schema = layer.schema();
+        assert_eq!(&DataType::Int64, schema.field(0).data_type());
+        assert_eq!(&DataType::Float64, schema.field(2).data_type());
+        assert_eq!(Some(4), geoarrow::primary_geometry_column(&schema));
+        assert_eq!(&geoarrow::point_data_type(), schema.field(4).data_type());
+        let point = geoarrow::get_geometry(layer.column(4).as_ref(), 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Some([1.5, 1.0, 1.5, 1.0]), point.envelope());
+        assert!(layer.column(4).is_null(2));
+    }
+
+    #[test]
+    fn test_read_wkt() {
+        let path = write_csv(
+            "ogr2arrow-wkt.csv",
+            "id;shape\n1;POINT (0 0)\n2;\"LINESTRING (0 0, 2 1)\"\n",
+        );
+        let options = CsvOptions {
+            delimiter: b';',
+            crs: ExtensionMetadata::from_authority_code("EPSG", 27700),
+            ..CsvOptions::default()
+        };
+
+        let file = CsvFile::open(&path, &options).unwrap();
+        let layer = file
+            .read(&ReadOptions {
+                bbox: Some([1.0, 0.5, 3.0, 3.0]),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(Some(1), geoarrow::primary_geometry_column(&layer.schema()));
+        assert_eq!(
+            &[2],
+            as_primitive_array::<Int64Type>(layer.column(0)).values()
+        );
+        assert_eq!(
+            Some([0.0, 0.0, 2.0, 1.0]),
+            geoarrow::get_geometry(layer.column(1).as_ref(), 0)
+                .unwrap()
+                .unwrap()
+                .envelope()
+        );
+    }
+}
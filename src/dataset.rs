@@ -1,43 +1,686 @@
-use std::{fmt::Display, path::Path};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::Context;
-use arrow::record_batch::RecordBatch;
+use arrow::{
+    datatypes::{Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream};
 
-use crate::gpkg;
+use crate::{
+    csv::{CsvFile, CsvOptions},
+    driver::{Driver, DriverRegistry},
+    fgb::FgbFile,
+    geoarrow::{self, ExtensionMetadata},
+    geojson::{GeoJsonFile, GeoJsonFormat},
+    geoparquet::GeoParquetFile,
+    gpkg::GeoPackage,
+    ipc::{IpcFile, IpcFormat},
+    mbtiles::MbTiles,
+    shapefile::Shapefile,
+    spatialite::Spatialite,
+};
 
-pub enum Dataset {
-    Gpkg(rusqlite::Connection),
+/// How geometry columns are encoded in a layer that has been read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GeometryFormat {
+    /// Whatever the format stores: GeoArrow points where possible, otherwise
+    /// WKB.
+    #[default]
+    Native,
+    /// `geoarrow.wkt` strings, with coordinates rounded to `precision`
+    /// decimal places if given.
+    Wkt { precision: Option<usize> },
+    /// `geoarrow.wkb` binaries.
+    Wkb,
 }
 
+/// Options shared by every format when reading a layer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadOptions {
+    /// Only return features whose envelope intersects
+    /// `[min_x, min_y, max_x, max_y]`.
+    pub bbox: Option<[f64; 4]>,
+    /// Only return these columns, in this order.
+    pub columns: Option<Vec<String>>,
+    /// The encoding of the returned geometry columns.
+    pub geometry_format: GeometryFormat,
+    /// An SQL expression that returned rows must satisfy, only supported by
+    /// GeoPackage, SpatiaLite and MBTiles.
+    pub filter: Option<String>,
+}
+
+impl ReadOptions {
+    /// Apply the bbox filter, using the primary geometry column, the
+    /// projection and then the geometry format to a fully read layer.
+    pub fn apply(&self, record_batch: RecordBatch) -> anyhow::Result<RecordBatch> {
+        self.ensure_no_filter()?;
+        self.apply_filtered(record_batch)
+    }
+
+    /// [`ReadOptions::apply`] for formats that have already applied the SQL
+    /// filter while reading.
+    pub(crate) fn apply_filtered(&self, record_batch: RecordBatch) -> anyhow::Result<RecordBatch> {
+        let record_batch = match (
+            self.bbox,
+            geoarrow::primary_geometry_column(&record_batch.schema()),
+        ) {
+            (Some(bbox), Some(column)) => geoarrow::filter_bbox(&record_batch, column, &bbox)?,
+            _ => record_batch,
+        };
+        let record_batch = self.project(record_batch)?;
+        self.encode(record_batch)
+    }
+
+    pub(crate) fn ensure_no_filter(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.filter.is_none(),
+            "SQL filters are only supported by GeoPackage, SpatiaLite and MBTiles"
+        );
+        Ok(())
+    }
+
+    pub(crate) fn project(&self, record_batch: RecordBatch) -> anyhow::Result<RecordBatch> {
+        let columns = match &self.columns {
+            None => return Ok(record_batch),
+            Some(columns) => columns,
+        };
+        let schema = record_batch.schema();
+        let indices = columns
+            .iter()
+            .map(|column| schema.index_of(column))
+            .collect::<Result<Vec<usize>, _>>()?;
+        Ok(record_batch.project(&indices)?)
+    }
+
+    /// Re-encode the geometry columns in the requested geometry format.
+    pub(crate) fn encode(&self, record_batch: RecordBatch) -> anyhow::Result<RecordBatch> {
+        match self.geometry_format {
+            GeometryFormat::Native => Ok(record_batch),
+            GeometryFormat::Wkt { precision } => {
+                geoarrow::geometries_to_wkt(&record_batch, precision)
+            }
+            GeometryFormat::Wkb => geoarrow::geometries_to_wkb(&record_batch),
+        }
+    }
+}
+
+/// An opened dataset, read through the [`Driver`] for its format.
+pub struct Dataset {
+    driver: Box<dyn Driver>,
+    /// Opens the driver again, so that the dataset can be read on another
+    /// thread. `None` for datasets made from a driver rather than a path.
+    reopen: Option<Reopen>,
+}
+
+type Reopen = Arc<dyn Fn() -> anyhow::Result<Box<dyn Driver>> + Send + Sync>;
+
+/// The file formats a [`Dataset`] can be opened as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    GeoPackage,
+    /// A SQLite database, read as SpatiaLite if it has the SpatiaLite
+    /// metadata tables.
+    Sqlite,
+    GeoParquet,
+    Ipc(IpcFormat),
+    FlatGeobuf,
+    GeoJson(GeoJsonFormat),
+    Shapefile,
+    /// An MBTiles tile pyramid.
+    MbTiles,
+    /// Delimited text with the given field delimiter.
+    Csv {
+        delimiter: u8,
+    },
+}
+
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+const GEOPACKAGE_APPLICATION_IDS: [&[u8]; 3] = [b"GPKG", b"GP10", b"GP11"];
+const MBTILES_APPLICATION_ID: &[u8] = b"MPBX";
+const SHAPEFILE_FILE_CODE: &[u8] = &[0x00, 0x00, 0x27, 0x0a];
+
+/// The number of bytes read from the start of a file to detect its format.
+const SNIFF_LENGTH: u64 = 4096;
+
+impl Format {
+    /// Detect the format of a file from its first bytes.
+    pub fn sniff(header: &[u8]) -> Option<Format> {
+        let format = match header {
+            header if header.starts_with(SQLITE_MAGIC) => match header.get(68..72) {
+                Some(application_id) if GEOPACKAGE_APPLICATION_IDS.contains(&application_id) => {
+                    Format::GeoPackage
+                }
+                Some(MBTILES_APPLICATION_ID) => Format::MbTiles,
+                _ => Format::Sqlite,
+            },
+            [b'P', b'A', b'R', b'1', ..] => Format::GeoParquet,
+            [b'A', b'R', b'R', b'O', b'W', b'1', ..] => Format::Ipc(IpcFormat::File),
+            [0xff, 0xff, 0xff, 0xff, ..] => Format::Ipc(IpcFormat::Stream),
+            [b'f', b'g', b'b', _, b'f', b'g', b'b', ..] => Format::FlatGeobuf,
+            header if header.starts_with(SHAPEFILE_FILE_CODE) => Format::Shapefile,
+            header => {
+                let text = header.strip_prefix("\u{feff}".as_bytes()).unwrap_or(header);
+                match text.iter().find(|byte| !byte.is_ascii_whitespace()) {
+                    Some(0x1e) => Format::GeoJson(GeoJsonFormat::Sequence),
+                    Some(b'{') => match String::from_utf8_lossy(text).contains("FeatureCollection")
+                    {
+                        true => Format::GeoJson(GeoJsonFormat::FeatureCollection),
+                        false => Format::GeoJson(GeoJsonFormat::Sequence),
+                    },
+                    _ => return None,
+                }
+            }
+        };
+        Some(format)
+    }
+
+    /// The format conventionally used for files with `extension`.
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        let format = match extension.to_ascii_lowercase().as_str() {
+            "gpkg" => Format::GeoPackage,
+            "sqlite" | "db" | "spatialite" => Format::Sqlite,
+            "parquet" | "geoparquet" => Format::GeoParquet,
+            "arrow" | "feather" => Format::Ipc(IpcFormat::File),
+            "arrows" => Format::Ipc(IpcFormat::Stream),
+            "fgb" => Format::FlatGeobuf,
+            "geojson" | "json" => Format::GeoJson(GeoJsonFormat::FeatureCollection),
+            "geojsonl" | "geojsons" => Format::GeoJson(GeoJsonFormat::Sequence),
+            "shp" => Format::Shapefile,
+            "mbtiles" => Format::MbTiles,
+            "csv" => Format::Csv { delimiter: b',' },
+            "tsv" => Format::Csv { delimiter: b'\t' },
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    /// The extension conventionally given to files in this format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::GeoPackage => "gpkg",
+            Format::Sqlite => "sqlite",
+            Format::GeoParquet => "parquet",
+            Format::Ipc(IpcFormat::File) => "arrow",
+            Format::Ipc(IpcFormat::Stream) => "arrows",
+            Format::FlatGeobuf => "fgb",
+            Format::GeoJson(GeoJsonFormat::FeatureCollection) => "geojson",
+            Format::GeoJson(GeoJsonFormat::Sequence) => "geojsonl",
+            Format::Shapefile => "shp",
+            Format::MbTiles => "mbtiles",
+            Format::Csv { delimiter: b'\t' } => "tsv",
+            Format::Csv { .. } => "csv",
+        }
+    }
+
+    /// Detect the format of a file from its first bytes, falling back to its
+    /// extension. SQLite databases are MBTiles if their extension says so,
+    /// as few set the MBTiles `application_id`.
+    pub fn identify(path: &Path, header: &[u8]) -> Option<Format> {
+        let from_extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Format::from_extension);
+        match Format::sniff(header) {
+            Some(Format::Sqlite) if from_extension == Some(Format::MbTiles) => from_extension,
+            None => from_extension,
+            format => format,
+        }
+    }
+
+    /// Detect the format of the file at `path` from its content, falling
+    /// back to its extension.
+    pub fn detect(path: &Path) -> anyhow::Result<Format> {
+        let mut header = Vec::new();
+        File::open(path)?
+            .take(SNIFF_LENGTH)
+            .read_to_end(&mut header)?;
+        Format::identify(path, &header).ok_or_else(|| {
+            UnsupportedFormat {
+                path: path.to_path_buf(),
+            }
+            .into()
+        })
+    }
+}
+
+/// The error returned when opening a file in no supported format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedFormat {
+    pub path: PathBuf,
+}
+
+impl Display for UnsupportedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unsupported format: {}", self.path.display())
+    }
+}
+
+impl std::error::Error for UnsupportedFormat {}
+
 impl Dataset {
+    /// Open `path` with the built-in drivers, detecting its format from its
+    /// content or extension. Files in no supported format return an
+    /// [`UnsupportedFormat`] error.
     pub fn open<P: AsRef<Path> + Display>(path: P) -> anyhow::Result<Dataset> {
+        Dataset::open_with_registry(path, &DriverRegistry::default())
+    }
+
+    /// Open `path` with the first driver of `registry` that accepts it.
+    pub fn open_with_registry<P: AsRef<Path>>(
+        path: P,
+        registry: &DriverRegistry,
+    ) -> anyhow::Result<Dataset> {
         let path = path.as_ref();
-        match path.extension() {
-            None => unimplemented!(),
-            Some(extension) => match extension.to_str() {
-                Some("gpkg") => {
-                    let connection = rusqlite::Connection::open(&path)
-                        .context(format!("Failed to open {}", &path.display()))?;
-                    let dataset = Dataset::Gpkg(connection);
-                    Ok(dataset)
+        let open = registry
+            .find(path)
+            .context(format!("Failed to open {}", &path.display()))?;
+        let driver = open(path).context(format!("Failed to open {}", &path.display()))?;
+        let path = path.to_path_buf();
+        Ok(Dataset {
+            driver,
+            reopen: Some(Arc::new(move || open(&path))),
+        })
+    }
+
+    /// Open `path` as `format`, regardless of its content or extension.
+    pub fn open_with_format<P: AsRef<Path>>(path: P, format: Format) -> anyhow::Result<Dataset> {
+        let path = path.as_ref();
+        let driver =
+            open_format(path, format).context(format!("Failed to open {}", &path.display()))?;
+        let path = path.to_path_buf();
+        Ok(Dataset {
+            driver,
+            reopen: Some(Arc::new(move || open_format(&path, format))),
+        })
+    }
+
+    pub fn driver(&self) -> &dyn Driver {
+        self.driver.as_ref()
+    }
+
+    pub fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        self.driver.list_layers().context("Failed to list layers")
+    }
+
+    /// A handle on the layer called `layer_name`.
+    pub fn layer(&self, layer_name: &str) -> anyhow::Result<Layer<'_>> {
+        let schema = self
+            .driver
+            .layer_schema(layer_name)
+            .context(format!("Failed to get {}", layer_name))?;
+        Ok(Layer {
+            name: layer_name.to_string(),
+            schema,
+            driver: self.driver.as_ref(),
+        })
+    }
+
+    /// Handles on every layer, in the order they are listed.
+    pub fn layers(&self) -> anyhow::Result<Vec<Layer<'_>>> {
+        self.list_layers()?
+            .iter()
+            .map(|layer_name| self.layer(layer_name))
+            .collect()
+    }
+
+    pub fn get_layer(&self, layer_name: &str) -> anyhow::Result<RecordBatch> {
+        self.get_layer_with_options(layer_name, &ReadOptions::default())
+    }
+
+    pub fn get_layer_with_options(
+        &self,
+        layer_name: &str,
+        options: &ReadOptions,
+    ) -> anyhow::Result<RecordBatch> {
+        self.driver
+            .read_layer(layer_name, options)
+            .context(format!("Failed to get {}", layer_name))
+    }
+
+    /// The WKT definitions the dataset records for the CRSs of the geometry
+    /// fields in `schema`, keyed by their GeoArrow `crs`, as writers expect
+    /// them.
+    pub fn crs_definitions(&self, schema: &Schema) -> anyhow::Result<HashMap<String, String>> {
+        let mut definitions = HashMap::new();
+        for field in schema.fields() {
+            let metadata = match geoarrow::get_extension_metadata(field) {
+                Some(metadata) => metadata,
+                None => continue,
+            };
+            if let (Some(crs), Some((authority, code))) = (&metadata.crs, metadata.authority_code())
+            {
+                if let Some(definition) = self.driver.crs_definition(authority, code)? {
+                    definitions.insert(crs.clone(), definition);
                 }
-                _ => unimplemented!(),
-            },
+            }
         }
+        Ok(definitions)
     }
-    pub fn list_layers(self) -> anyhow::Result<Vec<String>> {
-        let layers = match self {
-            Dataset::Gpkg(connection) => {
-                gpkg::list_layers(&connection).context("Failed to list layers")?
+
+    /// Stream a layer in record batches of up to `batch_size` rows. The
+    /// dataset is opened again on tokio's blocking thread pool and read there
+    /// through [`Driver::read_layer_batches`], pausing while
+    /// [`STREAM_BUFFER`] record batches are waiting for the consumer.
+    ///
+    /// GeoPackage and SpatiaLite feature tables are read a FID range at a
+    /// time; other layers are read whole on the blocking thread and then
+    /// split. Datasets made from a driver cannot be opened again, so their
+    /// stream only returns an error. Must be called from within a tokio
+    /// runtime.
+    pub fn get_layer_stream(
+        &self,
+        layer_name: &str,
+        options: &ReadOptions,
+        batch_size: usize,
+    ) -> impl Stream<Item = anyhow::Result<RecordBatch>> {
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let reopen = self.reopen.clone();
+        let layer_name = layer_name.to_string();
+        let options = options.clone();
+        tokio::task::spawn_blocking(move || {
+            let context = format!("Failed to get {}", layer_name);
+            let record_batches = reopen
+                .context("Only datasets opened from a path can be streamed")
+                .and_then(|reopen| reopen())
+                .and_then(|driver| {
+                    driver.read_layer_batches(&layer_name, &options, batch_size.max(1))
+                });
+            let record_batches = match record_batches {
+                Ok(record_batches) => record_batches,
+                Err(error) => {
+                    let _ = sender.blocking_send(Err(error.context(context)));
+                    return;
+                }
+            };
+            for record_batch in record_batches {
+                if sender
+                    .blocking_send(record_batch.context(context.clone()))
+                    .is_err()
+                {
+                    break;
+                }
             }
-        };
-        Ok(layers)
+        });
+        ReceiverStream::new(receiver)
+    }
+}
+
+/// The number of record batches [`Dataset::get_layer_stream`] reads ahead of
+/// its consumer.
+pub const STREAM_BUFFER: usize = 2;
+
+/// A layer of an open [`Dataset`], which can be read any number of times.
+pub struct Layer<'a> {
+    name: String,
+    schema: SchemaRef,
+    driver: &'a dyn Driver,
+}
+
+impl Layer<'_> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// The schema-level metadata of the layer.
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        self.schema.metadata()
+    }
+
+    /// The primary geometry field, if the layer has one.
+    pub fn geometry_field(&self) -> Option<&Field> {
+        geoarrow::primary_geometry_column(&self.schema).map(|index| self.schema.field(index))
+    }
+
+    /// The GeoArrow metadata, including the CRS, of the primary geometry
+    /// field.
+    pub fn extension_metadata(&self) -> Option<ExtensionMetadata> {
+        self.geometry_field()
+            .and_then(geoarrow::get_extension_metadata)
     }
-    pub fn get_layer(self, layer_name: &str) -> anyhow::Result<RecordBatch> {
-        let layer = match self {
-            Dataset::Gpkg(connection) => gpkg::get_layer(&connection, layer_name)
-                .context(format!("Failed to get {}", layer_name))?,
+
+    pub fn read(&self) -> anyhow::Result<RecordBatch> {
+        self.read_with_options(&ReadOptions::default())
+    }
+
+    pub fn read_with_options(&self, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        self.driver
+            .read_layer(&self.name, options)
+            .context(format!("Failed to get {}", self.name))
+    }
+}
+
+impl From<Box<dyn Driver>> for Dataset {
+    fn from(driver: Box<dyn Driver>) -> Self {
+        Dataset {
+            driver,
+            reopen: None,
+        }
+    }
+}
+
+/// Open `path` with the built-in driver for `format`.
+fn open_format(path: &Path, format: Format) -> anyhow::Result<Box<dyn Driver>> {
+    let driver: Box<dyn Driver> = match format {
+        Format::GeoPackage => Box::new(GeoPackage::open(path)?),
+        Format::Sqlite => Box::new(Spatialite::open(path)?),
+        Format::GeoParquet => Box::new(GeoParquetFile::open(path)?),
+        Format::Ipc(format) => Box::new(IpcFile::open(path, format)?),
+        Format::FlatGeobuf => Box::new(FgbFile::open(path)?),
+        Format::GeoJson(format) => Box::new(GeoJsonFile::open(path, format)?),
+        Format::Shapefile => Box::new(Shapefile::open(path)?),
+        Format::MbTiles => Box::new(MbTiles::open(path)?),
+        Format::Csv { delimiter } => {
+            let options = CsvOptions {
+                delimiter,
+                ..CsvOptions::default()
+            };
+            Box::new(CsvFile::open(path, &options)?)
+        }
+    };
+    Ok(driver)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::TempPath;
+    use arrow::array::{ArrayRef, Int32Array};
+    use std::{sync::Mutex, time::Duration};
+
+    #[test]
+    fn test_detect_format() {
+        let cases = [
+            ("Data/point.gpkg", Format::GeoPackage),
+            ("Data/spatialite.sqlite", Format::Sqlite),
+            ("Data/point.shp", Format::Shapefile),
+        ];
+        for (path, format) in cases {
+            assert_eq!(format, Format::detect(Path::new(path)).unwrap());
+        }
+        assert_eq!(
+            Some(Format::GeoJson(GeoJsonFormat::Sequence)),
+            Format::sniff(b"\x1e{\"type\": \"Feature\"}")
+        );
+        assert_eq!(None, Format::sniff(b"id,name\n1,a\n"));
+    }
+
+    #[test]
+    fn test_open_without_extension() {
+        let path = TempPath::new("ogr2arrow-point");
+        std::fs::copy("Data/point.gpkg", &path).unwrap();
+
+        let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(vec!["point".to_string()], dataset.list_layers().unwrap());
+    }
+
+    #[test]
+    fn test_open_with_format() {
+        let path = TempPath::new("ogr2arrow-points.txt");
+        std::fs::write(&path, "x\ty\n0\t0\n1\t1\n").unwrap();
+
+        let error = Dataset::open(path.to_str().unwrap()).err().unwrap();
+        assert_eq!(
+            Some(&UnsupportedFormat {
+                path: path.to_path_buf()
+            }),
+            error.downcast_ref::<UnsupportedFormat>()
+        );
+
+        let layer = Dataset::open_with_format(&path, Format::Csv { delimiter: b'\t' })
+            .unwrap()
+            .get_layer("ogr2arrow-points")
+            .unwrap();
+        assert_eq!(2, layer.num_rows());
+    }
+
+    #[test]
+    fn test_read_every_layer() {
+        let dataset = Dataset::open("Data/spatialite.sqlite").unwrap();
+
+        let layers = dataset.layers().unwrap();
+        let names: Vec<&str> = layers.iter().map(Layer::name).collect();
+        assert_eq!(vec!["point", "polygons"], names);
+        for layer in &layers {
+            let record_batch = layer.read().unwrap();
+            assert_eq!(layer.schema(), record_batch.schema());
+            assert_eq!(2, record_batch.num_rows());
+        }
+        assert_eq!(
+            Some(("EPSG", 27700)),
+            layers[0].extension_metadata().unwrap().authority_code()
+        );
+        assert_eq!("geometry", layers[1].geometry_field().unwrap().name());
+        assert!(dataset.layer("lines").is_err());
+    }
+
+    #[test]
+    fn test_read_tile_layer() {
+        let dataset = Dataset::open("Data/tiles.gpkg").unwrap();
+        let layer = dataset.layer("basemap").unwrap();
+        assert_eq!(layer.schema(), layer.read().unwrap().schema());
+
+        let options = ReadOptions {
+            bbox: Some([600.0, 600.0, 700.0, 700.0]),
+            columns: Some(vec!["zoom_level".to_string(), "tile_data".to_string()]),
+            ..Default::default()
         };
-        Ok(layer)
+        let record_batch = layer.read_with_options(&options).unwrap();
+        assert_eq!(2, record_batch.num_rows());
+        assert_eq!(2, record_batch.num_columns());
+    }
+
+    #[tokio::test]
+    async fn test_get_layer_stream() {
+        use tokio_stream::StreamExt;
+
+        let expected_layer = Dataset::open("Data/spatialite.sqlite")
+            .unwrap()
+            .get_layer("polygons")
+            .unwrap();
+        let record_batches: Vec<RecordBatch> = Dataset::open("Data/spatialite.sqlite")
+            .unwrap()
+            .get_layer_stream("polygons", &ReadOptions::default(), 1)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(2, record_batches.len());
+        assert_eq!(
+            expected_layer,
+            RecordBatch::concat(&expected_layer.schema(), &record_batches).unwrap()
+        );
+
+        let dataset = Dataset::open_with_format("Data/point.shp", Format::Shapefile).unwrap();
+        let rows: Vec<usize> = dataset
+            .get_layer_stream("point", &ReadOptions::default(), 1)
+            .map(|record_batch| record_batch.unwrap().num_rows())
+            .collect()
+            .await;
+        assert_eq!(vec![1, 1], rows);
+
+        let dataset = Dataset::open("Data/point.gpkg").unwrap();
+        let mut stream = dataset.get_layer_stream("lines", &ReadOptions::default(), 1);
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    }
+
+    /// Lets [`Gated`] read its layer.
+    static GATE: Mutex<Option<std::sync::mpsc::Receiver<()>>> = Mutex::new(None);
+
+    /// A format whose layer can only be read once [`GATE`] is opened, which
+    /// fails if it is read on the thread that has to open it.
+    struct Gated;
+
+    impl Driver for Gated {
+        fn probe(path: &Path, _header: &[u8]) -> bool {
+            path.extension()
+                .is_some_and(|extension| extension == "gated")
+        }
+
+        fn open(_path: &Path) -> anyhow::Result<Self> {
+            Ok(Gated)
+        }
+
+        fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+            Ok(vec!["gated".to_string()])
+        }
+
+        fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+            Ok(self
+                .read_layer(layer_name, &ReadOptions::default())?
+                .schema())
+        }
+
+        fn read_layer(
+            &self,
+            _layer_name: &str,
+            _options: &ReadOptions,
+        ) -> anyhow::Result<RecordBatch> {
+            GATE.lock()
+                .unwrap()
+                .take()
+                .context("The gate has already been used")?
+                .recv_timeout(Duration::from_secs(10))
+                .context("The layer was read before the gate was opened")?;
+            Ok(RecordBatch::try_from_iter(vec![(
+                "id",
+                Arc::new(Int32Array::from(vec![1, 2, 3])) as ArrayRef,
+            )])?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_layer_stream_without_blocking() {
+        use tokio_stream::StreamExt;
+
+        let path = TempPath::new("layer.gated");
+        std::fs::write(&path, "").unwrap();
+        let mut registry = DriverRegistry::empty();
+        registry.register::<Gated>("Gated");
+        let dataset = Dataset::open_with_registry(&path, &registry).unwrap();
+        let (open, gate) = std::sync::mpsc::channel();
+        *GATE.lock().unwrap() = Some(gate);
+
+        let stream = dataset.get_layer_stream("gated", &ReadOptions::default(), 2);
+        open.send(()).unwrap();
+        let rows: Vec<usize> = stream
+            .map(|record_batch| record_batch.unwrap().num_rows())
+            .collect()
+            .await;
+
+        assert_eq!(vec![2, 1], rows);
     }
 }
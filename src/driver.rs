@@ -0,0 +1,275 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+
+use crate::{
+    csv::CsvFile,
+    dataset::{Format, ReadOptions, UnsupportedFormat},
+    fgb::FgbFile,
+    geojson::GeoJsonFile,
+    geoparquet::GeoParquetFile,
+    gpkg::GeoPackage,
+    ipc::IpcFile,
+    mbtiles::MbTiles,
+    shapefile::Shapefile,
+    spatialite::Spatialite,
+};
+
+/// The number of bytes read from the start of a file for drivers to probe.
+const PROBE_LENGTH: u64 = 4096;
+
+/// A format that datasets can be read from. Implement it and add it to a
+/// [`DriverRegistry`] to read formats this crate does not know about.
+pub trait Driver {
+    /// Whether the file at `path`, which starts with `header`, is in this
+    /// driver's format.
+    fn probe(path: &Path, header: &[u8]) -> bool
+    where
+        Self: Sized;
+
+    fn open(path: &Path) -> anyhow::Result<Self>
+    where
+        Self: Sized;
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>>;
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef>;
+
+    /// Read a whole layer, applying `options`.
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch>;
+
+    /// Read a layer in record batches of up to `batch_size` rows, applying
+    /// `options`. By default the layer is read whole before this returns, and
+    /// then split.
+    fn read_layer_batches(
+        &self,
+        layer_name: &str,
+        options: &ReadOptions,
+        batch_size: usize,
+    ) -> anyhow::Result<RecordBatches> {
+        Ok(split_record_batch(
+            self.read_layer(layer_name, options)?,
+            batch_size,
+        ))
+    }
+
+    /// The WKT definition the dataset records for the CRS `authority:code`,
+    /// if any.
+    fn crs_definition(&self, _authority: &str, _code: i32) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+/// Record batches read by [`Driver::read_layer_batches`], which can be
+/// consumed on another thread.
+pub type RecordBatches = Box<dyn Iterator<Item = anyhow::Result<RecordBatch>> + Send>;
+
+/// Split `record_batch` into record batches of up to `batch_size` rows.
+pub(crate) fn split_record_batch(record_batch: RecordBatch, batch_size: usize) -> RecordBatches {
+    let num_rows = record_batch.num_rows();
+    let batch_size = batch_size.max(1);
+    Box::new(
+        (0..num_rows)
+            .step_by(batch_size)
+            .map(move |offset| Ok(record_batch.slice(offset, batch_size.min(num_rows - offset)))),
+    )
+}
+
+/// Check that a single layer file is being asked for its only layer.
+pub(crate) fn ensure_layer(path: &Path, layer_name: &str, expected: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        layer_name == expected,
+        "No layer {} in {}",
+        layer_name,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Whether the content, or failing that the extension, of a file matches
+/// `format`.
+pub(crate) fn probe_format(path: &Path, header: &[u8], format: fn(&Format) -> bool) -> bool {
+    Format::identify(path, header).as_ref().is_some_and(format)
+}
+
+type Probe = fn(&Path, &[u8]) -> bool;
+pub(crate) type Open = fn(&Path) -> anyhow::Result<Box<dyn Driver>>;
+
+fn open_driver<D: Driver + 'static>(path: &Path) -> anyhow::Result<Box<dyn Driver>> {
+    Ok(Box::new(D::open(path)?))
+}
+
+struct Registration {
+    name: String,
+    probe: Probe,
+    open: Open,
+}
+
+/// The drivers tried, in order, when opening a dataset.
+pub struct DriverRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl DriverRegistry {
+    /// A registry without any drivers.
+    pub fn empty() -> Self {
+        DriverRegistry {
+            registrations: Vec::new(),
+        }
+    }
+
+    /// Add `D` under `name`, to be probed before the drivers already
+    /// registered.
+    pub fn register<D: Driver + 'static>(&mut self, name: &str) {
+        self.registrations.insert(
+            0,
+            Registration {
+                name: name.to_string(),
+                probe: D::probe,
+                open: open_driver::<D>,
+            },
+        );
+    }
+
+    /// The names of the registered drivers, in the order they are probed.
+    pub fn names(&self) -> Vec<&str> {
+        self.registrations
+            .iter()
+            .map(|registration| registration.name.as_str())
+            .collect()
+    }
+
+    /// Open `path` with the first driver whose probe accepts it.
+    pub fn open(&self, path: &Path) -> anyhow::Result<Box<dyn Driver>> {
+        (self.find(path)?)(path)
+    }
+
+    /// The opener of the first driver whose probe accepts `path`.
+    pub(crate) fn find(&self, path: &Path) -> anyhow::Result<Open> {
+        let mut header = Vec::new();
+        File::open(path)?
+            .take(PROBE_LENGTH)
+            .read_to_end(&mut header)?;
+        let registration = self
+            .registrations
+            .iter()
+            .find(|registration| (registration.probe)(path, &header))
+            .ok_or_else(|| UnsupportedFormat {
+                path: PathBuf::from(path),
+            })?;
+        Ok(registration.open)
+    }
+
+    /// Open `path` with the driver registered as `name`.
+    pub fn open_with(&self, name: &str, path: &Path) -> anyhow::Result<Box<dyn Driver>> {
+        let registration = self
+            .registrations
+            .iter()
+            .find(|registration| registration.name == name)
+            .ok_or_else(|| anyhow::anyhow!("No driver named {}", name))?;
+        (registration.open)(path)
+    }
+}
+
+impl Default for DriverRegistry {
+    /// A registry of the drivers built into this crate.
+    fn default() -> Self {
+        let mut registry = DriverRegistry::empty();
+        registry.register::<CsvFile>("CSV");
+        registry.register::<Shapefile>("ESRI Shapefile");
+        registry.register::<GeoJsonFile>("GeoJSON");
+        registry.register::<FgbFile>("FlatGeobuf");
+        registry.register::<IpcFile>("Arrow");
+        registry.register::<GeoParquetFile>("Parquet");
+        registry.register::<Spatialite>("SQLite");
+        registry.register::<MbTiles>("MBTiles");
+        registry.register::<GeoPackage>("GPKG");
+        registry
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{geoarrow, test_util::TempPath};
+    use arrow::datatypes::Schema;
+    use std::sync::Arc;
+
+    /// A format of one `x,y` point per line, standing in for a third party
+    /// driver.
+    struct PointList {
+        path: PathBuf,
+    }
+
+    impl Driver for PointList {
+        fn probe(path: &Path, _header: &[u8]) -> bool {
+            path.extension().is_some_and(|extension| extension == "pts")
+        }
+
+        fn open(path: &Path) -> anyhow::Result<Self> {
+            Ok(PointList {
+                path: path.to_path_buf(),
+            })
+        }
+
+        fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+            Ok(vec!["points".to_string()])
+        }
+
+        fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+            Ok(self
+                .read_layer(layer_name, &ReadOptions::default())?
+                .schema())
+        }
+
+        fn read_layer(
+            &self,
+            layer_name: &str,
+            options: &ReadOptions,
+        ) -> anyhow::Result<RecordBatch> {
+            ensure_layer(&self.path, layer_name, "points")?;
+            let points = std::fs::read_to_string(&self.path)?
+                .lines()
+                .map(|line| {
+                    let (x, y) = line.split_once(',')?;
+                    Some([x.parse().ok()?, y.parse().ok()?])
+                })
+                .collect();
+            let schema = Schema::new(vec![geoarrow::geometry_field(
+                "geom",
+                geoarrow::point_data_type(),
+                &Default::default(),
+            )]);
+            let record_batch =
+                RecordBatch::try_new(Arc::new(schema), vec![geoarrow::point_array(points)])?;
+            options.apply(record_batch)
+        }
+    }
+
+    #[test]
+    fn test_register_driver() {
+        let path = TempPath::new("ogr2arrow-points.pts");
+        std::fs::write(&path, "0,0\n1,1\n2,2\n").unwrap();
+
+        let mut registry = DriverRegistry::default();
+        assert!(registry.open(&path).is_err());
+        registry.register::<PointList>("Points");
+        assert_eq!(Some(&"Points"), registry.names().first());
+
+        let driver = registry.open(&path).unwrap();
+        let options = ReadOptions {
+            bbox: Some([0.5, 0.5, 3.0, 3.0]),
+            ..Default::default()
+        };
+        assert_eq!(2, driver.read_layer("points", &options).unwrap().num_rows());
+        assert!(driver.read_layer("lines", &options).is_err());
+
+        let driver = registry.open(Path::new("Data/point.gpkg")).unwrap();
+        assert_eq!(vec!["point".to_string()], driver.list_layers().unwrap());
+        assert_eq!(3, driver.layer_schema("point").unwrap().fields().len());
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use arrow::{
+    datatypes::SchemaRef,
+    error::ArrowError,
+    ffi_stream::{export_reader_into_raw, FFI_ArrowArrayStream},
+    record_batch::{RecordBatch, RecordBatchReader},
+};
+
+use crate::dataset::Dataset;
+
+/// The `errno` returned for null or non-UTF-8 arguments.
+const EINVAL: c_int = 22;
+/// The `errno` returned when a layer cannot be read, or reading it panicked.
+const EIO: c_int = 5;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: &anyhow::Error) {
+    let message = CString::new(format!("{:#}", error).replace('\0', " "))
+        .expect("Interior nul bytes were replaced");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+}
+
+/// Run `f`, turning a panic into an error so that it does not unwind across
+/// the C boundary.
+fn catch_panic<T>(f: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        Err(anyhow::anyhow!("Panicked: {}", message))
+    })
+}
+
+/// The record batches of a layer that has been read, handed to the C Stream
+/// Interface.
+struct LayerReader {
+    schema: SchemaRef,
+    record_batches: std::vec::IntoIter<RecordBatch>,
+}
+
+impl Iterator for LayerReader {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.record_batches.next().map(Ok)
+    }
+}
+
+impl RecordBatchReader for LayerReader {
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+unsafe fn to_str<'a>(value: *const c_char, name: &str) -> anyhow::Result<&'a str> {
+    anyhow::ensure!(!value.is_null(), "{} is null", name);
+    Ok(CStr::from_ptr(value).to_str()?)
+}
+
+fn read_layer(path: &str, layer_name: Option<&str>) -> anyhow::Result<LayerReader> {
+    let dataset = Dataset::open(path)?;
+    let layer_name = match layer_name {
+        Some(layer_name) => layer_name.to_string(),
+        None => match dataset.list_layers()?.as_slice() {
+            [layer_name] => layer_name.clone(),
+            layer_names => anyhow::bail!(
+                "{} has several layers, choose one of {}",
+                path,
+                layer_names.join(", ")
+            ),
+        },
+    };
+    let record_batch = dataset.get_layer(&layer_name)?;
+    Ok(LayerReader {
+        schema: record_batch.schema(),
+        record_batches: vec![record_batch].into_iter(),
+    })
+}
+
+/// Open the dataset at `path` and export the layer `layer_name`, or its only
+/// layer if null, into `out` as an `ArrowArrayStream`. Returns 0 on success,
+/// or an `errno` code with the message available from
+/// [`ogr2arrow_last_error`]. Panics are caught and reported as `EIO`.
+///
+/// # Safety
+/// `path` and `layer_name` must be null or nul-terminated strings, and `out`
+/// must be null or point to memory for an `ArrowArrayStream`, which the
+/// caller releases.
+#[no_mangle]
+pub unsafe extern "C" fn ogr2arrow_open_layer_stream(
+    path: *const c_char,
+    layer_name: *const c_char,
+    out: *mut FFI_ArrowArrayStream,
+) -> c_int {
+    let arguments = to_str(path, "path").and_then(|path| {
+        anyhow::ensure!(!out.is_null(), "out is null");
+        let layer_name = match layer_name.is_null() {
+            true => None,
+            false => Some(to_str(layer_name, "layer_name")?),
+        };
+        Ok((path, layer_name))
+    });
+    let (path, layer_name) = match arguments {
+        Ok(arguments) => arguments,
+        Err(error) => {
+            set_last_error(&error);
+            return EINVAL;
+        }
+    };
+    match catch_panic(|| read_layer(path, layer_name)) {
+        Ok(reader) => {
+            export_reader_into_raw(Box::new(reader), out);
+            clear_last_error();
+            0
+        }
+        Err(error) => {
+            set_last_error(&error);
+            EIO
+        }
+    }
+}
+
+/// The message of the last error on this thread, or null. The string is
+/// owned by the library and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn ogr2arrow_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow::ffi_stream::ArrowArrayStreamReader;
+
+    #[test]
+    fn test_open_layer_stream() {
+        let expected_layer = Dataset::open("Data/spatialite.sqlite")
+            .unwrap()
+            .get_layer("polygons")
+            .unwrap();
+        let path = CString::new("Data/spatialite.sqlite").unwrap();
+        let layer_name = CString::new("polygons").unwrap();
+
+        let mut stream = FFI_ArrowArrayStream::empty();
+        let status =
+            unsafe { ogr2arrow_open_layer_stream(path.as_ptr(), layer_name.as_ptr(), &mut stream) };
+        assert_eq!(0, status);
+        let reader = ArrowArrayStreamReader::try_new(stream).unwrap();
+        let record_batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(1, record_batches.len());
+        assert_eq!(expected_layer.columns(), record_batches[0].columns());
+
+        let mut stream = FFI_ArrowArrayStream::empty();
+        let status =
+            unsafe { ogr2arrow_open_layer_stream(path.as_ptr(), ptr::null(), &mut stream) };
+        assert_eq!(EIO, status);
+        let message = unsafe { CStr::from_ptr(ogr2arrow_last_error()) };
+        assert!(message.to_str().unwrap().contains("point, polygons"));
+        assert_eq!(EINVAL, unsafe {
+            ogr2arrow_open_layer_stream(ptr::null(), ptr::null(), &mut stream)
+        });
+
+        let mut stream = FFI_ArrowArrayStream::empty();
+        let status =
+            unsafe { ogr2arrow_open_layer_stream(path.as_ptr(), layer_name.as_ptr(), &mut stream) };
+        assert_eq!(0, status);
+        assert!(ogr2arrow_last_error().is_null());
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        let error = catch_panic::<()>(|| panic!("boom")).unwrap_err();
+        assert_eq!("Panicked: boom", error.to_string());
+        assert_eq!(1, catch_panic(|| Ok(1)).unwrap());
+    }
+}
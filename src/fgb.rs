@@ -0,0 +1,638 @@
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use arrow::{
+    array::{
+        as_boolean_array, as_primitive_array, as_string_array, Array, ArrayBuilder, ArrayRef,
+        BinaryArray, BinaryBuilder, BooleanBuilder, Float32Builder, Float64Builder, Int16Builder,
+        Int32Builder, Int64Builder, Int8Builder, StringBuilder, UInt16Builder, UInt32Builder,
+        UInt64Builder, UInt8Builder,
+    },
+    datatypes::{
+        DataType, Field, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+        Schema, SchemaRef, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+    },
+    record_batch::RecordBatch,
+};
+use flatgeobuf::{
+    ColumnType, FallibleStreamingIterator, FeatureProperties, FgbCrs, FgbReader, FgbWriter,
+    FgbWriterOptions, GeometryType, Header,
+};
+use geozero::{
+    wkb::Wkb, ColumnValue, CoordDimensions, GeomProcessor, GeozeroGeometry, PropertyProcessor,
+    ToWkb,
+};
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver},
+    geoarrow::{self, ExtensionMetadata},
+    wkb::{WkbComponent, WkbGeometry},
+};
+
+/// The name given to the geometry column of a FlatGeobuf layer, which has no
+/// name of its own in the file.
+const GEOMETRY_COLUMN: &str = "geom";
+
+fn get_data_type(column_type: ColumnType) -> anyhow::Result<DataType> {
+    let data_type = match column_type {
+        ColumnType::Byte => DataType::Int8,
+        ColumnType::UByte => DataType::UInt8,
+        ColumnType::Bool => DataType::Boolean,
+        ColumnType::Short => DataType::Int16,
+        ColumnType::UShort => DataType::UInt16,
+        ColumnType::Int => DataType::Int32,
+        ColumnType::UInt => DataType::UInt32,
+        ColumnType::Long => DataType::Int64,
+        ColumnType::ULong => DataType::UInt64,
+        ColumnType::Float => DataType::Float32,
+        ColumnType::Double => DataType::Float64,
+        ColumnType::String | ColumnType::Json | ColumnType::DateTime => DataType::Utf8,
+        ColumnType::Binary => DataType::Binary,
+        column_type => anyhow::bail!("Unsupported FlatGeobuf column type {:?}", column_type),
+    };
+    Ok(data_type)
+}
+
+fn get_column_type(data_type: &DataType) -> anyhow::Result<ColumnType> {
+    let column_type = match data_type {
+        DataType::Int8 => ColumnType::Byte,
+        DataType::UInt8 => ColumnType::UByte,
+        DataType::Boolean => ColumnType::Bool,
+        DataType::Int16 => ColumnType::Short,
+        DataType::UInt16 => ColumnType::UShort,
+        DataType::Int32 => ColumnType::Int,
+        DataType::UInt32 => ColumnType::UInt,
+        DataType::Int64 => ColumnType::Long,
+        DataType::UInt64 => ColumnType::ULong,
+        DataType::Float32 => ColumnType::Float,
+        DataType::Float64 => ColumnType::Double,
+        DataType::Utf8 => ColumnType::String,
+        DataType::Binary => ColumnType::Binary,
+        data_type => anyhow::bail!("Unsupported column data type {:?}", data_type),
+    };
+    Ok(column_type)
+}
+
+/// Build the Arrow schema of a FlatGeobuf layer: its attribute columns
+/// followed by a WKB geometry column tagged with the header CRS.
+fn get_schema(header: &Header) -> anyhow::Result<Schema> {
+    let mut fields = Vec::new();
+    for column in header.columns().iter().flatten() {
+        let data_type = get_data_type(column.type_())?;
+        fields.push(Field::new(column.name(), data_type, column.nullable()));
+    }
+    let metadata = match header.crs() {
+        Some(crs) if crs.code() != 0 => {
+            ExtensionMetadata::from_authority_code(crs.org().unwrap_or("EPSG"), crs.code())
+        }
+        _ => ExtensionMetadata::default(),
+    };
+    fields.push(geoarrow::geometry_field(
+        GEOMETRY_COLUMN,
+        DataType::Binary,
+        &metadata,
+    ));
+    Ok(Schema::new(fields))
+}
+
+/// Appends the properties of one feature to per-column array builders.
+struct PropertyBuilders {
+    data_types: Vec<DataType>,
+    builders: Vec<Box<dyn ArrayBuilder>>,
+}
+
+macro_rules! append_value {
+    ($builders:expr, $index:expr, $builder:ty, $value:expr) => {
+        $builders[$index]
+            .as_any_mut()
+            .downcast_mut::<$builder>()
+            .context("Property does not match its column type")?
+            .append_value($value)
+    };
+}
+
+fn append_null(builder: &mut dyn ArrayBuilder, data_type: &DataType) {
+    let builder = builder.as_any_mut();
+    match data_type {
+        DataType::Int8 => builder.downcast_mut::<Int8Builder>().unwrap().append_null(),
+        DataType::UInt8 => builder
+            .downcast_mut::<UInt8Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::Boolean => builder
+            .downcast_mut::<BooleanBuilder>()
+            .unwrap()
+            .append_null(),
+        DataType::Int16 => builder
+            .downcast_mut::<Int16Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::UInt16 => builder
+            .downcast_mut::<UInt16Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::Int32 => builder
+            .downcast_mut::<Int32Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::UInt32 => builder
+            .downcast_mut::<UInt32Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::Int64 => builder
+            .downcast_mut::<Int64Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::UInt64 => builder
+            .downcast_mut::<UInt64Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::Float32 => builder
+            .downcast_mut::<Float32Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::Float64 => builder
+            .downcast_mut::<Float64Builder>()
+            .unwrap()
+            .append_null(),
+        DataType::Utf8 => builder
+            .downcast_mut::<StringBuilder>()
+            .unwrap()
+            .append_null(),
+        _ => builder
+            .downcast_mut::<BinaryBuilder>()
+            .unwrap()
+            .append_null(),
+    }
+}
+
+impl PropertyBuilders {
+    fn new(schema: &Schema) -> Self {
+        let data_types: Vec<DataType> = schema
+            .fields()
+            .iter()
+            .filter(|field| !geoarrow::is_geometry_field(field))
+            .map(|field| field.data_type().clone())
+            .collect();
+        let builders = data_types
+            .iter()
+            .map(|data_type| -> Box<dyn ArrayBuilder> {
+                match data_type {
+                    DataType::Int8 => Box::new(Int8Builder::new()),
+                    DataType::UInt8 => Box::new(UInt8Builder::new()),
+                    DataType::Boolean => Box::new(BooleanBuilder::new()),
+                    DataType::Int16 => Box::new(Int16Builder::new()),
+                    DataType::UInt16 => Box::new(UInt16Builder::new()),
+                    DataType::Int32 => Box::new(Int32Builder::new()),
+                    DataType::UInt32 => Box::new(UInt32Builder::new()),
+                    DataType::Int64 => Box::new(Int64Builder::new()),
+                    DataType::UInt64 => Box::new(UInt64Builder::new()),
+                    DataType::Float32 => Box::new(Float32Builder::new()),
+                    DataType::Float64 => Box::new(Float64Builder::new()),
+                    DataType::Utf8 => Box::new(StringBuilder::new()),
+                    _ => Box::new(BinaryBuilder::new()),
+                }
+            })
+            .collect();
+        PropertyBuilders {
+            data_types,
+            builders,
+        }
+    }
+
+    /// Pad every column that a feature left unset with a null.
+    fn finish_row(&mut self, rows: usize) {
+        for (builder, data_type) in self.builders.iter_mut().zip(&self.data_types) {
+            if builder.len() < rows {
+                append_null(builder.as_mut(), data_type);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<ArrayRef> {
+        self.builders
+            .iter_mut()
+            .map(|builder| builder.finish())
+            .collect()
+    }
+
+    fn append(&mut self, index: usize, value: &ColumnValue) -> anyhow::Result<()> {
+        let builders = &mut self.builders;
+        anyhow::ensure!(index < builders.len(), "Undeclared property {}", index);
+        match *value {
+            ColumnValue::Byte(value) => append_value!(builders, index, Int8Builder, value),
+            ColumnValue::UByte(value) => append_value!(builders, index, UInt8Builder, value),
+            ColumnValue::Bool(value) => append_value!(builders, index, BooleanBuilder, value),
+            ColumnValue::Short(value) => append_value!(builders, index, Int16Builder, value),
+            ColumnValue::UShort(value) => append_value!(builders, index, UInt16Builder, value),
+            ColumnValue::Int(value) => append_value!(builders, index, Int32Builder, value),
+            ColumnValue::UInt(value) => append_value!(builders, index, UInt32Builder, value),
+            ColumnValue::Long(value) => append_value!(builders, index, Int64Builder, value),
+            ColumnValue::ULong(value) => append_value!(builders, index, UInt64Builder, value),
+            ColumnValue::Float(value) => append_value!(builders, index, Float32Builder, value),
+            ColumnValue::Double(value) => append_value!(builders, index, Float64Builder, value),
+            ColumnValue::String(value)
+            | ColumnValue::Json(value)
+            | ColumnValue::DateTime(value) => {
+                append_value!(builders, index, StringBuilder, value)
+            }
+            ColumnValue::Binary(value) => append_value!(builders, index, BinaryBuilder, value),
+        }
+        Ok(())
+    }
+}
+
+impl PropertyProcessor for PropertyBuilders {
+    fn property(
+        &mut self,
+        index: usize,
+        _name: &str,
+        value: &ColumnValue,
+    ) -> geozero::error::Result<bool> {
+        self.append(index, value)
+            .map_err(|error| geozero::error::GeozeroError::Property(error.to_string()))?;
+        Ok(false)
+    }
+}
+
+/// A FlatGeobuf file, exposed as a single layer named after the file stem.
+pub struct FgbFile {
+    path: PathBuf,
+    schema: SchemaRef,
+    has_index: bool,
+    dimensions: CoordDimensions,
+}
+
+/// Whether a feature's geometry has no coordinates, as written for a null
+/// geometry.
+fn is_empty(geometry: &flatgeobuf::Geometry) -> bool {
+    geometry.xy().is_none_or(|xy| xy.is_empty())
+        && geometry.parts().is_none_or(|parts| parts.is_empty())
+}
+
+impl FgbFile {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let reader = FgbReader::open(BufReader::new(File::open(path)?))?;
+        let header = reader.header();
+        Ok(FgbFile {
+            path: path.to_path_buf(),
+            schema: Arc::new(get_schema(&header)?),
+            has_index: header.index_node_size() > 0 && header.features_count() > 0,
+            dimensions: CoordDimensions {
+                z: header.has_z(),
+                m: header.has_m(),
+                ..CoordDimensions::xy()
+            },
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn layer_name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Read the layer, using the packed Hilbert R-tree to only decode the
+    /// features whose envelope intersects the bbox, when the file has one.
+    pub fn read(&self, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        let reader = FgbReader::open(BufReader::new(File::open(&self.path)?))?;
+        let mut features = match options.bbox {
+            Some([min_x, min_y, max_x, max_y]) if self.has_index => {
+                reader.select_bbox(min_x, min_y, max_x, max_y)?
+            }
+            _ => reader.select_all()?,
+        };
+
+        let mut properties = PropertyBuilders::new(&self.schema);
+        let mut geometries: Vec<Option<Vec<u8>>> = Vec::new();
+        while let Some(feature) = features.next()? {
+            feature
+                .process_properties(&mut properties)
+                .context("Failed to read feature properties")?;
+            let wkb = match feature.geometry() {
+                Some(geometry) if !is_empty(&geometry) => Some(feature.to_wkb(self.dimensions)?),
+                _ => None,
+            };
+            geometries.push(wkb);
+            properties.finish_row(geometries.len());
+        }
+
+        let mut columns = properties.finish();
+        columns.push(Arc::new(BinaryArray::from_opt_vec(
+            geometries.iter().map(|wkb| wkb.as_deref()).collect(),
+        )));
+        let record_batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        options.apply(record_batch)
+    }
+}
+
+/// Options controlling how [`write_layer`] encodes a layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Write a packed Hilbert R-tree so readers can query by bbox.
+    pub spatial_index: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            spatial_index: true,
+        }
+    }
+}
+
+fn get_value(array: &dyn Array, index: usize) -> ColumnValue<'_> {
+    match array.data_type() {
+        DataType::Int8 => ColumnValue::Byte(as_primitive_array::<Int8Type>(array).value(index)),
+        DataType::UInt8 => ColumnValue::UByte(as_primitive_array::<UInt8Type>(array).value(index)),
+        DataType::Boolean => ColumnValue::Bool(as_boolean_array(array).value(index)),
+        DataType::Int16 => ColumnValue::Short(as_primitive_array::<Int16Type>(array).value(index)),
+        DataType::UInt16 => {
+            ColumnValue::UShort(as_primitive_array::<UInt16Type>(array).value(index))
+        }
+        DataType::Int32 => ColumnValue::Int(as_primitive_array::<Int32Type>(array).value(index)),
+        DataType::UInt32 => ColumnValue::UInt(as_primitive_array::<UInt32Type>(array).value(index)),
+        DataType::Int64 => ColumnValue::Long(as_primitive_array::<Int64Type>(array).value(index)),
+        DataType::UInt64 => {
+            ColumnValue::ULong(as_primitive_array::<UInt64Type>(array).value(index))
+        }
+        DataType::Float32 => {
+            ColumnValue::Float(as_primitive_array::<Float32Type>(array).value(index))
+        }
+        DataType::Float64 => {
+            ColumnValue::Double(as_primitive_array::<Float64Type>(array).value(index))
+        }
+        DataType::Utf8 => ColumnValue::String(as_string_array(array).value(index)),
+        _ => ColumnValue::Binary(
+            array
+                .as_any()
+                .downcast_ref::<BinaryArray>()
+                .unwrap()
+                .value(index),
+        ),
+    }
+}
+
+/// The geometry of a feature being written, with no coordinates for null.
+struct FeatureGeometry(Option<Vec<u8>>);
+
+impl GeozeroGeometry for FeatureGeometry {
+    fn process_geom<P: GeomProcessor>(&self, processor: &mut P) -> geozero::error::Result<()> {
+        match &self.0 {
+            Some(wkb) => Wkb(wkb).process_geom(processor),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Write the `RecordBatch`es of a layer to `writer` as FlatGeobuf, taking the
+/// geometry and CRS from the primary geometry column. The header declares Z
+/// and M if any geometry has them, and null geometries are written as
+/// features with an empty geometry, which [`FgbFile::read`] reads as null.
+pub fn write_layer<W: Write>(
+    writer: W,
+    layer_name: &str,
+    record_batches: &[RecordBatch],
+    options: &WriteOptions,
+) -> anyhow::Result<()> {
+    let schema = record_batches
+        .first()
+        .map(RecordBatch::schema)
+        .context("No record batches to write")?;
+    let geometry_column =
+        geoarrow::primary_geometry_column(&schema).context("Layer has no geometry column")?;
+    let metadata =
+        geoarrow::get_extension_metadata(schema.field(geometry_column)).unwrap_or_default();
+    // FlatGeobuf records a CRS by code, so OGC:CRS84 is written as EPSG:4326.
+    let (org, code) = match metadata.is_crs84() {
+        true => ("EPSG", 4326),
+        false => metadata.authority_code().unwrap_or(("EPSG", 0)),
+    };
+    let geometries = record_batches
+        .iter()
+        .map(|record_batch| {
+            let array = record_batch.column(geometry_column);
+            (0..array.len())
+                .map(|row| geoarrow::get_geometry(array.as_ref(), row))
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let dimensions: Vec<_> = geometries
+        .iter()
+        .flatten()
+        .flatten()
+        .map(WkbGeometry::dimension)
+        .collect();
+
+    let mut fgb = FgbWriter::create_with_options(
+        layer_name,
+        GeometryType::Unknown,
+        FgbWriterOptions {
+            write_index: options.spatial_index,
+            detect_type: true,
+            promote_to_multi: false,
+            crs: FgbCrs {
+                org: Some(org),
+                code,
+                ..Default::default()
+            },
+            has_z: dimensions.iter().any(|dimension| dimension.has_z()),
+            has_m: dimensions.iter().any(|dimension| dimension.has_m()),
+            ..Default::default()
+        },
+    )?;
+    let columns: Vec<usize> = (0..schema.fields().len())
+        .filter(|&index| !geoarrow::is_geometry_field(schema.field(index)))
+        .collect();
+    for &index in &columns {
+        let field = schema.field(index);
+        fgb.add_column(
+            field.name(),
+            get_column_type(field.data_type())?,
+            |_, column| column.nullable = field.is_nullable(),
+        );
+    }
+
+    for (record_batch, geometries) in record_batches.iter().zip(&geometries) {
+        for (row, geometry) in geometries.iter().enumerate() {
+            // Go through `add_feature_geom`, as only its feature writer knows
+            // the header's dimensions and so keeps Z and M.
+            let geometry = FeatureGeometry(geometry.as_ref().map(WkbGeometry::to_wkb));
+            let mut result = Ok(());
+            fgb.add_feature_geom(geometry, |feature| {
+                result = columns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &index)| !record_batch.column(index).is_null(row))
+                    .try_for_each(|(property_index, &index)| {
+                        feature
+                            .property(
+                                property_index,
+                                schema.field(index).name(),
+                                &get_value(record_batch.column(index).as_ref(), row),
+                            )
+                            .map(|_| ())
+                    });
+            })?;
+            result?;
+        }
+    }
+    fgb.write(writer)?;
+    Ok(())
+}
+
+impl Driver for FgbFile {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| *format == Format::FlatGeobuf)
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        FgbFile::open(path)
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(vec![self.layer_name()])
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        Ok(self.schema())
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        self.read(options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        dataset::Dataset,
+        test_util::{point_layer, TempPath},
+    };
+
+    fn write_point_layer(file_name: &str, options: &WriteOptions) -> TempPath {
+        let layer = point_layer();
+        let path = TempPath::new(file_name);
+        let file = File::create(&path).unwrap();
+        write_layer(file, "point", std::slice::from_ref(&layer), options).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_layer() {
+        let path = write_point_layer("ogr2arrow-point.fgb", &WriteOptions::default());
+
+        let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+        let layer = dataset.get_layer("ogr2arrow-point").unwrap();
+
+        let schema = layer.schema();
+        let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(vec!["fid", "name", "geom"], names);
+        assert_eq!(&DataType::Int64, schema.field(0).data_type());
+        assert_eq!(
+            Some(("EPSG", 27700)),
+            geoarrow::get_extension_metadata(schema.field(2))
+                .unwrap()
+                .authority_code()
+        );
+        assert_eq!(2, layer.num_rows());
+        let envelopes: Vec<[f64; 4]> = (0..2)
+            .map(|row| {
+                geoarrow::get_geometry(layer.column(2).as_ref(), row)
+                    .unwrap()
+                    .unwrap()
+                    .envelope()
+                    .unwrap()
+            })
+            .collect();
+        assert!(envelopes.contains(&[0.0, 0.0, 0.0, 0.0]));
+        assert!(envelopes.contains(&[1.0, 1.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn test_write_z_and_null_geometries() {
+        let wkb: Vec<Option<Vec<u8>>> = vec![
+            Some(WkbGeometry::from_wkt("POINT Z (1 2 3)").unwrap().to_wkb()),
+            None,
+            Some(WkbGeometry::from_wkt("POINT Z (4 5 6)").unwrap().to_wkb()),
+        ];
+        let geometry = BinaryArray::from_opt_vec(wkb.iter().map(|wkb| wkb.as_deref()).collect());
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            geoarrow::geometry_field("geom", DataType::Binary, &ExtensionMetadata::default()),
+        ]);
+        let ids = arrow::array::Int64Array::from(vec![1, 2, 3]);
+        let record_batch =
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(ids), Arc::new(geometry)])
+                .unwrap();
+
+        for spatial_index in [true, false] {
+            let path = TempPath::new("ogr2arrow-z.fgb");
+            let file = File::create(&path).unwrap();
+            let options = WriteOptions { spatial_index };
+            write_layer(file, "z", std::slice::from_ref(&record_batch), &options).unwrap();
+
+            let layer = FgbFile::open(&path)
+                .unwrap()
+                .read(&ReadOptions::default())
+                .unwrap();
+            let mut recieved_wkt: Vec<Option<String>> = (0..layer.num_rows())
+                .map(|row| {
+                    geoarrow::get_geometry(layer.column(1).as_ref(), row)
+                        .unwrap()
+                        .map(|geometry| geometry.to_wkt())
+                })
+                .collect();
+            recieved_wkt.sort();
+            assert_eq!(
+                vec![
+                    None,
+                    Some("POINT Z (1 2 3)".to_string()),
+                    Some("POINT Z (4 5 6)".to_string())
+                ],
+                recieved_wkt
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_bbox() {
+        for spatial_index in [true, false] {
+            let file_name = format!("ogr2arrow-point-bbox-{}.fgb", spatial_index);
+            let path = write_point_layer(&file_name, &WriteOptions { spatial_index });
+
+            let file = FgbFile::open(&path).unwrap();
+            assert_eq!(spatial_index, file.has_index);
+            let options = ReadOptions {
+                bbox: Some([0.5, 0.5, 2.0, 2.0]),
+                columns: Some(vec!["fid".to_string()]),
+                ..Default::default()
+            };
+            let layer = file.read(&options).unwrap();
+
+            assert_eq!(1, layer.num_columns());
+            assert_eq!(
+                &[2],
+                as_primitive_array::<Int64Type>(layer.column(0)).values()
+            );
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Context;
+use arrow::{
+    array::{
+        as_primitive_array, as_struct_array, make_array, Array, ArrayData, ArrayRef, BinaryArray,
+        BooleanArray, BooleanBufferBuilder, FixedSizeListArray, Float64Array, StringArray,
+    },
+    compute::filter_record_batch,
+    datatypes::{DataType, Field, Float64Type, Schema},
+    record_batch::RecordBatch,
+};
+use binread::{io::Cursor, BinReaderExt};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    wkb::{WkbGeometry, WkbPoint},
+    wkt,
+};
+
+pub const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+pub const EXTENSION_METADATA_KEY: &str = "ARROW:extension:metadata";
+
+pub const POINT: &str = "geoarrow.point";
+pub const WKB: &str = "geoarrow.wkb";
+pub const WKT: &str = "geoarrow.wkt";
+
+/// The `ARROW:extension:metadata` of a GeoArrow field.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExtensionMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crs_type: Option<String>,
+}
+
+impl ExtensionMetadata {
+    /// Metadata for a CRS identified by an authority and code, e.g. `EPSG:27700`.
+    pub fn from_authority_code(authority: &str, code: i32) -> Self {
+        ExtensionMetadata {
+            crs: Some(format!("{}:{}", authority, code)),
+            crs_type: Some("authority_code".to_string()),
+        }
+    }
+
+    /// Metadata for OGC:CRS84, WGS 84 with longitude before latitude, the
+    /// CRS of GeoJSON.
+    pub fn crs84() -> Self {
+        ExtensionMetadata {
+            crs: Some("OGC:CRS84".to_string()),
+            crs_type: Some("authority_code".to_string()),
+        }
+    }
+
+    /// Whether the CRS is OGC:CRS84.
+    pub fn is_crs84(&self) -> bool {
+        self.crs_type.as_deref() == Some("authority_code")
+            && self.crs.as_deref() == Some("OGC:CRS84")
+    }
+
+    /// The `(authority, code)` pair of an `authority_code` CRS.
+    pub fn authority_code(&self) -> Option<(&str, i32)> {
+        if self.crs_type.as_deref() != Some("authority_code") {
+            return None;
+        }
+        let (authority, code) = self.crs.as_deref()?.split_once(':')?;
+        Some((authority, code.parse().ok()?))
+    }
+}
+
+/// The GeoArrow extension name matching the storage type of a geometry column.
+pub fn extension_name(data_type: &DataType) -> Option<&'static str> {
+    match data_type {
+        DataType::FixedSizeList(_field, 2) => Some(POINT),
+        DataType::Struct(fields) if fields.len() == 2 => Some(POINT),
+        DataType::Binary => Some(WKB),
+        DataType::Utf8 => Some(WKT),
+        _ => None,
+    }
+}
+
+/// The interleaved `FixedSizeList<Float64, 2>` type used for point columns,
+/// with the `xy` child GeoArrow names it.
+pub fn point_data_type() -> DataType {
+    DataType::FixedSizeList(Box::new(Field::new("xy", DataType::Float64, true)), 2)
+}
+
+/// Build a point column, with a null for every missing point.
+pub fn point_array(points: Vec<Option<[f64; 2]>>) -> ArrayRef {
+    let mut validity = BooleanBufferBuilder::new(points.len());
+    let mut values = Vec::with_capacity(points.len() * 2);
+    for point in &points {
+        validity.append(point.is_some());
+        values.extend(point.unwrap_or([f64::NAN, f64::NAN]));
+    }
+    let list_data = ArrayData::builder(point_data_type())
+        .len(points.len())
+        .add_child_data(Float64Array::from(values).into_data())
+        .null_bit_buffer(Some(validity.finish()))
+        .build()
+        .unwrap();
+    make_array(list_data)
+}
+
+/// Build a nullable geometry field tagged with its GeoArrow extension type.
+pub fn geometry_field(name: &str, data_type: DataType, metadata: &ExtensionMetadata) -> Field {
+    let mut field_metadata = BTreeMap::new();
+    if let Some(extension_name) = extension_name(&data_type) {
+        field_metadata.insert(EXTENSION_NAME_KEY.to_string(), extension_name.to_string());
+        field_metadata.insert(
+            EXTENSION_METADATA_KEY.to_string(),
+            serde_json::to_string(metadata).unwrap(),
+        );
+    }
+    Field::new(name, data_type, true).with_metadata(Some(field_metadata))
+}
+
+/// Whether `field` carries a `geoarrow.*` extension name.
+pub fn is_geometry_field(field: &Field) -> bool {
+    field
+        .metadata()
+        .as_ref()
+        .and_then(|metadata| metadata.get(EXTENSION_NAME_KEY))
+        .is_some_and(|name| name.starts_with("geoarrow."))
+}
+
+/// The parsed extension metadata of a geometry field, if it has any.
+pub fn get_extension_metadata(field: &Field) -> Option<ExtensionMetadata> {
+    field
+        .metadata()
+        .as_ref()
+        .and_then(|metadata| metadata.get(EXTENSION_METADATA_KEY))
+        .and_then(|metadata| serde_json::from_str(metadata).ok())
+}
+
+/// The index of the first geometry field of `schema`.
+pub fn primary_geometry_column(schema: &Schema) -> Option<usize> {
+    schema.fields().iter().position(is_geometry_field)
+}
+
+/// Whether two `[min_x, min_y, max_x, max_y]` boxes intersect.
+pub fn intersects(a: &[f64; 4], b: &[f64; 4]) -> bool {
+    a[0] <= b[2] && a[2] >= b[0] && a[1] <= b[3] && a[3] >= b[1]
+}
+
+/// Keep the rows of `record_batch` whose geometry in `column` has an
+/// envelope intersecting `bbox`.
+pub fn filter_bbox(
+    record_batch: &RecordBatch,
+    column: usize,
+    bbox: &[f64; 4],
+) -> anyhow::Result<RecordBatch> {
+    let array = record_batch.column(column);
+    let mut predicate = Vec::with_capacity(array.len());
+    for index in 0..array.len() {
+        let envelope =
+            get_geometry(array.as_ref(), index)?.and_then(|geometry| geometry.envelope());
+        predicate.push(envelope.is_some_and(|envelope| intersects(&envelope, bbox)));
+    }
+    let filtered = filter_record_batch(record_batch, &BooleanArray::from(predicate))?;
+    Ok(filtered)
+}
+
+/// The `[min_x, min_y, max_x, max_y]` bounds of every geometry in a
+/// geometry array, or `None` if they are all null or empty.
+pub fn total_bounds(array: &dyn Array) -> anyhow::Result<Option<[f64; 4]>> {
+    let mut bounds: Option<[f64; 4]> = None;
+    for index in 0..array.len() {
+        if let Some(envelope) = get_geometry(array, index)?.and_then(|geometry| geometry.envelope())
+        {
+            let bounds = bounds.get_or_insert(envelope);
+            bounds[0] = bounds[0].min(envelope[0]);
+            bounds[1] = bounds[1].min(envelope[1]);
+            bounds[2] = bounds[2].max(envelope[2]);
+            bounds[3] = bounds[3].max(envelope[3]);
+        }
+    }
+    Ok(bounds)
+}
+
+/// Decode the geometry at `index` of a point (interleaved or separated), WKB
+/// or WKT array.
+pub fn get_geometry(array: &dyn Array, index: usize) -> anyhow::Result<Option<WkbGeometry>> {
+    if array.is_null(index) {
+        return Ok(None);
+    }
+    let geometry = match array.data_type() {
+        DataType::FixedSizeList(_field, 2) => {
+            let points = array
+                .as_any()
+                .downcast_ref::<FixedSizeListArray>()
+                .unwrap()
+                .value(index);
+            let coordinates = as_primitive_array::<Float64Type>(&points);
+            let point = WkbPoint::try_from([coordinates.value(0), coordinates.value(1)]).unwrap();
+            WkbGeometry::Point(point)
+        }
+        DataType::Struct(fields) if fields.len() == 2 => {
+            let points = as_struct_array(array);
+            let x = as_primitive_array::<Float64Type>(points.column(0)).value(index);
+            let y = as_primitive_array::<Float64Type>(points.column(1)).value(index);
+            WkbGeometry::Point(WkbPoint::try_from([x, y]).unwrap())
+        }
+        DataType::Binary => {
+            let wkb = array
+                .as_any()
+                .downcast_ref::<BinaryArray>()
+                .unwrap()
+                .value(index);
+            Cursor::new(wkb)
+                .read_ne()
+                .context("Failed to parse WKB geometry")?
+        }
+        DataType::Utf8 => {
+            let wkt = array
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .value(index);
+            wkt::parse_wkt(wkt)?
+        }
+        data_type => anyhow::bail!("Unsupported geometry data type {:?}", data_type),
+    };
+    Ok(Some(geometry))
+}
+
+/// Replace every geometry column of `record_batch` with `encode` of it, of
+/// type `data_type`.
+fn encode_geometries(
+    record_batch: &RecordBatch,
+    data_type: DataType,
+    encode: impl Fn(&dyn Array) -> anyhow::Result<ArrayRef>,
+) -> anyhow::Result<RecordBatch> {
+    let schema = record_batch.schema();
+    let mut fields = schema.fields().clone();
+    let mut columns = record_batch.columns().to_vec();
+    for (index, field) in schema.fields().iter().enumerate() {
+        if !is_geometry_field(field) {
+            continue;
+        }
+        let metadata = get_extension_metadata(field).unwrap_or_default();
+        fields[index] = geometry_field(field.name(), data_type.clone(), &metadata);
+        columns[index] = encode(record_batch.column(index).as_ref())?;
+    }
+    let schema = Schema::new_with_metadata(fields, schema.metadata().clone());
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+/// Re-encode every geometry column of `record_batch` as `geoarrow.wkt`,
+/// rounding coordinates to `precision` decimal places if given.
+pub fn geometries_to_wkt(
+    record_batch: &RecordBatch,
+    precision: Option<usize>,
+) -> anyhow::Result<RecordBatch> {
+    encode_geometries(record_batch, DataType::Utf8, |array| {
+        let wkts = (0..array.len())
+            .map(|row| {
+                let geometry = get_geometry(array, row)?;
+                Ok(geometry.map(|geometry| wkt::write_wkt(&geometry, precision)))
+            })
+            .collect::<anyhow::Result<StringArray>>()?;
+        Ok(Arc::new(wkts))
+    })
+}
+
+/// Re-encode every geometry column of `record_batch` as `geoarrow.wkb`.
+pub fn geometries_to_wkb(record_batch: &RecordBatch) -> anyhow::Result<RecordBatch> {
+    encode_geometries(record_batch, DataType::Binary, |array| {
+        let wkbs = (0..array.len())
+            .map(|row| Ok(get_geometry(array, row)?.map(|geometry| geometry.to_wkb())))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Arc::new(BinaryArray::from_opt_vec(
+            wkbs.iter().map(|wkb| wkb.as_deref()).collect(),
+        )))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_geometry_field() {
+        let metadata = ExtensionMetadata::from_authority_code("EPSG", 27700);
+        let field = geometry_field("geom", DataType::Binary, &metadata);
+
+        assert!(is_geometry_field(&field));
+        assert_eq!(Some(metadata), get_extension_metadata(&field));
+        assert_eq!(
+            Some(("EPSG", 27700)),
+            get_extension_metadata(&field).unwrap().authority_code()
+        );
+    }
+
+    #[test]
+    fn test_total_bounds() {
+        let points = point_array(vec![Some([1.0, 5.0]), None, Some([-2.0, 3.0])]);
+        assert_eq!(
+            Some([-2.0, 3.0, 1.0, 5.0]),
+            total_bounds(points.as_ref()).unwrap()
+        );
+        assert_eq!(
+            None,
+            total_bounds(point_array(vec![None]).as_ref()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_geometries_to_wkt() {
+        let metadata = ExtensionMetadata::from_authority_code("EPSG", 27700);
+        let polygon = wkt::parse_wkt("POLYGON Z ((0 0 1, 1 0 1, 0 1 1, 0 0 1))")
+            .unwrap()
+            .to_wkb();
+        let schema = Schema::new(vec![
+            geometry_field("point", point_data_type(), &metadata),
+            geometry_field("polygon", DataType::Binary, &metadata),
+        ]);
+        let record_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                point_array(vec![Some([0.123456, 1.0]), None]),
+                Arc::new(BinaryArray::from_opt_vec(vec![Some(&polygon), None])),
+            ],
+        )
+        .unwrap();
+
+        let record_batch = geometries_to_wkt(&record_batch, Some(3)).unwrap();
+
+        let schema = record_batch.schema();
+        assert_eq!(
+            Some(&WKT.to_string()),
+            schema
+                .field(1)
+                .metadata()
+                .as_ref()
+                .unwrap()
+                .get(EXTENSION_NAME_KEY)
+        );
+        assert_eq!(Some(metadata), get_extension_metadata(schema.field(0)));
+        let points = record_batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!("POINT (0.123 1)", points.value(0));
+        assert!(points.is_null(1));
+        assert_eq!(
+            Some([0.0, 0.0, 1.0, 1.0]),
+            get_geometry(record_batch.column(1).as_ref(), 0)
+                .unwrap()
+                .unwrap()
+                .envelope()
+        );
+    }
+
+    #[test]
+    fn test_geometries_to_wkb() {
+        let schema = Schema::new(vec![geometry_field(
+            "point",
+            point_data_type(),
+            &ExtensionMetadata::default(),
+        )]);
+        let record_batch = RecordBatch::try_new(
+            Arc::new(schema),
+            vec![point_array(vec![Some([1.0, 2.0]), None])],
+        )
+        .unwrap();
+
+        let record_batch = geometries_to_wkb(&record_batch).unwrap();
+
+        assert_eq!(
+            Some(WKB),
+            extension_name(record_batch.schema().field(0).data_type())
+        );
+        let points = record_batch.column(0);
+        assert_eq!(
+            Some("POINT (1 2)".to_string()),
+            get_geometry(points.as_ref(), 0)
+                .unwrap()
+                .map(|geometry| geometry.to_wkt())
+        );
+        assert!(points.is_null(1));
+    }
+}
@@ -0,0 +1,597 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use arrow::{
+    array::{ArrayRef, BinaryArray},
+    datatypes::{DataType, Schema, SchemaRef},
+    json::{
+        reader::{infer_json_schema_from_iterator, Decoder, DecoderOptions},
+        writer::record_batches_to_json_rows,
+    },
+    record_batch::RecordBatch,
+};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver},
+    geoarrow::{self, ExtensionMetadata},
+    wkb::{
+        Coordinate, LinearRing, WkbGeometry, WkbGeometryCollection, WkbLineString,
+        WkbMultiLineString, WkbMultiPoint, WkbMultiPolygon, WkbPoint, WkbPolygon,
+    },
+};
+
+/// The name given to the geometry column of a GeoJSON layer.
+const GEOMETRY_COLUMN: &str = "geometry";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoJsonFormat {
+    /// A single `FeatureCollection`, `.geojson` or `.json`.
+    FeatureCollection,
+    /// Newline-delimited features, `.geojsonl` or `.geojsons`, optionally
+    /// prefixed with the RFC 8142 record separator.
+    Sequence,
+}
+
+/// Options controlling how [`write_feature_collection`] and
+/// [`write_sequence`] encode a layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Round coordinates to this many decimal places. RFC 7946 suggests 6,
+    /// about 10cm in WGS 84.
+    pub coordinate_precision: Option<u32>,
+    /// Write a `bbox` member on every feature and on the collection.
+    pub bbox: bool,
+}
+
+impl WriteOptions {
+    fn round(&self, value: f64) -> f64 {
+        match self.coordinate_precision {
+            Some(precision) => {
+                let factor = 10f64.powi(precision as i32);
+                (value * factor).round() / factor
+            }
+            None => value,
+        }
+    }
+
+    fn coordinate(&self, coordinate: &Coordinate) -> Value {
+        [Some(coordinate.x), Some(coordinate.y), coordinate.z]
+            .into_iter()
+            .flatten()
+            .map(|value| json!(self.round(value)))
+            .collect()
+    }
+
+    fn coordinates(&self, coordinates: &[Coordinate]) -> Value {
+        coordinates
+            .iter()
+            .map(|coordinate| self.coordinate(coordinate))
+            .collect()
+    }
+
+    fn rings(&self, rings: &[LinearRing]) -> Value {
+        rings
+            .iter()
+            .map(|ring| self.coordinates(ring.coordinates()))
+            .collect()
+    }
+
+    fn polygons(&self, polygons: &[WkbPolygon]) -> Value {
+        polygons
+            .iter()
+            .map(|polygon| self.rings(&polygon.rings))
+            .collect()
+    }
+
+    fn bbox(&self, envelope: &[f64; 4]) -> Value {
+        envelope.iter().map(|value| self.round(*value)).collect()
+    }
+}
+
+/// Convert a geometry to a GeoJSON geometry object. Triangles, TINs and
+/// polyhedral surfaces, which GeoJSON lacks, become (multi)polygons.
+pub fn geometry_to_json(geometry: &WkbGeometry, options: &WriteOptions) -> Value {
+    let (geometry_type, coordinates) = match geometry {
+        WkbGeometry::Point(point) if point.point.x.is_nan() => ("Point", json!([])),
+        WkbGeometry::Point(point) => ("Point", options.coordinate(&point.point)),
+        WkbGeometry::LineString(line_string) => {
+            ("LineString", options.coordinates(&line_string.points))
+        }
+        WkbGeometry::Polygon(polygon) => ("Polygon", options.rings(&polygon.rings)),
+        WkbGeometry::Triangle(triangle) => ("Polygon", options.rings(&triangle.rings)),
+        WkbGeometry::MultiPoint(multi_point) => (
+            "MultiPoint",
+            multi_point
+                .points
+                .iter()
+                .map(|point| options.coordinate(&point.point))
+                .collect(),
+        ),
+        WkbGeometry::MultiLineString(multi_line_string) => (
+            "MultiLineString",
+            multi_line_string
+                .line_strings
+                .iter()
+                .map(|line_string| options.coordinates(&line_string.points))
+                .collect(),
+        ),
+        WkbGeometry::MultiPolygon(multi_polygon) => {
+            ("MultiPolygon", options.polygons(&multi_polygon.polygons))
+        }
+        WkbGeometry::PolyhedralSurface(surface) => {
+            ("MultiPolygon", options.polygons(&surface.polygons))
+        }
+        WkbGeometry::Tin(tin) => ("MultiPolygon", options.polygons(&tin.polygons)),
+        WkbGeometry::GeometryCollection(collection) => {
+            let geometries: Vec<Value> = collection
+                .geometries
+                .iter()
+                .map(|geometry| geometry_to_json(geometry, options))
+                .collect();
+            return json!({"type": "GeometryCollection", "geometries": geometries});
+        }
+    };
+    json!({"type": geometry_type, "coordinates": coordinates})
+}
+
+fn parse_coordinate(value: &Value) -> anyhow::Result<Coordinate> {
+    let position = value.as_array().context("Position is not an array")?;
+    anyhow::ensure!(position.len() >= 2, "Position has fewer than 2 elements");
+    let x = position[0].as_f64().context("Position is not numeric")?;
+    let y = position[1].as_f64().context("Position is not numeric")?;
+    let z = position.get(2).and_then(Value::as_f64);
+    Ok(Coordinate {
+        z,
+        ..Coordinate::new(x, y)
+    })
+}
+
+fn parse_array<T>(
+    value: &Value,
+    parse: impl Fn(&Value) -> anyhow::Result<T>,
+) -> anyhow::Result<Vec<T>> {
+    value
+        .as_array()
+        .context("Coordinates are not an array")?
+        .iter()
+        .map(parse)
+        .collect()
+}
+
+fn parse_point(value: &Value) -> anyhow::Result<WkbPoint> {
+    let point = match value.as_array() {
+        Some(position) if position.is_empty() => Coordinate::new(f64::NAN, f64::NAN),
+        _ => parse_coordinate(value)?,
+    };
+    Ok(WkbPoint::new(point))
+}
+
+fn parse_line_string(value: &Value) -> anyhow::Result<WkbLineString> {
+    Ok(WkbLineString::new(parse_array(value, parse_coordinate)?))
+}
+
+fn parse_polygon(value: &Value) -> anyhow::Result<WkbPolygon> {
+    let rings = parse_array(value, |ring| {
+        Ok(LinearRing::new(parse_array(ring, parse_coordinate)?))
+    })?;
+    Ok(WkbPolygon::new(rings))
+}
+
+/// Convert a GeoJSON geometry object to a geometry.
+pub fn geometry_from_json(value: &Value) -> anyhow::Result<WkbGeometry> {
+    let geometry_type = value
+        .get("type")
+        .and_then(Value::as_str)
+        .context("Geometry has no type")?;
+    if geometry_type == "GeometryCollection" {
+        let geometries = parse_array(
+            value
+                .get("geometries")
+                .context("Collection has no geometries")?,
+            geometry_from_json,
+        )?;
+        return Ok(WkbGeometry::GeometryCollection(WkbGeometryCollection::new(
+            geometries,
+        )));
+    }
+    let coordinates = value
+        .get("coordinates")
+        .context("Geometry has no coordinates")?;
+    let geometry = match geometry_type {
+        "Point" => WkbGeometry::Point(parse_point(coordinates)?),
+        "LineString" => WkbGeometry::LineString(parse_line_string(coordinates)?),
+        "Polygon" => WkbGeometry::Polygon(parse_polygon(coordinates)?),
+        "MultiPoint" => {
+            WkbGeometry::MultiPoint(WkbMultiPoint::new(parse_array(coordinates, parse_point)?))
+        }
+        "MultiLineString" => WkbGeometry::MultiLineString(WkbMultiLineString::new(parse_array(
+            coordinates,
+            parse_line_string,
+        )?)),
+        "MultiPolygon" => WkbGeometry::MultiPolygon(WkbMultiPolygon::new(parse_array(
+            coordinates,
+            parse_polygon,
+        )?)),
+        geometry_type => anyhow::bail!("Unsupported GeoJSON geometry type {}", geometry_type),
+    };
+    Ok(geometry)
+}
+
+fn merge_envelopes(a: Option<[f64; 4]>, b: Option<[f64; 4]>) -> Option<[f64; 4]> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some([
+            a[0].min(b[0]),
+            a[1].min(b[1]),
+            a[2].max(b[2]),
+            a[3].max(b[3]),
+        ]),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Convert the rows of a layer to GeoJSON features, taking the geometry from
+/// the primary geometry column and the properties from every other column.
+/// Also returns the envelope of all the features.
+fn get_features(
+    record_batches: &[RecordBatch],
+    options: &WriteOptions,
+) -> anyhow::Result<(Vec<Value>, Option<[f64; 4]>)> {
+    let mut features = Vec::new();
+    let mut envelope = None;
+    for record_batch in record_batches {
+        let schema = record_batch.schema();
+        let geometry_column = geoarrow::primary_geometry_column(&schema);
+        let property_columns: Vec<usize> = (0..schema.fields().len())
+            .filter(|&index| !geoarrow::is_geometry_field(schema.field(index)))
+            .collect();
+        let properties = record_batches_to_json_rows(&[record_batch.project(&property_columns)?])?;
+
+        for (row, properties) in properties.into_iter().enumerate() {
+            let geometry = match geometry_column {
+                Some(column) => geoarrow::get_geometry(record_batch.column(column).as_ref(), row)?,
+                None => None,
+            };
+            let mut feature = Map::new();
+            feature.insert("type".to_string(), json!("Feature"));
+            if options.bbox {
+                if let Some(feature_envelope) = geometry.as_ref().and_then(WkbGeometry::envelope) {
+                    feature.insert("bbox".to_string(), options.bbox(&feature_envelope));
+                    envelope = merge_envelopes(envelope, Some(feature_envelope));
+                }
+            }
+            feature.insert("properties".to_string(), Value::Object(properties));
+            feature.insert(
+                "geometry".to_string(),
+                geometry.map_or(Value::Null, |geometry| geometry_to_json(&geometry, options)),
+            );
+            features.push(Value::Object(feature));
+        }
+    }
+    Ok((features, envelope))
+}
+
+/// Write the `RecordBatch`es of a layer to `writer` as a GeoJSON
+/// `FeatureCollection`.
+pub fn write_feature_collection<W: Write>(
+    writer: W,
+    record_batches: &[RecordBatch],
+    options: &WriteOptions,
+) -> anyhow::Result<()> {
+    let (features, envelope) = get_features(record_batches, options)?;
+    let mut feature_collection = Map::new();
+    feature_collection.insert("type".to_string(), json!("FeatureCollection"));
+    if let Some(envelope) = envelope {
+        feature_collection.insert("bbox".to_string(), options.bbox(&envelope));
+    }
+    feature_collection.insert("features".to_string(), Value::Array(features));
+    serde_json::to_writer(writer, &feature_collection)?;
+    Ok(())
+}
+
+/// Write the `RecordBatch`es of a layer to `writer` as newline-delimited
+/// GeoJSON features.
+pub fn write_sequence<W: Write>(
+    mut writer: W,
+    record_batches: &[RecordBatch],
+    options: &WriteOptions,
+) -> anyhow::Result<()> {
+    let (features, _envelope) = get_features(record_batches, options)?;
+    for feature in features {
+        serde_json::to_writer(&mut writer, &feature)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn read_features(path: &Path, format: GeoJsonFormat) -> anyhow::Result<Vec<Value>> {
+    let reader = BufReader::new(File::open(path)?);
+    let features = match format {
+        GeoJsonFormat::FeatureCollection => {
+            let mut value: Value = serde_json::from_reader(reader)?;
+            match value.get("type").and_then(Value::as_str) {
+                Some("FeatureCollection") => match value.get_mut("features").map(Value::take) {
+                    Some(Value::Array(features)) => features,
+                    _ => anyhow::bail!("FeatureCollection has no features"),
+                },
+                Some("Feature") => vec![value],
+                _ => anyhow::bail!("Not a GeoJSON FeatureCollection"),
+            }
+        }
+        GeoJsonFormat::Sequence => {
+            let mut features = Vec::new();
+            for line in reader.lines() {
+                let line = line?;
+                let line = line.trim_start_matches('\u{1e}').trim();
+                if !line.is_empty() {
+                    features.push(serde_json::from_str(line)?);
+                }
+            }
+            features
+        }
+    };
+    Ok(features)
+}
+
+fn get_properties(feature: &Value) -> Value {
+    match feature.get("properties") {
+        Some(properties @ Value::Object(_)) => properties.clone(),
+        _ => Value::Object(Map::new()),
+    }
+}
+
+/// Infer the schema of JSON objects, keeping the columns in the order they
+/// first appear in.
+pub(crate) fn infer_objects_schema(objects: &[Value]) -> anyhow::Result<Schema> {
+    let schema = infer_json_schema_from_iterator(objects.iter().cloned().map(Ok))?;
+    let mut names: Vec<String> = Vec::new();
+    for object in objects {
+        if let Value::Object(object) = object {
+            for name in object.keys() {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+    }
+    let fields = names
+        .iter()
+        .map(|name| schema.field_with_name(name).cloned())
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Schema::new(fields))
+}
+
+/// Decode JSON objects into a column per field of `schema`.
+pub(crate) fn decode_objects(
+    schema: SchemaRef,
+    objects: &[Value],
+) -> anyhow::Result<Vec<ArrayRef>> {
+    if schema.fields().is_empty() {
+        return Ok(Vec::new());
+    }
+    let decoder = Decoder::new(
+        schema.clone(),
+        DecoderOptions::new().with_batch_size(objects.len().max(1)),
+    );
+    let record_batch = decoder
+        .next_batch(&mut objects.iter().cloned().map(Ok))?
+        .unwrap_or_else(|| RecordBatch::new_empty(schema));
+    Ok(record_batch.columns().to_vec())
+}
+
+/// Infer the schema of the feature properties.
+fn infer_properties_schema(features: &[Value]) -> anyhow::Result<Schema> {
+    let properties: Vec<Value> = features.iter().map(get_properties).collect();
+    infer_objects_schema(&properties)
+}
+
+/// A GeoJSON file, exposed as a single layer named after the file stem. The
+/// features are parsed once, when the file is opened, and kept until it is
+/// dropped.
+pub struct GeoJsonFile {
+    path: PathBuf,
+    schema: SchemaRef,
+    features: Vec<Value>,
+}
+
+impl GeoJsonFile {
+    pub fn open(path: &Path, format: GeoJsonFormat) -> anyhow::Result<Self> {
+        let features = read_features(path, format)?;
+        let mut fields = infer_properties_schema(&features)?.fields().clone();
+        fields.push(geoarrow::geometry_field(
+            GEOMETRY_COLUMN,
+            DataType::Binary,
+            &ExtensionMetadata::crs84(),
+        ));
+        Ok(GeoJsonFile {
+            path: path.to_path_buf(),
+            schema: Arc::new(Schema::new(fields)),
+            features,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn layer_name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn read(&self, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        let features = &self.features;
+        let num_properties = self.schema.fields().len() - 1;
+
+        let properties_schema =
+            Arc::new(Schema::new(self.schema.fields()[..num_properties].to_vec()));
+        let properties: Vec<Value> = features.iter().map(get_properties).collect();
+        let mut columns = decode_objects(properties_schema, &properties)?;
+
+        let geometries = features
+            .iter()
+            .map(|feature| match feature.get("geometry") {
+                Some(Value::Null) | None => Ok(None),
+                Some(geometry) => Ok(Some(geometry_from_json(geometry)?.to_wkb())),
+            })
+            .collect::<anyhow::Result<Vec<Option<Vec<u8>>>>>()?;
+        columns.push(Arc::new(BinaryArray::from_opt_vec(
+            geometries.iter().map(|wkb| wkb.as_deref()).collect(),
+        )));
+
+        let record_batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        options.apply(record_batch)
+    }
+}
+
+impl Driver for GeoJsonFile {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| matches!(format, Format::GeoJson(_)))
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let format = match Format::detect(path)? {
+            Format::GeoJson(format) => format,
+            _ => GeoJsonFormat::FeatureCollection,
+        };
+        GeoJsonFile::open(path, format)
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(vec![self.layer_name()])
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        Ok(self.schema())
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        self.read(options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        dataset::Dataset,
+        test_util::{point_layer, TempPath},
+    };
+    use arrow::array::{as_primitive_array, as_string_array};
+    use arrow::datatypes::Int64Type;
+
+    #[test]
+    fn test_geometry_round_trip() {
+        let geometry = WkbGeometry::MultiPolygon(
+            WkbMultiPolygon::try_from(vec![vec![vec![
+                [0.0, 0.0],
+                [2.123456789, 0.0],
+                [2.0, 1.0],
+                [0.0, 0.0],
+            ]]])
+            .unwrap(),
+        );
+        let options = WriteOptions {
+            coordinate_precision: Some(6),
+            bbox: false,
+        };
+
+        let value = geometry_to_json(&geometry, &options);
+
+        assert_eq!(
+            json!({
+                "type": "MultiPolygon",
+                "coordinates": [[[[0.0, 0.0], [2.123457, 0.0], [2.0, 1.0], [0.0, 0.0]]]]
+            }),
+            value
+        );
+        assert_eq!(
+            Some([0.0, 0.0, 2.123457, 1.0]),
+            geometry_from_json(&value).unwrap().envelope()
+        );
+    }
+
+    #[test]
+    fn test_write_feature_collection() {
+        let layer = point_layer();
+        let mut buffer = Vec::new();
+        let options = WriteOptions {
+            coordinate_precision: None,
+            bbox: true,
+        };
+        write_feature_collection(&mut buffer, std::slice::from_ref(&layer), &options).unwrap();
+
+        let value: Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(json!([0.0, 0.0, 1.0, 1.0]), value["bbox"]);
+        let feature = &value["features"][1];
+        assert_eq!(json!([1.0, 1.0, 1.0, 1.0]), feature["bbox"]);
+        assert_eq!(json!(2), feature["properties"]["fid"]);
+        assert_eq!(
+            json!({"type": "Point", "coordinates": [1.0, 1.0]}),
+            feature["geometry"]
+        );
+    }
+
+    #[test]
+    fn test_read_layer() {
+        let layer = point_layer();
+        for (extension, format) in [
+            ("geojson", GeoJsonFormat::FeatureCollection),
+            ("geojsonl", GeoJsonFormat::Sequence),
+        ] {
+            let path = TempPath::new(&format!("ogr2arrow-point.{}", extension));
+            let file = File::create(&path).unwrap();
+            match format {
+                GeoJsonFormat::FeatureCollection => write_feature_collection(
+                    file,
+                    std::slice::from_ref(&layer),
+                    &WriteOptions::default(),
+                ),
+                GeoJsonFormat::Sequence => {
+                    write_sequence(file, std::slice::from_ref(&layer), &WriteOptions::default())
+                }
+            }
+            .unwrap();
+
+            let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+            let recieved_layer = dataset.get_layer("ogr2arrow-point").unwrap();
+
+            let schema = recieved_layer.schema();
+            let names: Vec<&str> = schema.fields().iter().map(|f| f.name().as_str()).collect();
+            assert_eq!(vec!["fid", "name", "geometry"], names);
+            assert_eq!(
+                &[1, 2],
+                as_primitive_array::<Int64Type>(recieved_layer.column(0)).values()
+            );
+            assert_eq!(
+                as_string_array(layer.column(2)),
+                as_string_array(recieved_layer.column(1))
+            );
+            assert_eq!(
+                Some([1.0, 1.0, 1.0, 1.0]),
+                geoarrow::get_geometry(recieved_layer.column(2).as_ref(), 1)
+                    .unwrap()
+                    .unwrap()
+                    .envelope()
+            );
+            assert_eq!(
+                Some(ExtensionMetadata::crs84()),
+                geoarrow::get_extension_metadata(schema.field(2))
+            );
+        }
+    }
+}
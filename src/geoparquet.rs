@@ -0,0 +1,1035 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use arrow::{
+    array::{
+        as_primitive_array, as_struct_array, Array, ArrayRef, BinaryArray, BooleanArray,
+        BooleanBufferBuilder, Float64Array, StructArray,
+    },
+    compute::filter_record_batch,
+    datatypes::{DataType, Field, Float64Type, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use parquet::{
+    arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter},
+    basic::Compression,
+    file::{
+        metadata::{KeyValue, RowGroupMetaData},
+        properties::WriterProperties,
+        reader::FileReader,
+        serialized_reader::SerializedFileReader,
+        statistics::Statistics,
+    },
+};
+use serde::{Deserialize as _, Deserializer};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver},
+    geoarrow::{self, ExtensionMetadata},
+    projjson,
+    wkb::{WkbComponent, WkbGeometry, WkbGeometryType},
+};
+
+pub const GEO_METADATA_KEY: &str = "geo";
+pub const VERSION: &str = "1.1.0";
+
+/// The file-level `geo` metadata of a GeoParquet file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GeoMetadata {
+    pub version: String,
+    pub primary_column: String,
+    pub columns: BTreeMap<String, GeoColumn>,
+}
+
+/// The `geo` metadata of a single geometry column.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GeoColumn {
+    pub encoding: String,
+    pub geometry_types: Vec<String>,
+    /// The PROJJSON CRS: `None` if missing, which means OGC:CRS84, and
+    /// `Some(Value::Null)` for an explicitly undefined CRS.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_crs",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub crs: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub covering: Option<Covering>,
+}
+
+/// Deserialize a present `crs`, even a `null` one, as `Some`.
+fn deserialize_crs<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<serde_json::Value>, D::Error> {
+    serde_json::Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Covering {
+    pub bbox: BboxCovering,
+}
+
+/// Paths to the `xmin`, `ymin`, `xmax` and `ymax` fields of a bbox column.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BboxCovering {
+    pub xmin: Vec<String>,
+    pub ymin: Vec<String>,
+    pub xmax: Vec<String>,
+    pub ymax: Vec<String>,
+}
+
+impl BboxCovering {
+    fn new(column: &str) -> Self {
+        let path = |field: &str| vec![column.to_string(), field.to_string()];
+        BboxCovering {
+            xmin: path("xmin"),
+            ymin: path("ymin"),
+            xmax: path("xmax"),
+            ymax: path("ymax"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeometryEncoding {
+    /// Every geometry column is written as WKB.
+    Wkb,
+    /// Point columns are written as separated `x`/`y` structs, anything else
+    /// falls back to WKB.
+    Native,
+}
+
+/// Options controlling how [`write_layer`] encodes a layer.
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    pub encoding: GeometryEncoding,
+    pub compression: Compression,
+    pub max_row_group_size: usize,
+    /// Add a `<column>_bbox` struct column per geometry column, registered
+    /// as its bbox covering, so that readers can prune row groups.
+    pub bbox_covering: bool,
+    /// WKT definitions of the geometry columns' CRSs, keyed by their GeoArrow
+    /// `crs`, e.g. from `Dataset::crs_definitions`. They are written as
+    /// PROJJSON; a CRS without a definition is written with only its `id`.
+    pub crs_definitions: HashMap<String, String>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            encoding: GeometryEncoding::Wkb,
+            compression: Compression::SNAPPY,
+            max_row_group_size: 64 * 1024,
+            bbox_covering: true,
+            crs_definitions: HashMap::new(),
+        }
+    }
+}
+
+/// The GeoParquet name of a geometry's type. Triangles, polyhedral surfaces
+/// and TINs have no GeoParquet type and are described as the polygons they
+/// are made of.
+fn get_geometry_type_name(geometry: &WkbGeometry) -> String {
+    let name = match geometry.geometry_type() {
+        WkbGeometryType::Point => "Point",
+        WkbGeometryType::LineString => "LineString",
+        WkbGeometryType::Polygon | WkbGeometryType::Triangle => "Polygon",
+        WkbGeometryType::MultiPoint => "MultiPoint",
+        WkbGeometryType::MultiLineString => "MultiLineString",
+        WkbGeometryType::MultiPolygon
+        | WkbGeometryType::PolyhedralSurface
+        | WkbGeometryType::Tin => "MultiPolygon",
+        WkbGeometryType::GeometryCollection => "GeometryCollection",
+    };
+    match geometry.dimension().has_z() {
+        true => format!("{} Z", name),
+        false => name.to_string(),
+    }
+}
+
+/// The GeoParquet `crs` of a field: the PROJJSON of its CRS, an explicit
+/// `null` for an undefined CRS, or nothing if the field carries no GeoArrow
+/// metadata or its CRS is OGC:CRS84, which GeoParquet assumes when `crs` is
+/// missing.
+///
+/// Authority codes are converted from their WKT in `crs_definitions`, keeping
+/// at least their `id` when they have no definition that converts. A CRS
+/// given as WKT, as read from a `.prj`, is converted directly, and one given
+/// as PROJJSON is written as it is.
+fn get_crs(
+    field: &Field,
+    crs_definitions: &HashMap<String, String>,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    let metadata = match geoarrow::get_extension_metadata(field) {
+        Some(metadata) if !metadata.is_crs84() => metadata,
+        _ => return Ok(None),
+    };
+    let crs = match metadata.crs.as_deref() {
+        Some(crs) => crs,
+        None => return Ok(Some(serde_json::Value::Null)),
+    };
+    let (authority, code) = match metadata.authority_code() {
+        Some(authority_code) => authority_code,
+        None if metadata.crs_type.as_deref() == Some("projjson") => {
+            return Ok(Some(serde_json::from_str(crs)?))
+        }
+        None => return projjson::from_wkt(crs).map(Some),
+    };
+    let mut projjson = crs_definitions
+        .get(crs)
+        .and_then(|definition| projjson::from_wkt(definition).ok())
+        .unwrap_or_else(|| json!({}));
+    if let serde_json::Value::Object(object) = &mut projjson {
+        object
+            .entry("id")
+            .or_insert_with(|| json!({"authority": authority, "code": code}));
+    }
+    Ok(Some(projjson))
+}
+
+fn point_data_type() -> DataType {
+    DataType::Struct(vec![
+        Field::new("x", DataType::Float64, true),
+        Field::new("y", DataType::Float64, true),
+    ])
+}
+
+fn bbox_data_type() -> DataType {
+    DataType::Struct(
+        ["xmin", "ymin", "xmax", "ymax"]
+            .into_iter()
+            .map(|name| Field::new(name, DataType::Float64, true))
+            .collect(),
+    )
+}
+
+fn merge_envelope(bbox: &mut Option<[f64; 4]>, envelope: &[f64; 4]) {
+    let bbox = bbox.get_or_insert(*envelope);
+    bbox[0] = bbox[0].min(envelope[0]);
+    bbox[1] = bbox[1].min(envelope[1]);
+    bbox[2] = bbox[2].max(envelope[2]);
+    bbox[3] = bbox[3].max(envelope[3]);
+}
+
+/// The geometry types and bounds of a geometry column, gathered as its
+/// geometries are decoded.
+#[derive(Debug, Default)]
+struct ColumnSummary {
+    geometry_types: Vec<String>,
+    bbox: Option<[f64; 4]>,
+}
+
+impl ColumnSummary {
+    fn add(&mut self, geometry: &WkbGeometry) {
+        let geometry_type = get_geometry_type_name(geometry);
+        if !self.geometry_types.contains(&geometry_type) {
+            self.geometry_types.push(geometry_type);
+        }
+        if let Some(envelope) = geometry.envelope() {
+            merge_envelope(&mut self.bbox, &envelope);
+        }
+    }
+
+    fn merge(&mut self, other: ColumnSummary) {
+        for geometry_type in other.geometry_types {
+            if !self.geometry_types.contains(&geometry_type) {
+                self.geometry_types.push(geometry_type);
+            }
+        }
+        if let Some(bbox) = other.bbox {
+            merge_envelope(&mut self.bbox, &bbox);
+        }
+    }
+
+    fn into_geo_column(
+        self,
+        field: &Field,
+        crs_definitions: &HashMap<String, String>,
+    ) -> anyhow::Result<GeoColumn> {
+        let encoding = match geoarrow::extension_name(field.data_type()) {
+            Some(geoarrow::POINT) => "point",
+            _ => "WKB",
+        };
+        Ok(GeoColumn {
+            encoding: encoding.to_string(),
+            geometry_types: self.geometry_types,
+            crs: get_crs(field, crs_definitions)
+                .context(format!("Failed to convert the CRS of {}", field.name()))?,
+            bbox: self.bbox.map(|bbox| bbox.to_vec()),
+            covering: None,
+        })
+    }
+}
+
+fn get_primary_column(schema: &Schema) -> anyhow::Result<String> {
+    geoarrow::primary_geometry_column(schema)
+        .map(|index| schema.field(index).name().clone())
+        .context("No geometry column found")
+}
+
+/// Describe the geometry columns of `record_batches`, as they are currently
+/// encoded, as `geo` metadata.
+///
+/// Geometry columns are the fields tagged with a `geoarrow.*` extension type;
+/// the first one becomes the primary column. The CRS is taken from the field's
+/// extension metadata and written as PROJJSON converted from its WKT in
+/// `crs_definitions`, keyed by the GeoArrow `crs`.
+pub fn get_geo_metadata(
+    record_batches: &[RecordBatch],
+    crs_definitions: &HashMap<String, String>,
+) -> anyhow::Result<GeoMetadata> {
+    let schema = record_batches
+        .first()
+        .map(RecordBatch::schema)
+        .context("No record batches to describe")?;
+
+    let mut columns = BTreeMap::new();
+    for (index, field) in schema.fields().iter().enumerate() {
+        if !geoarrow::is_geometry_field(field) {
+            continue;
+        }
+        let mut summary = ColumnSummary::default();
+        for record_batch in record_batches {
+            let array = record_batch.column(index);
+            for row in 0..array.len() {
+                if let Some(geometry) = geoarrow::get_geometry(array.as_ref(), row)? {
+                    summary.add(&geometry);
+                }
+            }
+        }
+        let column = summary.into_geo_column(field, crs_definitions)?;
+        columns.insert(field.name().clone(), column);
+    }
+
+    Ok(GeoMetadata {
+        version: VERSION.to_string(),
+        primary_column: get_primary_column(&schema)?,
+        columns,
+    })
+}
+
+fn float64_array(values: impl Iterator<Item = Option<f64>>) -> ArrayRef {
+    Arc::new(values.collect::<Float64Array>()) as ArrayRef
+}
+
+/// Encode a geometry column for output and, if requested, build its bbox
+/// covering column, summarising its geometries on the way.
+fn encode_column(
+    array: &ArrayRef,
+    native: bool,
+    bbox_covering: bool,
+) -> anyhow::Result<(ArrayRef, Option<ArrayRef>, ColumnSummary)> {
+    let mut summary = ColumnSummary::default();
+    let mut geometries: Vec<Option<WkbGeometry>> = Vec::with_capacity(array.len());
+    for index in 0..array.len() {
+        let geometry = geoarrow::get_geometry(array.as_ref(), index)?;
+        if let Some(geometry) = &geometry {
+            summary.add(geometry);
+        }
+        geometries.push(geometry);
+    }
+    let envelopes: Vec<Option<[f64; 4]>> = geometries
+        .iter()
+        .map(|geometry| geometry.as_ref().and_then(WkbGeometry::envelope))
+        .collect();
+
+    let encoded = match native {
+        true => {
+            let DataType::Struct(fields) = point_data_type() else {
+                unreachable!()
+            };
+            let coordinates: Vec<Option<[f64; 2]>> = geometries
+                .iter()
+                .map(|geometry| match geometry {
+                    Some(WkbGeometry::Point(point)) => Ok(Some([point.point.x, point.point.y])),
+                    Some(geometry) => Err(anyhow::anyhow!(
+                        "Cannot encode a {:?} as a point",
+                        geometry.geometry_type()
+                    )),
+                    None => Ok(None),
+                })
+                .collect::<anyhow::Result<_>>()?;
+            let mut validity = BooleanBufferBuilder::new(coordinates.len());
+            for coordinate in &coordinates {
+                validity.append(coordinate.is_some());
+            }
+            let axis = |axis: usize| {
+                float64_array(
+                    coordinates
+                        .iter()
+                        .map(|coordinate| coordinate.map(|coordinate| coordinate[axis])),
+                )
+            };
+            let points = StructArray::from((
+                vec![(fields[0].clone(), axis(0)), (fields[1].clone(), axis(1))],
+                validity.finish(),
+            ));
+            Arc::new(points) as ArrayRef
+        }
+        false => {
+            let wkb: Vec<Option<Vec<u8>>> = geometries
+                .iter()
+                .map(|geometry| geometry.as_ref().map(WkbGeometry::to_wkb))
+                .collect();
+            let wkb = BinaryArray::from_opt_vec(wkb.iter().map(|wkb| wkb.as_deref()).collect());
+            Arc::new(wkb) as ArrayRef
+        }
+    };
+
+    let covering = bbox_covering.then(|| {
+        let DataType::Struct(fields) = bbox_data_type() else {
+            unreachable!()
+        };
+        let columns = fields
+            .into_iter()
+            .enumerate()
+            .map(|(axis, field)| {
+                let values = envelopes
+                    .iter()
+                    .map(|envelope| envelope.map(|envelope| envelope[axis]));
+                (field, float64_array(values))
+            })
+            .collect::<Vec<_>>();
+        Arc::new(StructArray::from(columns)) as ArrayRef
+    });
+
+    Ok((encoded, covering, summary))
+}
+
+/// Write the `RecordBatch`es of a layer, as returned by `Dataset::get_layer`,
+/// to `writer` as GeoParquet, with `geo` metadata describing the encoded
+/// geometry columns.
+pub fn write_layer<W: Write>(
+    writer: W,
+    record_batches: &[RecordBatch],
+    options: &WriteOptions,
+) -> anyhow::Result<()> {
+    let input_schema = record_batches
+        .first()
+        .map(RecordBatch::schema)
+        .context("No record batches to write")?;
+
+    let geometry_columns: Vec<(usize, bool)> = input_schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(_index, field)| geoarrow::is_geometry_field(field))
+        .map(|(index, field)| {
+            let native = options.encoding == GeometryEncoding::Native
+                && geoarrow::extension_name(field.data_type()) == Some(geoarrow::POINT);
+            (index, native)
+        })
+        .collect();
+
+    let mut fields: Vec<Field> = input_schema.fields().clone();
+    for (index, native) in &geometry_columns {
+        let field = input_schema.field(*index);
+        let extension_metadata = geoarrow::get_extension_metadata(field).unwrap_or_default();
+        let data_type = match native {
+            true => point_data_type(),
+            false => DataType::Binary,
+        };
+        fields[*index] = geoarrow::geometry_field(field.name(), data_type, &extension_metadata);
+    }
+    if options.bbox_covering {
+        for (index, _native) in &geometry_columns {
+            let name = format!("{}_bbox", input_schema.field(*index).name());
+            fields.push(Field::new(&name, bbox_data_type(), true));
+        }
+    }
+    let schema = Arc::new(Schema::new_with_metadata(
+        fields,
+        input_schema.metadata().clone(),
+    ));
+
+    let mut summaries: Vec<ColumnSummary> = geometry_columns
+        .iter()
+        .map(|_column| ColumnSummary::default())
+        .collect();
+    let mut output_batches: Vec<RecordBatch> = Vec::with_capacity(record_batches.len());
+    for record_batch in record_batches {
+        let mut columns = record_batch.columns().to_vec();
+        let mut coverings = Vec::new();
+        for ((index, native), summary) in geometry_columns.iter().zip(&mut summaries) {
+            let (encoded, covering, batch_summary) =
+                encode_column(record_batch.column(*index), *native, options.bbox_covering)?;
+            columns[*index] = encoded;
+            coverings.extend(covering);
+            summary.merge(batch_summary);
+        }
+        columns.extend(coverings);
+        output_batches.push(RecordBatch::try_new(schema.clone(), columns)?);
+    }
+
+    let mut geo_columns = BTreeMap::new();
+    for ((index, _native), summary) in geometry_columns.iter().zip(summaries) {
+        let field = schema.field(*index);
+        let mut column = summary.into_geo_column(field, &options.crs_definitions)?;
+        if options.bbox_covering {
+            column.covering = Some(Covering {
+                bbox: BboxCovering::new(&format!("{}_bbox", field.name())),
+            });
+        }
+        geo_columns.insert(field.name().clone(), column);
+    }
+    let geo_metadata = GeoMetadata {
+        version: VERSION.to_string(),
+        primary_column: get_primary_column(&schema)?,
+        columns: geo_columns,
+    };
+    let properties = WriterProperties::builder()
+        .set_compression(options.compression)
+        .set_max_row_group_size(options.max_row_group_size)
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            GEO_METADATA_KEY.to_string(),
+            serde_json::to_string(&geo_metadata)?,
+        )]))
+        .build();
+
+    let mut writer = ArrowWriter::try_new(writer, schema, Some(properties))?;
+    for record_batch in &output_batches {
+        writer.write(record_batch)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+/// Extension metadata for a field whose CRS is described by GeoParquet `crs`:
+/// OGC:CRS84 if `crs` is missing, no CRS if it is `null`, the `id` of a
+/// PROJJSON CRS if it has one, and otherwise the PROJJSON itself.
+pub fn get_extension_metadata(geo_column: &GeoColumn) -> ExtensionMetadata {
+    let crs = match &geo_column.crs {
+        None => return ExtensionMetadata::crs84(),
+        Some(serde_json::Value::Null) => return ExtensionMetadata::default(),
+        Some(crs) => crs,
+    };
+    let authority = crs
+        .pointer("/id/authority")
+        .and_then(|value| value.as_str());
+    let code = crs.pointer("/id/code").and_then(|code| match code {
+        serde_json::Value::String(code) => code.parse().ok(),
+        code => code.as_i64().and_then(|code| i32::try_from(code).ok()),
+    });
+    match (authority, code) {
+        (Some(authority), Some(code)) => ExtensionMetadata::from_authority_code(authority, code),
+        _ if authority == Some("OGC") && crs.pointer("/id/code") == Some(&json!("CRS84")) => {
+            ExtensionMetadata::crs84()
+        }
+        _ => ExtensionMetadata {
+            crs: Some(crs.to_string()),
+            crs_type: Some("projjson".to_string()),
+        },
+    }
+}
+
+/// A GeoParquet file, exposed as a single layer named after the file stem.
+pub struct GeoParquetFile {
+    path: PathBuf,
+    metadata: GeoMetadata,
+}
+
+fn get_statistics(row_group: &RowGroupMetaData, path: &[String]) -> Option<(f64, f64)> {
+    let path = path.join(".");
+    let column = row_group
+        .columns()
+        .iter()
+        .find(|column| column.column_path().string() == path)?;
+    match column.statistics()? {
+        Statistics::Double(statistics) if statistics.has_min_max_set() => {
+            Some((*statistics.min(), *statistics.max()))
+        }
+        _ => None,
+    }
+}
+
+/// Whether the statistics of a row group's bbox covering columns rule out any
+/// feature intersecting `bbox`.
+fn can_prune(row_group: &RowGroupMetaData, covering: &BboxCovering, bbox: &[f64; 4]) -> bool {
+    let xmin = get_statistics(row_group, &covering.xmin);
+    let ymin = get_statistics(row_group, &covering.ymin);
+    let xmax = get_statistics(row_group, &covering.xmax);
+    let ymax = get_statistics(row_group, &covering.ymax);
+    match (xmin, ymin, xmax, ymax) {
+        (Some(xmin), Some(ymin), Some(xmax), Some(ymax)) => {
+            !geoarrow::intersects(&[xmin.0, ymin.0, xmax.1, ymax.1], bbox)
+        }
+        _ => false,
+    }
+}
+
+fn get_covering_column(schema: &Schema, covering: &BboxCovering) -> Option<usize> {
+    let column = covering.xmin.first()?;
+    schema.index_of(column).ok()
+}
+
+/// Keep the rows whose bbox covering struct intersects `bbox`.
+fn filter_covering(
+    record_batch: &RecordBatch,
+    column: usize,
+    bbox: &[f64; 4],
+) -> anyhow::Result<RecordBatch> {
+    let covering = as_struct_array(record_batch.column(column));
+    let values: Vec<&Float64Array> = (0..4)
+        .map(|axis| as_primitive_array::<Float64Type>(covering.column(axis)))
+        .collect();
+    let predicate: BooleanArray = (0..record_batch.num_rows())
+        .map(|row| {
+            let envelope = [0, 1, 2, 3].map(|axis| values[axis].value(row));
+            Some(!covering.is_null(row) && geoarrow::intersects(&envelope, bbox))
+        })
+        .collect();
+    Ok(filter_record_batch(record_batch, &predicate)?)
+}
+
+impl GeoParquetFile {
+    /// Open `path` and parse its `geo` metadata.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let reader = SerializedFileReader::new(File::open(path)?)?;
+        let geo = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .and_then(|key_values| {
+                key_values
+                    .iter()
+                    .find(|key_value| key_value.key == GEO_METADATA_KEY)
+            })
+            .and_then(|key_value| key_value.value.as_ref())
+            .context("Missing geo metadata")?;
+        let metadata = serde_json::from_str(geo).context("Invalid geo metadata")?;
+        Ok(GeoParquetFile {
+            path: path.to_path_buf(),
+            metadata,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn metadata(&self) -> &GeoMetadata {
+        &self.metadata
+    }
+
+    pub fn layer_name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn covering(&self) -> anyhow::Result<Option<BboxCovering>> {
+        let primary_column = self
+            .metadata
+            .columns
+            .get(&self.metadata.primary_column)
+            .context("Missing primary column metadata")?;
+        Ok(primary_column
+            .covering
+            .as_ref()
+            .map(|covering| covering.bbox.clone()))
+    }
+
+    /// The file's schema with its geometry columns tagged and any bbox
+    /// covering column removed.
+    fn output_schema(&self, schema: &Schema) -> anyhow::Result<Schema> {
+        let covering_column = self
+            .covering()?
+            .and_then(|covering| get_covering_column(schema, &covering));
+        let fields = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(index, _field)| Some(*index) != covering_column)
+            .map(
+                |(_index, field)| match self.metadata.columns.get(field.name()) {
+                    Some(geo_column) => geoarrow::geometry_field(
+                        field.name(),
+                        field.data_type().clone(),
+                        &get_extension_metadata(geo_column),
+                    ),
+                    None => field.clone(),
+                },
+            )
+            .collect();
+        Ok(Schema::new(fields))
+    }
+
+    pub fn schema(&self) -> anyhow::Result<SchemaRef> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&self.path)?)?;
+        Ok(Arc::new(self.output_schema(builder.schema())?))
+    }
+
+    /// Read the layer, skipping row groups whose bbox covering statistics
+    /// lie outside `options.bbox` and then filtering the remaining rows.
+    pub fn read(&self, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        options.ensure_no_filter()?;
+        let covering = self.covering()?;
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&self.path)?)?;
+        let row_groups: Vec<usize> = builder
+            .metadata()
+            .row_groups()
+            .iter()
+            .enumerate()
+            .filter(|(_index, row_group)| match (&options.bbox, &covering) {
+                (Some(bbox), Some(covering)) => !can_prune(row_group, covering, bbox),
+                _ => true,
+            })
+            .map(|(index, _row_group)| index)
+            .collect();
+        let reader_schema = builder.schema().clone();
+        let record_batches = builder
+            .with_row_groups(row_groups)
+            .with_batch_size(64 * 1024)
+            .build()?
+            .collect::<Result<Vec<_>, _>>()?;
+        let schema = match record_batches.first() {
+            Some(record_batch) => record_batch.schema(),
+            None => reader_schema,
+        };
+        let mut record_batch = RecordBatch::concat(&schema, &record_batches)?;
+
+        let covering_column = covering
+            .as_ref()
+            .and_then(|covering| get_covering_column(&schema, covering));
+        if let Some(bbox) = options.bbox {
+            record_batch = match covering_column {
+                Some(column) => filter_covering(&record_batch, column, &bbox)?,
+                None => {
+                    let column = schema.index_of(&self.metadata.primary_column)?;
+                    geoarrow::filter_bbox(&record_batch, column, &bbox)?
+                }
+            };
+        }
+
+        let columns = (0..record_batch.num_columns())
+            .filter(|index| Some(*index) != covering_column)
+            .map(|index| record_batch.column(index).clone())
+            .collect();
+        let record_batch = RecordBatch::try_new(Arc::new(self.output_schema(&schema)?), columns)?;
+        options.encode(options.project(record_batch)?)
+    }
+}
+
+impl Driver for GeoParquetFile {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| *format == Format::GeoParquet)
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        GeoParquetFile::open(path)
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(vec![self.layer_name()])
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        self.schema()
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        self.read(options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{dataset::Dataset, test_util::TempPath, wkb::WkbPoint};
+
+    fn write_point_layer(name: &str, options: &WriteOptions) -> TempPath {
+        let dataset = Dataset::open("Data/point.gpkg").unwrap();
+        let layer = dataset.get_layer("point").unwrap();
+        let options = WriteOptions {
+            crs_definitions: dataset.crs_definitions(&layer.schema()).unwrap(),
+            ..options.clone()
+        };
+        let path = TempPath::new(&format!("{}.parquet", name));
+        let file = File::create(&path).unwrap();
+        write_layer(file, &[layer], &options).unwrap();
+        path
+    }
+
+    fn read_geo_metadata(file: File) -> GeoMetadata {
+        let reader = SerializedFileReader::new(file).unwrap();
+        let key_value_metadata = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap()
+            .clone();
+        let geo = key_value_metadata
+            .into_iter()
+            .find(|key_value| key_value.key == GEO_METADATA_KEY)
+            .and_then(|key_value| key_value.value)
+            .unwrap();
+        serde_json::from_str(&geo).unwrap()
+    }
+
+    #[test]
+    fn test_write_geo_metadata() {
+        let path = write_point_layer("ogr2arrow-geo-metadata", &WriteOptions::default());
+        let file = File::open(path).unwrap();
+
+        let recieved_metadata = read_geo_metadata(file);
+
+        let crs = recieved_metadata.columns["geom"].crs.clone().unwrap();
+        assert_eq!("ProjectedCRS", crs["type"]);
+        assert_eq!("OSGB36 / British National Grid", crs["name"]);
+        assert_eq!(json!({"authority": "EPSG", "code": 27700}), crs["id"]);
+        let expected_column = GeoColumn {
+            encoding: "WKB".to_string(),
+            geometry_types: vec!["Point".to_string()],
+            crs: Some(crs),
+            bbox: Some(vec![0.0, 0.0, 1.0, 1.0]),
+            covering: Some(Covering {
+                bbox: BboxCovering::new("geom_bbox"),
+            }),
+        };
+        assert_eq!(VERSION, recieved_metadata.version);
+        assert_eq!("geom", recieved_metadata.primary_column);
+        assert_eq!(
+            Some(&expected_column),
+            recieved_metadata.columns.get("geom")
+        );
+    }
+
+    #[test]
+    fn test_write_native_encoding() {
+        let options = WriteOptions {
+            encoding: GeometryEncoding::Native,
+            bbox_covering: false,
+            ..Default::default()
+        };
+        let path = write_point_layer("ogr2arrow-native", &options);
+        let file = File::open(path).unwrap();
+
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(file.try_clone().unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let record_batch = reader.next().unwrap().unwrap();
+        let geometry = geoarrow::get_geometry(record_batch.column(1).as_ref(), 1)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(Some([1.0, 1.0, 1.0, 1.0]), geometry.envelope());
+        assert_eq!(3, record_batch.num_columns());
+        assert_eq!("point", read_geo_metadata(file).columns["geom"].encoding);
+    }
+
+    #[test]
+    fn test_write_native_nulls() {
+        let metadata = ExtensionMetadata::from_authority_code("EPSG", 4326);
+        let schema = Schema::new(vec![geoarrow::geometry_field(
+            "geom",
+            geoarrow::point_data_type(),
+            &metadata,
+        )]);
+        let points = geoarrow::point_array(vec![Some([1.0, 2.0]), None]);
+        let record_batch = RecordBatch::try_new(Arc::new(schema), vec![points]).unwrap();
+        let options = WriteOptions {
+            encoding: GeometryEncoding::Native,
+            ..Default::default()
+        };
+        let path = TempPath::new("ogr2arrow-native-nulls.parquet");
+        write_layer(File::create(&path).unwrap(), &[record_batch], &options).unwrap();
+
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let record_batch = reader.next().unwrap().unwrap();
+        let points = as_struct_array(record_batch.column(0));
+        assert!(points.is_valid(0));
+        assert!(points.is_null(1));
+        assert_eq!(
+            None,
+            geoarrow::get_geometry(record_batch.column(0).as_ref(), 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_geometry_types() {
+        let wkb: Vec<Vec<u8>> = ["POINT Z (1 2 3)", "TRIANGLE ((0 0, 1 0, 0 1, 0 0))"]
+            .into_iter()
+            .map(|wkt| WkbGeometry::from_wkt(wkt).unwrap().to_wkb())
+            .collect();
+        let geometry = BinaryArray::from_vec(wkb.iter().map(Vec::as_slice).collect());
+        let metadata = ExtensionMetadata::from_authority_code("EPSG", 4326);
+        let schema = Schema::new(vec![geoarrow::geometry_field(
+            "geom",
+            DataType::Binary,
+            &metadata,
+        )]);
+        let record_batch =
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(geometry) as ArrayRef]).unwrap();
+
+        let geo_metadata = get_geo_metadata(&[record_batch], &HashMap::new()).unwrap();
+        let column = &geo_metadata.columns["geom"];
+        assert_eq!(vec!["Point Z", "Polygon"], column.geometry_types);
+        assert_eq!(
+            Some(json!({"id": {"authority": "EPSG", "code": 4326}})),
+            column.crs
+        );
+    }
+
+    #[test]
+    fn test_get_extension_metadata() {
+        let metadata = |geo_column: serde_json::Value| {
+            get_extension_metadata(&serde_json::from_value(geo_column).unwrap())
+        };
+
+        assert_eq!(
+            ExtensionMetadata::crs84(),
+            metadata(json!({"encoding": "WKB", "geometry_types": []}))
+        );
+        assert_eq!(
+            ExtensionMetadata::default(),
+            metadata(json!({"encoding": "WKB", "geometry_types": [], "crs": null}))
+        );
+        assert_eq!(
+            ExtensionMetadata::from_authority_code("EPSG", 27700),
+            metadata(json!({
+                "encoding": "WKB",
+                "geometry_types": [],
+                "crs": {"id": {"authority": "EPSG", "code": 27700}}
+            }))
+        );
+        let crs = json!({"type": "GeographicCRS", "name": "Unknown"});
+        assert_eq!(
+            ExtensionMetadata {
+                crs: Some(crs.to_string()),
+                crs_type: Some("projjson".to_string()),
+            },
+            metadata(json!({"encoding": "WKB", "geometry_types": [], "crs": crs}))
+        );
+    }
+
+    #[test]
+    fn test_read_crs84() {
+        let wkb = WkbGeometry::from_wkt("POINT (1 2)").unwrap().to_wkb();
+        let geometry = BinaryArray::from_vec(vec![wkb.as_slice()]);
+        let schema = Schema::new(vec![geoarrow::geometry_field(
+            "geom",
+            DataType::Binary,
+            &ExtensionMetadata::crs84(),
+        )]);
+        let record_batch =
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(geometry) as ArrayRef]).unwrap();
+        let path = TempPath::new("ogr2arrow-crs84.parquet");
+        let file = File::create(&path).unwrap();
+        write_layer(file, &[record_batch], &WriteOptions::default()).unwrap();
+
+        let file = GeoParquetFile::open(&path).unwrap();
+        assert_eq!(None, file.metadata.columns["geom"].crs);
+        let schema = file.schema().unwrap();
+        assert_eq!(
+            Some(ExtensionMetadata::crs84()),
+            geoarrow::get_extension_metadata(schema.field(0))
+        );
+    }
+
+    fn diagonal_points(path: &Path, count: usize) {
+        let wkb: Vec<Vec<u8>> = (0..count)
+            .map(|index| {
+                let point = WkbPoint::try_from([index as f64, index as f64]).unwrap();
+                WkbGeometry::Point(point).to_wkb()
+            })
+            .collect();
+        let geometry = BinaryArray::from_vec(wkb.iter().map(Vec::as_slice).collect());
+        let metadata = ExtensionMetadata::from_authority_code("EPSG", 4326);
+        let schema = Schema::new(vec![geoarrow::geometry_field(
+            "geom",
+            DataType::Binary,
+            &metadata,
+        )]);
+        let record_batch =
+            RecordBatch::try_new(Arc::new(schema), vec![Arc::new(geometry) as ArrayRef]).unwrap();
+        let options = WriteOptions {
+            max_row_group_size: 2,
+            ..Default::default()
+        };
+        write_layer(File::create(path).unwrap(), &[record_batch], &options).unwrap();
+    }
+
+    #[test]
+    fn test_read_layer() {
+        let path = write_point_layer("ogr2arrow-read-layer", &WriteOptions::default());
+
+        let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+        let recieved_layer = dataset.get_layer("ogr2arrow-read-layer").unwrap();
+
+        let geometry_field = recieved_layer.schema().field(1).clone();
+        assert_eq!(3, recieved_layer.num_columns());
+        assert_eq!(2, recieved_layer.num_rows());
+        assert_eq!(
+            Some(("EPSG", 27700)),
+            geoarrow::get_extension_metadata(&geometry_field)
+                .unwrap()
+                .authority_code()
+        );
+    }
+
+    #[test]
+    fn test_read_bbox() {
+        let path = TempPath::new("ogr2arrow-diagonal.parquet");
+        diagonal_points(&path, 10);
+
+        let file = GeoParquetFile::open(&path).unwrap();
+        let options = ReadOptions {
+            bbox: Some([2.5, 2.5, 5.5, 5.5]),
+            ..Default::default()
+        };
+        let recieved_layer = file.read(&options).unwrap();
+
+        let recieved_envelopes: Vec<Option<[f64; 4]>> = (0..recieved_layer.num_rows())
+            .map(|index| {
+                geoarrow::get_geometry(recieved_layer.column(0).as_ref(), index)
+                    .unwrap()
+                    .and_then(|geometry| geometry.envelope())
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                Some([3.0, 3.0, 3.0, 3.0]),
+                Some([4.0, 4.0, 4.0, 4.0]),
+                Some([5.0, 5.0, 5.0, 5.0]),
+            ],
+            recieved_envelopes
+        );
+        assert_eq!(1, recieved_layer.num_columns());
+    }
+
+    #[test]
+    fn test_row_group_pruning() {
+        let path = TempPath::new("ogr2arrow-pruning.parquet");
+        diagonal_points(&path, 10);
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let covering = BboxCovering::new("geom_bbox");
+        let bbox = [2.5, 2.5, 5.5, 5.5];
+        let recieved_row_groups: Vec<usize> = (0..reader.num_row_groups())
+            .filter(|index| !can_prune(reader.metadata().row_group(*index), &covering, &bbox))
+            .collect();
+
+        assert_eq!(5, reader.num_row_groups());
+        assert_eq!(vec![1, 2], recieved_row_groups);
+    }
+}
@@ -1,18 +1,30 @@
-use crate::wkb::WkbGeometry;
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver, RecordBatches},
+    geoarrow::{self, ExtensionMetadata},
+    parallel,
+    tiles::{self, TileQuery},
+    wkb::{WkbComponent, WkbGeometry},
+};
+use anyhow::Context;
 use arrow::{
     self,
     array::{
-        ArrayData, ArrayRef, BooleanArray, FixedSizeListArray, Float32Array, Float64Array,
-        Int16Array, Int32Array, Int64Array, Int8Array, StringArray,
+        as_boolean_array, as_primitive_array, as_string_array, Array, ArrayRef, BinaryArray,
+        BooleanArray, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, Int8Array,
+        StringArray,
+    },
+    datatypes::{
+        DataType, Field, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type,
+        Schema, SchemaRef,
     },
-    datatypes::{DataType, Field, Schema},
     record_batch::RecordBatch,
 };
-use binread::{BinRead, BinReaderExt};
+use binread::{io::Cursor, BinRead, BinReaderExt};
 use fallible_iterator::FallibleIterator;
 use modular_bitfield::prelude::*;
-use rusqlite::{self, named_params, Connection};
-use std::{iter::Iterator, sync::Arc};
+use rusqlite::{self, named_params, types::Value, Connection, OptionalExtension};
+use std::{iter::Iterator, path::Path, sync::Arc};
 
 fn get_data_type(sql_name: Option<&str>) -> DataType {
     sql_name
@@ -26,9 +38,7 @@ fn get_data_type(sql_name: Option<&str>) -> DataType {
             "REAL" => DataType::Float64,
             name if name.starts_with("TEXT") => DataType::Utf8,
             name if name.starts_with("BLOB") => DataType::Utf8,
-            "POINT" => {
-                DataType::FixedSizeList(Box::new(Field::new("Point", DataType::Float64, true)), 2)
-            }
+            "POINT" => geoarrow::point_data_type(),
             "DATE" | "DATETIME" => DataType::Utf8,
             "GEOMETRY" | "LINESTRING" | "POLYGON" | "MULTIPOINT" | "MULTILINESTRING"
             | "MULTIPOLYGON" | "GEOMETRYCOLLECTION" => DataType::Binary,
@@ -37,15 +47,117 @@ fn get_data_type(sql_name: Option<&str>) -> DataType {
         .unwrap()
 }
 
+/// A row of `gpkg_spatial_ref_sys`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpatialRefSys {
+    pub srs_name: String,
+    pub srs_id: i32,
+    pub organization: String,
+    pub organization_coordsys_id: i32,
+    pub definition: String,
+}
+
+impl SpatialRefSys {
+    /// The GeoArrow extension metadata identifying this SRS, empty for the
+    /// undefined cartesian and geographic systems.
+    pub fn extension_metadata(&self) -> ExtensionMetadata {
+        if self.organization.eq_ignore_ascii_case("NONE") {
+            return ExtensionMetadata::default();
+        }
+        ExtensionMetadata::from_authority_code(&self.organization, self.organization_coordsys_id)
+    }
+}
+
+/// The spatial reference system of `layer`, as recorded in `gpkg_contents`.
+pub fn get_spatial_ref_sys(
+    connection: &Connection,
+    layer: &str,
+) -> rusqlite::Result<SpatialRefSys> {
+    let mut statement = connection.prepare(
+        "SELECT srs_name, srs.srs_id, organization, organization_coordsys_id, definition
+        FROM gpkg_spatial_ref_sys AS srs
+        JOIN gpkg_contents AS contents ON contents.srs_id = srs.srs_id
+        WHERE contents.table_name = :layer",
+    )?;
+    statement.query_row(named_params! {":layer": layer}, |row| {
+        Ok(SpatialRefSys {
+            srs_name: row.get(0)?,
+            srs_id: row.get(1)?,
+            organization: row.get(2)?,
+            organization_coordsys_id: row.get(3)?,
+            definition: row.get(4)?,
+        })
+    })
+}
+
+/// The WKT `definition` of the SRS `organization:organization_coordsys_id`,
+/// unless it is missing or `undefined`.
+pub fn get_crs_definition(
+    connection: &Connection,
+    organization: &str,
+    organization_coordsys_id: i32,
+) -> rusqlite::Result<Option<String>> {
+    let definition: Option<String> = connection
+        .query_row(
+            "SELECT definition FROM gpkg_spatial_ref_sys
+            WHERE organization = :organization COLLATE NOCASE
+            AND organization_coordsys_id = :organization_coordsys_id",
+            named_params! {
+                ":organization": organization,
+                ":organization_coordsys_id": organization_coordsys_id,
+            },
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(definition.filter(|definition| !definition.eq_ignore_ascii_case("undefined")))
+}
+
+fn get_geometry_columns(connection: &Connection, layer: &str) -> rusqlite::Result<Vec<String>> {
+    let mut statement = connection
+        .prepare("SELECT column_name FROM gpkg_geometry_columns WHERE table_name = :layer")?;
+    let rows = statement.query(named_params! {":layer": layer})?;
+    rows.map(|row| row.get(0)).collect()
+}
+
 pub fn get_schema(connection: &Connection, layer: &str) -> rusqlite::Result<Schema> {
-    let sql = format!("SELECT * FROM {}", layer);
+    let geometry_columns = get_geometry_columns(connection, layer)?;
+    let extension_metadata = match geometry_columns.is_empty() {
+        true => ExtensionMetadata::default(),
+        false => get_spatial_ref_sys(connection, layer)?.extension_metadata(),
+    };
+    get_table_schema(connection, layer, &geometry_columns, &extension_metadata)
+}
+
+/// Quote an SQL identifier, doubling any `"` inside it.
+pub(crate) fn quote_identifier(identifier: &str) -> String {
+    format!(r#""{}""#, identifier.replace('"', r#""""#))
+}
+
+/// The schema of `SELECT * FROM layer`, tagging `geometry_columns` as
+/// GeoArrow geometries in the given CRS.
+pub(crate) fn get_table_schema(
+    connection: &Connection,
+    layer: &str,
+    geometry_columns: &[String],
+    extension_metadata: &ExtensionMetadata,
+) -> rusqlite::Result<Schema> {
+    let sql = format!("SELECT * FROM {}", quote_identifier(layer));
     let statement = connection.prepare(&sql)?;
 
     let columns = statement.columns();
 
     let fields: Vec<Field> = columns
         .into_iter()
-        .map(|column| Field::new(column.name(), get_data_type(column.decl_type()), true))
+        .map(|column| {
+            let data_type = get_data_type(column.decl_type());
+            match geometry_columns
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(column.name()))
+            {
+                true => geoarrow::geometry_field(column.name(), data_type, extension_metadata),
+                false => Field::new(column.name(), data_type, true),
+            }
+        })
         .collect();
 
     Ok(Schema::new(fields))
@@ -59,55 +171,15 @@ macro_rules! generate_match_arm {
                 Ok(value)
             })
             .collect();
-        let data = <$array_type>::from_iter(values.unwrap());
+        let data = <$array_type>::from_iter(values?);
         Arc::new(data) as ArrayRef
     }};
 }
 
-fn get_number_of_features(connection: &Connection, layer: &str) -> i32 {
-    let sql = format!("SELECT MAX(FID) FROM {}", layer);
-    let mut statement = connection.prepare(&sql).unwrap();
-    statement.query_row([], |row| row.get(0)).unwrap()
-}
-
-fn get_points(connection: &Connection, layer: &str, number_of_features: i32) -> Vec<f64> {
-    let mut points: Vec<f64> = Vec::new();
-    for row_id in 1..=number_of_features {
-        let mut geometry_blob = connection
-            .blob_open(
-                rusqlite::DatabaseName::Main,
-                layer,
-                "geom",
-                row_id as i64,
-                true,
-            )
-            .unwrap();
-        let gpb: StandardGeoPackageBinary = geometry_blob.read_ne().unwrap();
-        if let WkbGeometry::Point(point) = gpb.geometry {
-            let array: [f64; 2] = point.try_into().unwrap();
-            array.into_iter().for_each(|point| {
-                points.push(point);
-            })
-        };
-    }
-    points
-}
-
-fn build_fixed_list_from_points(points: Vec<f64>) -> FixedSizeListArray {
-    let float_array = Float64Array::from_iter_values(points);
-
-    let child_data = ArrayData::from(float_array);
-
-    let array_data_type =
-        DataType::FixedSizeList(Box::new(Field::new("Point", DataType::Float64, true)), 2);
-
-    let list_data = ArrayData::builder(array_data_type)
-        .len(2)
-        .add_child_data(child_data)
-        .build()
-        .unwrap();
-
-    FixedSizeListArray::from(list_data)
+/// Decode the geometry of a GeoPackage binary blob.
+pub(crate) fn decode_geopackage_binary(blob: &[u8]) -> anyhow::Result<WkbGeometry> {
+    let gpb: StandardGeoPackageBinary = Cursor::new(blob).read_ne()?;
+    Ok(gpb.geometry)
 }
 
 #[allow(dead_code)]
@@ -115,7 +187,19 @@ pub fn get_fields(
     connection: &Connection,
     schema: &Schema,
     layer: &str,
-) -> Vec<Arc<(dyn arrow::array::Array)>> {
+) -> anyhow::Result<Vec<ArrayRef>> {
+    get_fields_with(connection, schema, layer, None, decode_geopackage_binary)
+}
+
+/// Read every column of `schema` from the rows of `layer` matching the SQL
+/// `filter`, decoding geometry blobs with `decode`.
+pub(crate) fn get_fields_with(
+    connection: &Connection,
+    schema: &Schema,
+    layer: &str,
+    filter: Option<&str>,
+    decode: fn(&[u8]) -> anyhow::Result<WkbGeometry>,
+) -> anyhow::Result<Vec<ArrayRef>> {
     let names_and_types = schema
         .fields()
         .iter()
@@ -123,19 +207,24 @@ pub fn get_fields(
 
     names_and_types
         .map(|(field_name, field_type)| {
-            let sql = format!("SELECT {} FROM {}", field_name, layer);
+            let sql = match filter {
+                Some(filter) => format!(
+                    "SELECT {} FROM {} WHERE {}",
+                    quote_identifier(field_name),
+                    quote_identifier(layer),
+                    filter
+                ),
+                None => format!(
+                    "SELECT {} FROM {}",
+                    quote_identifier(field_name),
+                    quote_identifier(layer)
+                ),
+            };
             let mut statement = connection
                 .prepare(&sql)
-                .expect("Failed to prepare statment.");
-            let rows = statement.query([]).expect("Failed to execute query.");
-            // let mut statement = connection
-            //     .prepare("SELECT :field_name FROM :layer")
-            //     .expect("Failed to prepare statment.");
-            // let rows = statement.query(named_params! {
-            //     ":field_name": field_name,
-            //     ":layer": layer,
-            // }).expect("Failed to execute query.");
-            match field_type {
+                .context(format!("Failed to prepare {}", sql))?;
+            let mut rows = statement.query([])?;
+            let array = match field_type {
                 DataType::Boolean => generate_match_arm!(rows, bool, BooleanArray),
                 DataType::Int8 => generate_match_arm!(rows, i8, Int8Array),
                 DataType::Int16 => generate_match_arm!(rows, i16, Int16Array),
@@ -145,34 +234,77 @@ pub fn get_fields(
                 DataType::Float64 => generate_match_arm!(rows, f64, Float64Array),
                 DataType::Utf8 => generate_match_arm!(rows, String, StringArray),
                 DataType::FixedSizeList(_field, _offset) => {
-                    let number_of_features = get_number_of_features(connection, layer);
-
-                    let points = get_points(connection, layer, number_of_features);
-
-                    let fixed_list_array = build_fixed_list_from_points(points);
-
-                    Arc::new(fixed_list_array) as ArrayRef
+                    let mut points = Vec::new();
+                    while let Some(row) = rows.next()? {
+                        let blob: Option<Vec<u8>> = row.get(0)?;
+                        let point = match blob {
+                            Some(blob) => match decode(&blob)? {
+                                WkbGeometry::Point(point) => point.try_into().ok(),
+                                _ => None,
+                            },
+                            None => None,
+                        };
+                        points.push(point);
+                    }
+                    geoarrow::point_array(points)
                 }
-                // DataType::Binary => {
-                //     let values: rusqlite::Result<Vec<_>> = rows
-                //         .map(|row| {
-                //             let value: Vec<u8> = row.get_unwrap(0);
-                //             let binary_array = BinaryArray::from_vec(vec![&value[..]]);
-                //             Ok(binary_array.into_data())
-                //         })
-                //         .collect();
-                //     let data = StructArray::from(values.unwrap());
-                //     Arc::new(data) as ArrayRef
-                // }
-                _ => unimplemented!(),
-            }
+                DataType::Binary => {
+                    let mut values = Vec::new();
+                    while let Some(row) = rows.next()? {
+                        let blob: Option<Vec<u8>> = row.get(0)?;
+                        let wkb = blob.map(|blob| decode(&blob)).transpose()?;
+                        values.push(wkb.map(|geometry| geometry.to_wkb()));
+                    }
+                    let data = BinaryArray::from_opt_vec(
+                        values.iter().map(|value| value.as_deref()).collect(),
+                    );
+                    Arc::new(data) as ArrayRef
+                }
+                data_type => anyhow::bail!("Unsupported data type {:?}", data_type),
+            };
+            Ok(array)
         })
         .collect()
 }
 
 pub fn get_layer(connection: &Connection, layer_name: &str) -> anyhow::Result<RecordBatch> {
+    get_filtered_layer(connection, layer_name, None)
+}
+
+/// Fail early on a filter that is not a valid SQL expression over `layer`,
+/// rather than when each column is read.
+pub(crate) fn check_filter(
+    connection: &Connection,
+    layer: &str,
+    filter: Option<&str>,
+) -> anyhow::Result<()> {
+    if let Some(filter) = filter {
+        connection
+            .prepare(&format!(
+                "SELECT 1 FROM {} WHERE {}",
+                quote_identifier(layer),
+                filter
+            ))
+            .context(format!("Invalid filter {}", filter))?;
+    }
+    Ok(())
+}
+
+/// Read the rows of a layer matching the SQL expression `filter`.
+pub fn get_filtered_layer(
+    connection: &Connection,
+    layer_name: &str,
+    filter: Option<&str>,
+) -> anyhow::Result<RecordBatch> {
     let schema = get_schema(connection, layer_name)?;
-    let fields = get_fields(connection, &schema, layer_name);
+    check_filter(connection, layer_name, filter)?;
+    let fields = get_fields_with(
+        connection,
+        &schema,
+        layer_name,
+        filter,
+        decode_geopackage_binary,
+    )?;
     let record_batch = RecordBatch::try_new(Arc::new(schema), fields)?;
     Ok(record_batch)
 }
@@ -217,6 +349,79 @@ pub fn list_layers(connection: &Connection) -> rusqlite::Result<Vec<String>> {
     values
 }
 
+/// A GeoPackage, whose feature and tile tables are its layers.
+pub struct GeoPackage {
+    connection: Connection,
+}
+
+impl GeoPackage {
+    pub fn new(connection: Connection) -> Self {
+        GeoPackage { connection }
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Driver for GeoPackage {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| *format == Format::GeoPackage)
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        Ok(GeoPackage::new(Connection::open(path)?))
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(list_layers(&self.connection)?)
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        if tiles::is_tile_table(&self.connection, layer_name)? {
+            return Ok(Arc::new(tiles::tile_schema()));
+        }
+        Ok(Arc::new(get_schema(&self.connection, layer_name)?))
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        let filter = options.filter.as_deref();
+        let record_batch = match tiles::is_tile_table(&self.connection, layer_name)? {
+            true => {
+                let query = TileQuery {
+                    bbox: options.bbox,
+                    ..Default::default()
+                };
+                tiles::get_tiles(&self.connection, layer_name, &query, filter)?
+            }
+            false => get_filtered_layer(&self.connection, layer_name, filter)?,
+        };
+        options.apply_filtered(record_batch)
+    }
+
+    /// Feature tables of a database file are read a FID range at a time.
+    fn read_layer_batches(
+        &self,
+        layer_name: &str,
+        options: &ReadOptions,
+        batch_size: usize,
+    ) -> anyhow::Result<RecordBatches> {
+        match self.connection.path().filter(|path| path.is_file()) {
+            Some(path) if !tiles::is_tile_table(&self.connection, layer_name)? => {
+                parallel::read_batches(path, layer_name, options, batch_size)
+            }
+            _ => Ok(driver::split_record_batch(
+                self.read_layer(layer_name, options)?,
+                batch_size,
+            )),
+        }
+    }
+
+    fn crs_definition(&self, authority: &str, code: i32) -> anyhow::Result<Option<String>> {
+        Ok(get_crs_definition(&self.connection, authority, code)?)
+    }
+}
+
 #[allow(dead_code)]
 fn get_bounds(connection: &Connection, layer: &str) -> rusqlite::Result<[f64; 4]> {
     let mut statement = connection
@@ -231,8 +436,425 @@ fn get_bounds(connection: &Connection, layer: &str) -> rusqlite::Result<[f64; 4]
     })
 }
 
+/// Options controlling how [`write_layer`] creates a feature table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOptions {
+    /// Name of the geometry column in the `RecordBatch` and the new table.
+    pub geometry_column: String,
+    /// `srs_id` recorded in `gpkg_contents` and every geometry blob.
+    pub srs_id: i32,
+    /// The WKT definition recorded for a new `srs_id`, required unless it is
+    /// already defined or is EPSG:4326 or EPSG:3857.
+    pub srs_definition: Option<String>,
+    /// Create and bulk-load the `gpkg_rtree_index` extension.
+    pub spatial_index: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            geometry_column: "geom".to_string(),
+            srs_id: -1,
+            srs_definition: None,
+            spatial_index: true,
+        }
+    }
+}
+
+const GEOPACKAGE_APPLICATION_ID: i32 = 0x4750_4B47;
+const GEOPACKAGE_USER_VERSION: i32 = 10_300;
+
+pub(crate) fn create_core_tables(connection: &Connection) -> rusqlite::Result<()> {
+    connection.execute_batch(&format!(
+        "PRAGMA application_id = {};
+        PRAGMA user_version = {};
+        CREATE TABLE IF NOT EXISTS gpkg_spatial_ref_sys (
+            srs_name TEXT NOT NULL,
+            srs_id INTEGER PRIMARY KEY,
+            organization TEXT NOT NULL,
+            organization_coordsys_id INTEGER NOT NULL,
+            definition TEXT NOT NULL,
+            description TEXT
+        );
+        INSERT OR IGNORE INTO gpkg_spatial_ref_sys VALUES
+            ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', 'undefined cartesian coordinate reference system'),
+            ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', 'undefined geographic coordinate reference system');
+        CREATE TABLE IF NOT EXISTS gpkg_contents (
+            table_name TEXT NOT NULL PRIMARY KEY,
+            data_type TEXT NOT NULL,
+            identifier TEXT UNIQUE,
+            description TEXT DEFAULT '',
+            last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+            min_x DOUBLE,
+            min_y DOUBLE,
+            max_x DOUBLE,
+            max_y DOUBLE,
+            srs_id INTEGER,
+            CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+        );
+        CREATE TABLE IF NOT EXISTS gpkg_geometry_columns (
+            table_name TEXT NOT NULL,
+            column_name TEXT NOT NULL,
+            geometry_type_name TEXT NOT NULL,
+            srs_id INTEGER NOT NULL,
+            z TINYINT NOT NULL,
+            m TINYINT NOT NULL,
+            CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name),
+            CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name),
+            CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id)
+        );",
+        GEOPACKAGE_APPLICATION_ID, GEOPACKAGE_USER_VERSION
+    ))
+}
+
+pub(crate) const EPSG_4326: &str = r#"GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AXIS["Latitude",NORTH],AXIS["Longitude",EAST],AUTHORITY["EPSG","4326"]]"#;
+pub(crate) const EPSG_3857: &str = r#"PROJCS["WGS 84 / Pseudo-Mercator",GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563,AUTHORITY["EPSG","7030"]],AUTHORITY["EPSG","6326"]],PRIMEM["Greenwich",0,AUTHORITY["EPSG","8901"]],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4326"]],PROJECTION["Mercator_1SP"],PARAMETER["central_meridian",0],PARAMETER["scale_factor",1],PARAMETER["false_easting",0],PARAMETER["false_northing",0],UNIT["metre",1,AUTHORITY["EPSG","9001"]],AXIS["Easting",EAST],AXIS["Northing",NORTH],EXTENSION["PROJ4","+proj=merc +a=6378137 +b=6378137 +lat_ts=0 +lon_0=0 +x_0=0 +y_0=0 +k=1 +units=m +nadgrids=@null +wktext +no_defs"],AUTHORITY["EPSG","3857"]]"#;
+
+/// The name and WKT of the EPSG CRSs that are written without a definition
+/// from the source, such as the web mercator of tile pyramids.
+fn well_known_srs(srs_id: i32) -> Option<(&'static str, &'static str)> {
+    match srs_id {
+        4326 => Some(("WGS 84 geodetic", EPSG_4326)),
+        3857 => Some(("WGS 84 / Pseudo-Mercator", EPSG_3857)),
+        _ => None,
+    }
+}
+
+/// Record `srs_id` as an EPSG code with the WKT `definition`, falling back to
+/// the WKT of well known codes, unless it is already defined. Codes that are
+/// neither defined nor given a definition are an error.
+pub(crate) fn insert_spatial_ref_sys(
+    connection: &Connection,
+    srs_id: i32,
+    definition: Option<&str>,
+) -> anyhow::Result<()> {
+    let defined: bool = connection.query_row(
+        "SELECT EXISTS (SELECT 1 FROM gpkg_spatial_ref_sys WHERE srs_id = :srs_id)",
+        named_params! {":srs_id": srs_id},
+        |row| row.get(0),
+    )?;
+    if defined {
+        return Ok(());
+    }
+    let well_known = well_known_srs(srs_id);
+    let srs_name =
+        well_known.map_or_else(|| format!("EPSG:{}", srs_id), |(name, _)| name.to_string());
+    let definition = definition
+        .or(well_known.map(|(_, definition)| definition))
+        .context(format!("No WKT definition of EPSG:{}", srs_id))?;
+    connection.execute(
+        "INSERT INTO gpkg_spatial_ref_sys VALUES (:srs_name, :srs_id, 'EPSG', :srs_id, :definition, NULL)",
+        named_params! {
+            ":srs_name": srs_name,
+            ":srs_id": srs_id,
+            ":definition": definition,
+        },
+    )?;
+    Ok(())
+}
+
+fn create_spatial_index(
+    connection: &Connection,
+    layer: &str,
+    geometry_column: &str,
+    fid_column: &str,
+    envelopes: &[(i64, [f64; 4])],
+) -> rusqlite::Result<()> {
+    let rtree_name = format!("rtree_{}_{}", layer, geometry_column);
+    let rtree = quote_identifier(&rtree_name);
+    let trigger = |suffix: &str| quote_identifier(&format!("{}_{}", rtree_name, suffix));
+    let t = quote_identifier(layer);
+    let c = quote_identifier(geometry_column);
+    let i = quote_identifier(fid_column);
+    connection.execute_batch(&format!(
+        r#"CREATE TABLE IF NOT EXISTS gpkg_extensions (
+            table_name TEXT,
+            column_name TEXT,
+            extension_name TEXT NOT NULL,
+            definition TEXT NOT NULL,
+            scope TEXT NOT NULL,
+            CONSTRAINT ge_tce UNIQUE (table_name, column_name, extension_name)
+        );
+        CREATE VIRTUAL TABLE {rtree} USING rtree(id, minx, maxx, miny, maxy);"#
+    ))?;
+    connection.execute(
+        "INSERT INTO gpkg_extensions VALUES (:table_name, :column_name, 'gpkg_rtree_index', 'http://www.geopackage.org/spec120/#extension_rtree', 'write-only')",
+        named_params! {
+            ":table_name": layer,
+            ":column_name": geometry_column,
+        },
+    )?;
+
+    let mut statement = connection.prepare(&format!(
+        r#"INSERT INTO {rtree} VALUES (:id, :minx, :maxx, :miny, :maxy)"#
+    ))?;
+    for (id, [min_x, min_y, max_x, max_y]) in envelopes {
+        statement.execute(named_params! {
+            ":id": id,
+            ":minx": min_x,
+            ":maxx": max_x,
+            ":miny": min_y,
+            ":maxy": max_y,
+        })?;
+    }
+
+    // The standard triggers from the GeoPackage RTree Spatial Indexes extension.
+    let insert = trigger("insert");
+    let update1 = trigger("update1");
+    let update2 = trigger("update2");
+    let update3 = trigger("update3");
+    let update4 = trigger("update4");
+    let delete = trigger("delete");
+    connection.execute_batch(&format!(
+        r#"CREATE TRIGGER {insert} AFTER INSERT ON {t}
+        WHEN (new.{c} NOT NULL AND NOT ST_IsEmpty(NEW.{c}))
+        BEGIN
+            INSERT OR REPLACE INTO {rtree} VALUES (
+                NEW.{i},
+                ST_MinX(NEW.{c}), ST_MaxX(NEW.{c}),
+                ST_MinY(NEW.{c}), ST_MaxY(NEW.{c})
+            );
+        END;
+        CREATE TRIGGER {update1} AFTER UPDATE OF {c} ON {t}
+        WHEN OLD.{i} = NEW.{i} AND (NEW.{c} NOTNULL AND NOT ST_IsEmpty(NEW.{c}))
+        BEGIN
+            INSERT OR REPLACE INTO {rtree} VALUES (
+                NEW.{i},
+                ST_MinX(NEW.{c}), ST_MaxX(NEW.{c}),
+                ST_MinY(NEW.{c}), ST_MaxY(NEW.{c})
+            );
+        END;
+        CREATE TRIGGER {update2} AFTER UPDATE OF {c} ON {t}
+        WHEN OLD.{i} = NEW.{i} AND (NEW.{c} ISNULL OR ST_IsEmpty(NEW.{c}))
+        BEGIN
+            DELETE FROM {rtree} WHERE id = OLD.{i};
+        END;
+        CREATE TRIGGER {update3} AFTER UPDATE ON {t}
+        WHEN OLD.{i} != NEW.{i} AND (NEW.{c} NOTNULL AND NOT ST_IsEmpty(NEW.{c}))
+        BEGIN
+            DELETE FROM {rtree} WHERE id = OLD.{i};
+            INSERT OR REPLACE INTO {rtree} VALUES (
+                NEW.{i},
+                ST_MinX(NEW.{c}), ST_MaxX(NEW.{c}),
+                ST_MinY(NEW.{c}), ST_MaxY(NEW.{c})
+            );
+        END;
+        CREATE TRIGGER {update4} AFTER UPDATE ON {t}
+        WHEN OLD.{i} != NEW.{i} AND (NEW.{c} ISNULL OR ST_IsEmpty(NEW.{c}))
+        BEGIN
+            DELETE FROM {rtree} WHERE id IN (OLD.{i}, NEW.{i});
+        END;
+        CREATE TRIGGER {delete} AFTER DELETE ON {t}
+        WHEN old.{c} NOT NULL
+        BEGIN
+            DELETE FROM {rtree} WHERE id = OLD.{i};
+        END;"#
+    ))
+}
+
+fn get_sql_name(data_type: &DataType) -> anyhow::Result<&'static str> {
+    let sql_name = match data_type {
+        DataType::Boolean => "BOOLEAN",
+        DataType::Int8 => "TINYINT",
+        DataType::Int16 => "SMALLINT",
+        DataType::Int32 => "MEDIUMINT",
+        DataType::Int64 => "INTEGER",
+        DataType::Float32 => "FLOAT",
+        DataType::Float64 => "REAL",
+        DataType::Utf8 => "TEXT",
+        DataType::Binary => "BLOB",
+        data_type => anyhow::bail!("Unsupported data type {:?}", data_type),
+    };
+    Ok(sql_name)
+}
+
+fn get_value(array: &dyn Array, index: usize) -> anyhow::Result<Value> {
+    if array.is_null(index) {
+        return Ok(Value::Null);
+    }
+    let value = match array.data_type() {
+        DataType::Boolean => Value::Integer(as_boolean_array(array).value(index) as i64),
+        DataType::Int8 => Value::Integer(as_primitive_array::<Int8Type>(array).value(index) as i64),
+        DataType::Int16 => {
+            Value::Integer(as_primitive_array::<Int16Type>(array).value(index) as i64)
+        }
+        DataType::Int32 => {
+            Value::Integer(as_primitive_array::<Int32Type>(array).value(index) as i64)
+        }
+        DataType::Int64 => Value::Integer(as_primitive_array::<Int64Type>(array).value(index)),
+        DataType::Float32 => {
+            Value::Real(as_primitive_array::<Float32Type>(array).value(index) as f64)
+        }
+        DataType::Float64 => Value::Real(as_primitive_array::<Float64Type>(array).value(index)),
+        DataType::Utf8 => Value::Text(as_string_array(array).value(index).to_string()),
+        DataType::Binary => Value::Blob(
+            array
+                .as_any()
+                .downcast_ref::<BinaryArray>()
+                .unwrap()
+                .value(index)
+                .to_vec(),
+        ),
+        data_type => anyhow::bail!("Unsupported data type {:?}", data_type),
+    };
+    Ok(value)
+}
+
+fn get_geometry_type_name(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::FixedSizeList(_field, 2) => "POINT",
+        _ => "GEOMETRY",
+    }
+}
+
+/// Write `record_batch` to a new feature table called `layer_name`, creating
+/// the GeoPackage core tables if `connection` does not have them yet.
+///
+/// A column named `fid` is used as the primary key, otherwise one is added.
+pub fn write_layer(
+    connection: &Connection,
+    layer_name: &str,
+    record_batch: &RecordBatch,
+    options: &WriteOptions,
+) -> anyhow::Result<()> {
+    let schema = record_batch.schema();
+    let geometry_index = schema.index_of(&options.geometry_column).context(format!(
+        "Missing geometry column {}",
+        options.geometry_column
+    ))?;
+    let fid_index = schema
+        .fields()
+        .iter()
+        .position(|field| field.name().eq_ignore_ascii_case("fid"));
+    let fid_column = fid_index
+        .map(|index| schema.field(index).name().as_str())
+        .unwrap_or("fid");
+
+    let transaction = connection.unchecked_transaction()?;
+    create_core_tables(&transaction)?;
+    insert_spatial_ref_sys(
+        &transaction,
+        options.srs_id,
+        options.srs_definition.as_deref(),
+    )?;
+
+    let mut column_definitions = vec![format!(
+        "{} INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL",
+        quote_identifier(fid_column)
+    )];
+    for (index, field) in schema.fields().iter().enumerate() {
+        if Some(index) == fid_index {
+            continue;
+        }
+        let sql_name = if index == geometry_index {
+            get_geometry_type_name(field.data_type())
+        } else {
+            get_sql_name(field.data_type())?
+        };
+        column_definitions.push(format!("{} {}", quote_identifier(field.name()), sql_name));
+    }
+    transaction.execute_batch(&format!(
+        "CREATE TABLE {} ({});",
+        quote_identifier(layer_name),
+        column_definitions.join(", ")
+    ))?;
+
+    let column_names: Vec<String> = schema
+        .fields()
+        .iter()
+        .map(|field| quote_identifier(field.name()))
+        .collect();
+    let placeholders = vec!["?"; column_names.len()].join(", ");
+    let mut statement = transaction.prepare(&format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_identifier(layer_name),
+        column_names.join(", "),
+        placeholders
+    ))?;
+
+    let mut envelopes: Vec<(i64, [f64; 4])> = Vec::new();
+    let (mut has_z, mut has_m) = (false, false);
+    for row in 0..record_batch.num_rows() {
+        let mut values: Vec<Value> = Vec::with_capacity(record_batch.num_columns());
+        let mut envelope = None;
+        for (index, column) in record_batch.columns().iter().enumerate() {
+            if index == geometry_index {
+                let geometry = geoarrow::get_geometry(column.as_ref(), row)?;
+                envelope = geometry.as_ref().and_then(WkbGeometry::envelope);
+                if let Some(geometry) = &geometry {
+                    has_z |= geometry.dimension().has_z();
+                    has_m |= geometry.dimension().has_m();
+                }
+                let value = geometry
+                    .map(|geometry| {
+                        let gpb = StandardGeoPackageBinary::new(options.srs_id, geometry);
+                        Value::Blob(gpb.to_bytes())
+                    })
+                    .unwrap_or(Value::Null);
+                values.push(value);
+            } else {
+                values.push(get_value(column.as_ref(), row)?);
+            }
+        }
+        statement.execute(rusqlite::params_from_iter(values))?;
+        if let Some(envelope) = envelope {
+            envelopes.push((transaction.last_insert_rowid(), envelope));
+        }
+    }
+    drop(statement);
+
+    let extent = envelopes
+        .iter()
+        .map(|(_id, envelope)| *envelope)
+        .reduce(|a, b| {
+            [
+                a[0].min(b[0]),
+                a[1].min(b[1]),
+                a[2].max(b[2]),
+                a[3].max(b[3]),
+            ]
+        });
+    transaction.execute(
+        "INSERT INTO gpkg_contents (table_name, data_type, identifier, min_x, min_y, max_x, max_y, srs_id)
+        VALUES (:layer, 'features', :layer, :min_x, :min_y, :max_x, :max_y, :srs_id)",
+        named_params! {
+            ":layer": layer_name,
+            ":min_x": extent.map(|extent| extent[0]),
+            ":min_y": extent.map(|extent| extent[1]),
+            ":max_x": extent.map(|extent| extent[2]),
+            ":max_y": extent.map(|extent| extent[3]),
+            ":srs_id": options.srs_id,
+        },
+    )?;
+    transaction.execute(
+        "INSERT INTO gpkg_geometry_columns VALUES (:layer, :column, :type_name, :srs_id, :z, :m)",
+        named_params! {
+            ":layer": layer_name,
+            ":column": options.geometry_column,
+            ":type_name": get_geometry_type_name(schema.field(geometry_index).data_type()),
+            ":srs_id": options.srs_id,
+            ":z": has_z,
+            ":m": has_m,
+        },
+    )?;
+
+    if options.spatial_index {
+        create_spatial_index(
+            &transaction,
+            layer_name,
+            &options.geometry_column,
+            fid_column,
+            &envelopes,
+        )
+        .context("Failed to create spatial index")?;
+    }
+
+    transaction.commit()?;
+    Ok(())
+}
+
 #[bitfield]
-#[derive(BinRead, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[br(map = Self::from_bytes)]
 pub struct Flags {
     #[allow(dead_code)]
@@ -269,10 +891,48 @@ pub struct StandardGeoPackageBinary {
     pub geometry: WkbGeometry,
 }
 
+impl StandardGeoPackageBinary {
+    /// Wrap `geometry` in a little-endian header carrying its XY envelope.
+    pub fn new(srs_id: i32, geometry: WkbGeometry) -> Self {
+        let envelope = geometry.envelope();
+        let flags = Flags::new()
+            .with_byte_order(1)
+            .with_envelope_size(envelope.map_or(0, |_| 1))
+            .with_empty_geometry_flag(envelope.map_or(1, |_| 0))
+            .with_gpb_type(0)
+            .with_reserved(0);
+        let header = GeoPackageBinaryHeader {
+            version: 0,
+            flags,
+            srs_id: srs_id as u32,
+            envelope: envelope
+                .map(|[min_x, min_y, max_x, max_y]| vec![min_x, max_x, min_y, max_y])
+                .unwrap_or_default(),
+        };
+        StandardGeoPackageBinary { header, geometry }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = b"GP".to_vec();
+        bytes.push(self.header.version);
+        bytes.extend_from_slice(&self.header.flags.into_bytes());
+        bytes.extend_from_slice(&self.header.srs_id.to_le_bytes());
+        self.header
+            .envelope
+            .iter()
+            .for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+        self.geometry.write_wkb(&mut bytes);
+        bytes
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::wkb::{Coordinate, WkbByteOrder::Ndr, WkbGeometryType::Point, WkbPoint};
+    use crate::{
+        dataset::Dataset,
+        wkb::{Coordinate, WkbPoint},
+    };
     use binread::{io::Cursor, BinReaderExt};
 
     #[test]
@@ -295,6 +955,33 @@ mod test {
         assert_eq!(expected_bounds, recieved_bounds)
     }
 
+    #[test]
+    fn test_get_filtered_layer() {
+        let connection = Connection::open("Data/point.gpkg").unwrap();
+
+        let layer = get_filtered_layer(&connection, "point", Some("name = 'point_2'")).unwrap();
+        assert_eq!(1, layer.num_rows());
+        assert!(get_filtered_layer(&connection, "point", Some("missing = 1")).is_err());
+    }
+
+    #[test]
+    fn test_get_layer_invalid_geometry() {
+        let source = Connection::open("Data/point.gpkg").unwrap();
+        let layer = get_layer(&source, "point").unwrap();
+
+        let connection = Connection::open_in_memory().unwrap();
+        let options = WriteOptions {
+            spatial_index: false,
+            ..Default::default()
+        };
+        write_layer(&connection, "point", &layer, &options).unwrap();
+        connection
+            .execute("UPDATE point SET geom = x'4750' WHERE fid = 1", [])
+            .unwrap();
+
+        assert!(get_layer(&connection, "point").is_err());
+    }
+
     #[test]
     fn test_standard_geopackage_binary() {
         let expected_gpb_header_flags = Flags::new()
@@ -311,11 +998,7 @@ mod test {
             envelope: [].to_vec(),
         };
 
-        let expected_gpb_geometry = WkbPoint {
-            byte_order: Ndr,
-            wkb_type: Point,
-            point: Coordinate { x: 0.0, y: 0.0 },
-        };
+        let expected_gpb_geometry = WkbPoint::new(Coordinate::new(0.0, 0.0));
 
         let expected_gpb = StandardGeoPackageBinary {
             header: expected_gpb_header,
@@ -330,4 +1013,157 @@ mod test {
 
         assert_eq!(expected_gpb, recieved_gpb)
     }
+
+    #[test]
+    fn test_geopackage_binary_round_trip() {
+        let geometry = WkbGeometry::Point(WkbPoint::try_from([1.0, 2.0]).unwrap());
+        let expected_gpb = StandardGeoPackageBinary::new(27700, geometry);
+
+        let mut reader = Cursor::new(expected_gpb.to_bytes());
+        let recieved_gpb: StandardGeoPackageBinary = reader.read_ne().unwrap();
+
+        assert_eq!(expected_gpb, recieved_gpb);
+        assert_eq!(vec![1.0, 1.0, 2.0, 2.0], recieved_gpb.header.envelope);
+    }
+
+    #[test]
+    fn test_write_layer() {
+        let source = Connection::open("Data/point.gpkg").unwrap();
+        let expected_layer = get_layer(&source, "point").unwrap();
+
+        let connection = Connection::open_in_memory().unwrap();
+        let options = WriteOptions {
+            srs_id: 27700,
+            srs_definition: get_crs_definition(&source, "EPSG", 27700).unwrap(),
+            ..Default::default()
+        };
+        write_layer(&connection, "point", &expected_layer, &options).unwrap();
+
+        let recieved_layer = get_layer(&connection, "point").unwrap();
+        assert_eq!(expected_layer, recieved_layer);
+        assert_eq!(vec!["point".to_string()], list_layers(&connection).unwrap());
+        assert_eq!(
+            [0.0f64, 0.0f64, 1.0f64, 1.0f64],
+            get_bounds(&connection, "point").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_write_layer_quoted_name() {
+        let source = Connection::open("Data/point.gpkg").unwrap();
+        let expected_layer = get_layer(&source, "point").unwrap();
+
+        let connection = Connection::open_in_memory().unwrap();
+        let layer_name = r#"point's "layer""#;
+        let options = WriteOptions {
+            srs_id: 27700,
+            srs_definition: get_crs_definition(&source, "EPSG", 27700).unwrap(),
+            ..Default::default()
+        };
+        write_layer(&connection, layer_name, &expected_layer, &options).unwrap();
+
+        let recieved_layer = get_layer(&connection, layer_name).unwrap();
+        assert_eq!(expected_layer, recieved_layer);
+        let rtree_count: i64 = connection
+            .query_row(
+                r#"SELECT COUNT(*) FROM "rtree_point's ""layer""_geom""#,
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(2, rtree_count);
+    }
+
+    #[test]
+    fn test_write_layer_spatial_index() {
+        let source = Connection::open("Data/point.gpkg").unwrap();
+        let layer = get_layer(&source, "point").unwrap();
+
+        let connection = Connection::open_in_memory().unwrap();
+        write_layer(&connection, "point", &layer, &WriteOptions::default()).unwrap();
+
+        let mut statement = connection
+            .prepare("SELECT id, minx, maxx, miny, maxy FROM rtree_point_geom ORDER BY id")
+            .unwrap();
+        let recieved_entries: Vec<(i64, f64, f64, f64, f64)> = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            vec![(1, 0.0, 0.0, 0.0, 0.0), (2, 1.0, 1.0, 1.0, 1.0)],
+            recieved_entries
+        );
+
+        let extension: String = connection
+            .query_row(
+                "SELECT extension_name FROM gpkg_extensions WHERE table_name = 'point' AND column_name = 'geom'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!("gpkg_rtree_index", extension);
+    }
+
+    #[test]
+    fn test_write_layer_without_spatial_index() {
+        let source = Connection::open("Data/point.gpkg").unwrap();
+        let layer = get_layer(&source, "point").unwrap();
+
+        let connection = Connection::open_in_memory().unwrap();
+        let options = WriteOptions {
+            spatial_index: false,
+            ..Default::default()
+        };
+        write_layer(&connection, "point", &layer, &options).unwrap();
+
+        let rtree_count: i64 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'rtree_point_geom'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(0, rtree_count);
+    }
+
+    #[test]
+    fn test_write_layer_dimensions() {
+        let options = ReadOptions {
+            columns: Some(vec!["name".to_string(), "geom".to_string()]),
+            ..Default::default()
+        };
+        let layer = Dataset::open("Data/polygon_z.shp")
+            .unwrap()
+            .get_layer_with_options("polygon_z", &options)
+            .unwrap();
+        let connection = Connection::open_in_memory().unwrap();
+        write_layer(&connection, "polygon_z", &layer, &WriteOptions::default()).unwrap();
+
+        let (z, m): (i64, i64) = connection
+            .query_row(
+                "SELECT z, m FROM gpkg_geometry_columns WHERE table_name = 'polygon_z'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((1, 1), (z, m));
+
+        let options = WriteOptions {
+            srs_id: 27700,
+            ..Default::default()
+        };
+        let error = write_layer(&connection, "polygon_z_27700", &layer, &options)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("EPSG:27700"));
+    }
 }
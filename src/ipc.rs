@@ -0,0 +1,357 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use arrow::{
+    datatypes::{Schema, SchemaRef},
+    ipc::{
+        reader::{FileReader, StreamReader},
+        writer::{FileWriter, IpcWriteOptions, StreamWriter},
+        CompressionType,
+    },
+    record_batch::RecordBatch,
+};
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver},
+    geoarrow,
+    geoparquet::{self, GeoMetadata, GEO_METADATA_KEY},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Lz4,
+    Zstd,
+}
+
+/// Options controlling how [`write_file`] and [`write_stream`] encode a layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Compress record batch bodies, which requires IPC format version 5.
+    pub compression: Option<Compression>,
+    /// WKT definitions of the geometry columns' CRSs for the `geo` metadata,
+    /// as in [`geoparquet::WriteOptions::crs_definitions`].
+    pub crs_definitions: HashMap<String, String>,
+}
+
+impl WriteOptions {
+    fn ipc_write_options(&self) -> anyhow::Result<IpcWriteOptions> {
+        let compression = self.compression.map(|compression| match compression {
+            Compression::Lz4 => CompressionType::LZ4_FRAME,
+            Compression::Zstd => CompressionType::ZSTD,
+        });
+        let options = IpcWriteOptions::default().try_with_compression(compression)?;
+        Ok(options)
+    }
+}
+
+/// Re-attach `record_batches` to a schema carrying the `geo` metadata read by
+/// GeoPandas, on top of the per-field GeoArrow extension metadata.
+fn with_geo_metadata(
+    record_batches: &[RecordBatch],
+    crs_definitions: &HashMap<String, String>,
+) -> anyhow::Result<(Arc<Schema>, Vec<RecordBatch>)> {
+    let input_schema = record_batches
+        .first()
+        .map(RecordBatch::schema)
+        .context("No record batches to write")?;
+    let geo_metadata = geoparquet::get_geo_metadata(record_batches, crs_definitions)?;
+
+    let mut metadata: HashMap<String, String> = input_schema.metadata().clone();
+    metadata.insert(
+        GEO_METADATA_KEY.to_string(),
+        serde_json::to_string(&geo_metadata)?,
+    );
+    let schema = Arc::new(Schema::new_with_metadata(
+        input_schema.fields().clone(),
+        metadata,
+    ));
+    let record_batches = record_batches
+        .iter()
+        .map(|record_batch| RecordBatch::try_new(schema.clone(), record_batch.columns().to_vec()))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((schema, record_batches))
+}
+
+/// Write the `RecordBatch`es of a layer to `writer` in the Arrow IPC file
+/// format, also known as Feather v2.
+pub fn write_file<W: Write>(
+    writer: W,
+    record_batches: &[RecordBatch],
+    options: &WriteOptions,
+) -> anyhow::Result<()> {
+    let (schema, record_batches) = with_geo_metadata(record_batches, &options.crs_definitions)?;
+    let mut writer =
+        FileWriter::try_new_with_options(writer, &schema, options.ipc_write_options()?)?;
+    for record_batch in &record_batches {
+        writer.write(record_batch)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write the `RecordBatch`es of a layer to `writer` in the Arrow IPC stream
+/// format.
+pub fn write_stream<W: Write>(
+    writer: W,
+    record_batches: &[RecordBatch],
+    options: &WriteOptions,
+) -> anyhow::Result<()> {
+    let (schema, record_batches) = with_geo_metadata(record_batches, &options.crs_definitions)?;
+    let mut writer =
+        StreamWriter::try_new_with_options(writer, &schema, options.ipc_write_options()?)?;
+    for record_batch in &record_batches {
+        writer.write(record_batch)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcFormat {
+    /// The random-access file format, `.arrow` or `.feather`.
+    File,
+    /// The streaming format, `.arrows`.
+    Stream,
+}
+
+/// An Arrow IPC file, exposed as a single layer named after the file stem.
+pub struct IpcFile {
+    path: PathBuf,
+    format: IpcFormat,
+    schema: SchemaRef,
+}
+
+/// Tag the geometry columns named in GeoPandas' `geo` schema metadata that
+/// were written without GeoArrow extension metadata.
+fn tag_geometry_columns(schema: &Schema) -> Schema {
+    let geo_metadata: Option<GeoMetadata> = schema
+        .metadata()
+        .get(GEO_METADATA_KEY)
+        .and_then(|geo| serde_json::from_str(geo).ok());
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let geo_column = geo_metadata
+                .as_ref()
+                .and_then(|geo_metadata| geo_metadata.columns.get(field.name()));
+            match geo_column {
+                Some(geo_column) if !geoarrow::is_geometry_field(field) => {
+                    geoarrow::geometry_field(
+                        field.name(),
+                        field.data_type().clone(),
+                        &geoparquet::get_extension_metadata(geo_column),
+                    )
+                }
+                _ => field.clone(),
+            }
+        })
+        .collect();
+    Schema::new_with_metadata(fields, schema.metadata().clone())
+}
+
+impl IpcFile {
+    pub fn open(path: &Path, format: IpcFormat) -> anyhow::Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        let schema = match format {
+            IpcFormat::File => FileReader::try_new(file, None)?.schema(),
+            IpcFormat::Stream => StreamReader::try_new(file, None)?.schema(),
+        };
+        Ok(IpcFile {
+            path: path.to_path_buf(),
+            format,
+            schema: Arc::new(tag_geometry_columns(&schema)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn layer_name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Read the layer, decoding only the requested columns plus the primary
+    /// geometry column when a bbox filter needs it.
+    pub fn read(&self, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        let primary_column = geoarrow::primary_geometry_column(&self.schema);
+        let projection = match &options.columns {
+            None => None,
+            Some(columns) => {
+                let mut indices = columns
+                    .iter()
+                    .map(|column| self.schema.index_of(column))
+                    .collect::<Result<Vec<usize>, _>>()?;
+                if let (Some(_bbox), Some(column)) = (options.bbox, primary_column) {
+                    if !indices.contains(&column) {
+                        indices.push(column);
+                    }
+                }
+                indices.sort_unstable();
+                Some(indices)
+            }
+        };
+        let schema = Arc::new(match &projection {
+            Some(indices) => self.schema.project(indices)?,
+            None => self.schema.as_ref().clone(),
+        });
+
+        let file = BufReader::new(File::open(&self.path)?);
+        let record_batches = match self.format {
+            IpcFormat::File => {
+                FileReader::try_new(file, projection)?.collect::<Result<Vec<_>, _>>()?
+            }
+            IpcFormat::Stream => {
+                StreamReader::try_new(file, projection)?.collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        let record_batches = record_batches
+            .into_iter()
+            .map(|record_batch| {
+                RecordBatch::try_new(schema.clone(), record_batch.columns().to_vec())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let record_batch = RecordBatch::concat(&schema, &record_batches)?;
+        options.apply(record_batch)
+    }
+}
+
+impl Driver for IpcFile {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| matches!(format, Format::Ipc(_)))
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let format = match Format::detect(path)? {
+            Format::Ipc(format) => format,
+            _ => IpcFormat::File,
+        };
+        IpcFile::open(path, format)
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(vec![self.layer_name()])
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        Ok(self.schema())
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        self.read(options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        dataset::Dataset,
+        test_util::{point_layer, TempPath},
+    };
+    use std::io::Cursor;
+
+    fn assert_geometry_metadata(schema: &Schema) {
+        let geometry_field = schema.field_with_name("geom").unwrap();
+        assert!(geoarrow::is_geometry_field(geometry_field));
+        assert_eq!(&geoarrow::point_data_type(), geometry_field.data_type());
+        assert_eq!(
+            Some(("EPSG", 27700)),
+            geoarrow::get_extension_metadata(geometry_field)
+                .unwrap()
+                .authority_code()
+        );
+        let geo_metadata: GeoMetadata =
+            serde_json::from_str(&schema.metadata()[GEO_METADATA_KEY]).unwrap();
+        assert_eq!("geom", geo_metadata.primary_column);
+        assert_eq!("point", geo_metadata.columns["geom"].encoding);
+    }
+
+    #[test]
+    fn test_write_file() {
+        let layer = point_layer();
+        for compression in [None, Some(Compression::Lz4), Some(Compression::Zstd)] {
+            let mut buffer = Vec::new();
+            write_file(
+                &mut buffer,
+                std::slice::from_ref(&layer),
+                &WriteOptions {
+                    compression,
+                    ..WriteOptions::default()
+                },
+            )
+            .unwrap();
+
+            let reader = FileReader::try_new(Cursor::new(buffer), None).unwrap();
+            assert_geometry_metadata(&reader.schema());
+            let recieved_batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+            assert_eq!(layer.columns(), recieved_batches[0].columns());
+        }
+    }
+
+    #[test]
+    fn test_write_stream() {
+        let layer = point_layer();
+        let mut buffer = Vec::new();
+        let options = WriteOptions {
+            compression: Some(Compression::Zstd),
+            ..WriteOptions::default()
+        };
+        write_stream(&mut buffer, std::slice::from_ref(&layer), &options).unwrap();
+
+        let reader = StreamReader::try_new(Cursor::new(buffer), None).unwrap();
+        assert_geometry_metadata(&reader.schema());
+        let recieved_batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(layer.columns(), recieved_batches[0].columns());
+    }
+
+    #[test]
+    fn test_read_layer() {
+        let layer = point_layer();
+        let path = TempPath::new("ogr2arrow-point.feather");
+        let file = File::create(&path).unwrap();
+        write_file(file, std::slice::from_ref(&layer), &WriteOptions::default()).unwrap();
+
+        let dataset = Dataset::open(path.to_str().unwrap()).unwrap();
+        let recieved_layer = dataset.get_layer("ogr2arrow-point").unwrap();
+
+        assert_geometry_metadata(&recieved_layer.schema());
+        assert_eq!(layer.columns(), recieved_layer.columns());
+    }
+
+    #[test]
+    fn test_read_stream_with_options() {
+        let layer = point_layer();
+        let path = TempPath::new("ogr2arrow-point.arrows");
+        let file = File::create(&path).unwrap();
+        write_stream(file, std::slice::from_ref(&layer), &WriteOptions::default()).unwrap();
+
+        let file = IpcFile::open(&path, IpcFormat::Stream).unwrap();
+        let options = ReadOptions {
+            bbox: Some([0.5, 0.5, 2.0, 2.0]),
+            columns: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let recieved_layer = file.read(&options).unwrap();
+
+        assert_eq!(1, recieved_layer.num_columns());
+        assert_eq!(&layer.column(2).slice(1, 1), recieved_layer.column(0));
+    }
+}
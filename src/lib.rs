@@ -1,3 +1,26 @@
+pub mod convert;
+pub mod csv;
 pub mod dataset;
+pub mod driver;
+pub mod ffi;
+pub mod fgb;
+pub mod geoarrow;
+pub mod geojson;
+pub mod geoparquet;
 pub mod gpkg;
+pub mod ipc;
+pub mod mbtiles;
+pub mod mvt;
+pub mod parallel;
+pub mod projjson;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod shapefile;
+pub mod spatialite;
+#[cfg(feature = "datafusion")]
+pub mod table_provider;
+#[cfg(test)]
+mod test_util;
+pub mod tiles;
 pub mod wkb;
+pub mod wkt;
@@ -1,11 +1,237 @@
-use ogr2arrow::dataset::Dataset;
+use std::path::Path;
 
-fn main() -> anyhow::Result<()> {
-    let dataset = Dataset::open("Data/point.gpkg")?;
+use anyhow::Context;
+use arrow::{datatypes::Field, util::pretty};
+use clap::{Parser, Subcommand};
+use ogr2arrow::{
+    convert::{self, Layout},
+    dataset::{Dataset, Format, GeometryFormat, ReadOptions},
+    geoarrow,
+    tiles::tile_schema,
+};
+
+/// Inspect and convert vector datasets without GDAL.
+#[derive(Parser)]
+#[command(name = "ogr2arrow", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the layers of a dataset.
+    Layers { file: String },
+    /// Describe the schema, feature count, bounds and SRS of a layer, or of
+    /// every layer.
+    Info { file: String, layer: Option<String> },
+    /// Convert a layer, or every layer, to another format. Tile pyramids are
+    /// copied whole between GeoPackage and MBTiles.
+    Convert {
+        input: String,
+        /// The output file, or directory with `--all-layers`.
+        output: String,
+        /// The layer to convert; required when the input has several.
+        #[arg(long, conflicts_with = "all_layers")]
+        layer: Option<String>,
+        /// Convert every layer, in parallel, into a file per layer in the
+        /// output directory.
+        #[arg(long)]
+        all_layers: bool,
+        /// With `--all-layers`, write each layer to `<name>/part-0.<format>`,
+        /// a directory per layer, instead.
+        #[arg(long, requires = "all_layers")]
+        partitioned: bool,
+        /// The output format as a file extension, e.g. `parquet`. Taken from
+        /// the output path by default.
+        #[arg(long)]
+        format: Option<String>,
+        /// An SQL expression rows must satisfy (GeoPackage, SpatiaLite and MBTiles).
+        #[arg(long = "where")]
+        filter: Option<String>,
+        /// Only convert features intersecting `min_x,min_y,max_x,max_y`.
+        #[arg(long, value_parser = parse_bbox, allow_hyphen_values = true)]
+        bbox: Option<[f64; 4]>,
+        /// Only convert these comma separated columns.
+        #[arg(long, value_delimiter = ',')]
+        select: Option<Vec<String>>,
+    },
+    /// Print the first rows of a layer, with geometries as WKT.
+    Head {
+        file: String,
+        layer: Option<String>,
+        #[arg(short = 'n', long, default_value_t = 10)]
+        rows: usize,
+    },
+}
+
+fn parse_bbox(value: &str) -> anyhow::Result<[f64; 4]> {
+    let values = value
+        .split(',')
+        .map(|value| value.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()?;
+    values
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Expected min_x,min_y,max_x,max_y"))
+}
 
-    let layer = dataset.get_layer("point")?;
+/// The requested layer, or the only layer of the dataset.
+fn select_layer(dataset: &Dataset, layer: Option<String>) -> anyhow::Result<String> {
+    if let Some(layer) = layer {
+        return Ok(layer);
+    }
+    match dataset.list_layers()?.as_slice() {
+        [layer] => Ok(layer.clone()),
+        layers => anyhow::bail!("Choose one of the layers {}", layers.join(", ")),
+    }
+}
+
+/// The GeoArrow extension name of a geometry field, or the data type of any
+/// other field.
+fn describe_type(field: &Field) -> String {
+    field
+        .metadata()
+        .as_ref()
+        .and_then(|metadata| metadata.get(geoarrow::EXTENSION_NAME_KEY))
+        .cloned()
+        .unwrap_or_else(|| format!("{:?}", field.data_type()))
+}
 
-    dbg!(layer);
+fn print_info(dataset: &Dataset, layer_name: &str) -> anyhow::Result<()> {
+    let layer = dataset.layer(layer_name)?;
+    let record_batch = layer.read()?;
 
+    println!("Layer: {}", layer.name());
+    println!("Features: {}", record_batch.num_rows());
+    if let Some(field) = layer.geometry_field() {
+        println!("Geometry: {}", field.name());
+        let crs = layer
+            .extension_metadata()
+            .and_then(|metadata| metadata.crs)
+            .unwrap_or_else(|| "unknown".to_string());
+        println!("SRS: {}", crs);
+        let index = record_batch.schema().index_of(field.name())?;
+        if let Some(bounds) = geoarrow::total_bounds(record_batch.column(index).as_ref())? {
+            println!(
+                "Bounds: ({}, {}) - ({}, {})",
+                bounds[0], bounds[1], bounds[2], bounds[3]
+            );
+        }
+    }
+    println!("Fields:");
+    for field in layer.schema().fields() {
+        println!("  {}: {}", field.name(), describe_type(field));
+    }
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Layers { file } => {
+            for layer in Dataset::open(file)?.list_layers()? {
+                println!("{}", layer);
+            }
+        }
+        Command::Info { file, layer } => {
+            let dataset = Dataset::open(file)?;
+            let layers = match layer {
+                Some(layer) => vec![layer],
+                None => dataset.list_layers()?,
+            };
+            for (index, layer) in layers.iter().enumerate() {
+                if index > 0 {
+                    println!();
+                }
+                print_info(&dataset, layer)?;
+            }
+        }
+        Command::Convert {
+            input,
+            output,
+            layer,
+            all_layers,
+            partitioned,
+            format,
+            filter,
+            bbox,
+            select,
+        } => {
+            let output = Path::new(&output);
+            let format = match format {
+                Some(format) => {
+                    Format::from_extension(&format).context(format!("Unknown format {}", format))?
+                }
+                None if all_layers => anyhow::bail!("Pass --format with --all-layers"),
+                None => output
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(Format::from_extension)
+                    .context("Pass --format for an output without a known extension")?,
+            };
+            let options = ReadOptions {
+                bbox,
+                columns: select,
+                filter,
+                ..Default::default()
+            };
+            if all_layers {
+                let layout = match partitioned {
+                    true => Layout::Partitioned,
+                    false => Layout::Files,
+                };
+                let paths = convert::convert_all(
+                    Path::new(&input),
+                    output,
+                    format,
+                    layout,
+                    &options,
+                    |progress| {
+                        let status = match progress.succeeded {
+                            true => "done",
+                            false => "failed",
+                        };
+                        eprintln!(
+                            "[{}/{}] {} {}",
+                            progress.completed, progress.total, progress.layer_name, status
+                        );
+                    },
+                )?;
+                for path in paths {
+                    println!("{}", path.display());
+                }
+                return Ok(());
+            }
+            let dataset = Dataset::open(&input)?;
+            let layer_name = select_layer(&dataset, layer)?;
+            if format == Format::MbTiles || *dataset.layer(&layer_name)?.schema() == tile_schema() {
+                anyhow::ensure!(
+                    options == ReadOptions::default(),
+                    "--where, --bbox and --select are not supported when copying tiles"
+                );
+                return convert::convert_tiles(Path::new(&input), &layer_name, output, format);
+            }
+            let record_batch = dataset.get_layer_with_options(&layer_name, &options)?;
+            let crs_definitions = dataset.crs_definitions(&record_batch.schema())?;
+            convert::write_layer(output, format, &layer_name, &record_batch, &crs_definitions)?;
+        }
+        Command::Head { file, layer, rows } => {
+            let dataset = Dataset::open(file)?;
+            let layer_name = select_layer(&dataset, layer)?;
+            let options = ReadOptions {
+                geometry_format: GeometryFormat::Wkt { precision: None },
+                ..Default::default()
+            };
+            let first_batch = dataset
+                .driver()
+                .read_layer_batches(&layer_name, &options, rows.max(1))
+                .and_then(|mut record_batches| record_batches.next().transpose())
+                .context(format!("Failed to get {}", layer_name))?;
+            let record_batches: Vec<_> = first_batch
+                .map(|record_batch| record_batch.slice(0, rows.min(record_batch.num_rows())))
+                .into_iter()
+                .collect();
+            pretty::print_batches(&record_batches)?;
+        }
+    }
     Ok(())
 }
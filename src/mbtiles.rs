@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
+use fallible_iterator::FallibleIterator;
+use rusqlite::Connection;
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver},
+    gpkg,
+    tiles::{self, Tile, TileMatrix, TileMatrixSet, TilePyramid, TileQuery, TileRange},
+};
+
+/// The only layer of an MBTiles file, named after its tile table.
+pub const LAYER_NAME: &str = "tiles";
+
+/// The `application_id` of an MBTiles file, `MPBX`.
+const MBTILES_APPLICATION_ID: i32 = 0x4d50_4258;
+
+/// Half the width of the Web Mercator world, in metres.
+const WEB_MERCATOR_EXTENT: f64 = 20_037_508.342_789_244;
+
+const TILE_SIZE: i64 = 256;
+
+/// The extent of every MBTiles pyramid, Web Mercator's.
+pub fn web_mercator_matrix_set() -> TileMatrixSet {
+    TileMatrixSet {
+        srs_id: 3857,
+        srs_definition: Some(gpkg::EPSG_3857.to_string()),
+        bounds: [
+            -WEB_MERCATOR_EXTENT,
+            -WEB_MERCATOR_EXTENT,
+            WEB_MERCATOR_EXTENT,
+            WEB_MERCATOR_EXTENT,
+        ],
+    }
+}
+
+/// The Web Mercator zoom level of `2^zoom_level` 256 pixel tiles square.
+pub fn web_mercator_matrix(zoom_level: i64) -> TileMatrix {
+    let matrix_width = 1 << zoom_level;
+    let pixel_size = 2.0 * WEB_MERCATOR_EXTENT / (TILE_SIZE * matrix_width) as f64;
+    TileMatrix {
+        zoom_level,
+        matrix_width,
+        matrix_height: matrix_width,
+        tile_width: TILE_SIZE,
+        tile_height: TILE_SIZE,
+        pixel_x_size: pixel_size,
+        pixel_y_size: pixel_size,
+    }
+}
+
+/// Convert between MBTiles' TMS rows, numbered from the bottom, and rows
+/// numbered from the top, in either direction.
+pub fn flip_row(zoom_level: i64, tile_row: i64) -> i64 {
+    (1 << zoom_level) - 1 - tile_row
+}
+
+fn flip_range(range: TileRange) -> TileRange {
+    TileRange {
+        min_row: flip_row(range.zoom_level, range.max_row),
+        max_row: flip_row(range.zoom_level, range.min_row),
+        ..range
+    }
+}
+
+/// Flip the rows of tiles, keeping them in the order the other containers
+/// use.
+fn flip_tiles(tiles: Vec<Tile>) -> Vec<Tile> {
+    let mut tiles: Vec<Tile> = tiles
+        .into_iter()
+        .map(|tile| Tile {
+            tile_row: flip_row(tile.zoom_level, tile.tile_row),
+            ..tile
+        })
+        .collect();
+    tiles.sort_by_key(|tile| (tile.zoom_level, tile.tile_row, tile.tile_column));
+    tiles
+}
+
+/// The key-value pairs of the `metadata` table.
+pub fn get_metadata(connection: &Connection) -> rusqlite::Result<HashMap<String, String>> {
+    let mut statement = connection.prepare("SELECT name, value FROM metadata")?;
+    let rows = statement.query([])?;
+    rows.map(|row| Ok((row.get(0)?, row.get(1)?))).collect()
+}
+
+/// The zoom levels that have tiles, from the lowest.
+pub fn get_tile_matrices(connection: &Connection) -> rusqlite::Result<Vec<TileMatrix>> {
+    let mut statement =
+        connection.prepare("SELECT DISTINCT zoom_level FROM tiles ORDER BY zoom_level")?;
+    let rows = statement.query([])?;
+    rows.map(|row| Ok(web_mercator_matrix(row.get(0)?)))
+        .collect()
+}
+
+/// Read the tiles selected by `query` and matching the SQL expression
+/// `filter`, which sees the stored TMS rows. The returned rows are numbered
+/// from the top, as in GeoPackage.
+pub fn get_tiles(
+    connection: &Connection,
+    query: &TileQuery,
+    filter: Option<&str>,
+) -> anyhow::Result<RecordBatch> {
+    let matrix_set = web_mercator_matrix_set();
+    let matrices = get_tile_matrices(connection)?;
+    let ranges: Vec<TileRange> = query
+        .tile_ranges(&matrix_set, &matrices)
+        .into_iter()
+        .map(flip_range)
+        .collect();
+    let sql_filter = tiles::tiles_filter(&ranges, filter);
+    let tiles = flip_tiles(tiles::select_tiles(connection, LAYER_NAME, &sql_filter)?);
+    tiles::tiles_to_record_batch(&matrix_set, &matrices, &tiles)
+}
+
+/// Read every tile of an MBTiles file.
+pub fn read_tile_pyramid(connection: &Connection) -> anyhow::Result<TilePyramid> {
+    Ok(TilePyramid {
+        matrix_set: web_mercator_matrix_set(),
+        matrices: get_tile_matrices(connection)?,
+        tiles: flip_tiles(tiles::select_tiles(connection, LAYER_NAME, "1")?),
+    })
+}
+
+/// The `format` metadata value of a tile, from its magic bytes.
+fn tile_format(tile_data: &[u8]) -> Option<&'static str> {
+    let format = match tile_data {
+        [0x89, b'P', b'N', b'G', ..] => "png",
+        [0xff, 0xd8, 0xff, ..] => "jpg",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "webp",
+        [0x1f, 0x8b, ..] => "pbf",
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// The metadata MBTiles requires of a pyramid called `name`: its name,
+/// tile format, and zoom range.
+pub fn default_metadata(name: &str, pyramid: &TilePyramid) -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+    metadata.insert("name".to_string(), name.to_string());
+    if let Some(format) = pyramid
+        .tiles
+        .first()
+        .and_then(|tile| tile_format(&tile.tile_data))
+    {
+        metadata.insert("format".to_string(), format.to_string());
+    }
+    if let (Some(first), Some(last)) = (pyramid.matrices.first(), pyramid.matrices.last()) {
+        metadata.insert("minzoom".to_string(), first.zoom_level.to_string());
+        metadata.insert("maxzoom".to_string(), last.zoom_level.to_string());
+    }
+    metadata
+}
+
+/// Check that a pyramid has the Web Mercator extent and zoom levels MBTiles
+/// assumes.
+fn ensure_web_mercator(pyramid: &TilePyramid) -> anyhow::Result<()> {
+    let expected = web_mercator_matrix_set();
+    anyhow::ensure!(
+        pyramid.matrix_set.srs_id == expected.srs_id,
+        "MBTiles requires EPSG:3857 tiles, not SRS {}",
+        pyramid.matrix_set.srs_id
+    );
+    anyhow::ensure!(
+        pyramid
+            .matrix_set
+            .bounds
+            .iter()
+            .zip(expected.bounds)
+            .all(|(bound, expected)| (bound - expected).abs() < 1.0),
+        "MBTiles requires tiles covering the whole Web Mercator extent"
+    );
+    for matrix in &pyramid.matrices {
+        anyhow::ensure!(
+            matrix.matrix_width == 1 << matrix.zoom_level
+                && matrix.matrix_height == matrix.matrix_width,
+            "MBTiles requires 2^z by 2^z tiles at zoom level {}",
+            matrix.zoom_level
+        );
+    }
+    Ok(())
+}
+
+/// Write a Web Mercator tile pyramid and its `metadata` to a new MBTiles
+/// file at `connection`.
+pub fn write_tile_pyramid(
+    connection: &Connection,
+    metadata: &HashMap<String, String>,
+    pyramid: &TilePyramid,
+) -> anyhow::Result<()> {
+    ensure_web_mercator(pyramid)?;
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute_batch(&format!(
+        "PRAGMA application_id = {};
+        CREATE TABLE metadata (name TEXT, value TEXT);
+        CREATE TABLE tiles (
+            zoom_level INTEGER,
+            tile_column INTEGER,
+            tile_row INTEGER,
+            tile_data BLOB
+        );
+        CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row);",
+        MBTILES_APPLICATION_ID
+    ))?;
+    let mut names: Vec<&String> = metadata.keys().collect();
+    names.sort();
+    for name in names {
+        transaction.execute(
+            "INSERT INTO metadata VALUES (?, ?)",
+            rusqlite::params![name, metadata[name]],
+        )?;
+    }
+    tiles::insert_tiles(&transaction, LAYER_NAME, &flip_tiles(pyramid.tiles.clone()))?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// An MBTiles file, whose tile table is its only layer.
+pub struct MbTiles {
+    path: PathBuf,
+    connection: Connection,
+}
+
+impl MbTiles {
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Driver for MbTiles {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| *format == Format::MbTiles)
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        Ok(MbTiles {
+            path: path.to_path_buf(),
+            connection: Connection::open(path)?,
+        })
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(vec![LAYER_NAME.to_string()])
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        driver::ensure_layer(&self.path, layer_name, LAYER_NAME)?;
+        Ok(Arc::new(tiles::tile_schema()))
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        driver::ensure_layer(&self.path, layer_name, LAYER_NAME)?;
+        let query = TileQuery {
+            bbox: options.bbox,
+            ..Default::default()
+        };
+        let record_batch = get_tiles(&self.connection, &query, options.filter.as_deref())?;
+        options.apply_filtered(record_batch)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dataset::Dataset;
+    use arrow::array::BinaryArray;
+
+    #[test]
+    fn test_get_tiles() {
+        let dataset = Dataset::open("Data/tiles.mbtiles").unwrap();
+        assert_eq!(vec![LAYER_NAME.to_string()], dataset.list_layers().unwrap());
+        let connection = Connection::open("Data/tiles.mbtiles").unwrap();
+        assert_eq!("basemap", get_metadata(&connection).unwrap()["name"]);
+
+        let query = TileQuery {
+            zoom_level: Some(1),
+            bbox: Some([-1000.0, 1000.0, -500.0, 2000.0]),
+        };
+        let tiles = get_tiles(&connection, &query, None).unwrap();
+        assert_eq!(1, tiles.num_rows());
+        let tile_data = tiles
+            .column(3)
+            .as_any()
+            .downcast_ref::<BinaryArray>()
+            .unwrap();
+        assert_eq!(b"tile 1/0/0", tile_data.value(0));
+
+        let options = ReadOptions {
+            filter: Some("zoom_level = 1".to_string()),
+            ..Default::default()
+        };
+        let tiles = dataset
+            .get_layer_with_options(LAYER_NAME, &options)
+            .unwrap();
+        assert_eq!(4, tiles.num_rows());
+    }
+
+    #[test]
+    fn test_write_tile_pyramid() {
+        let connection = Connection::open("Data/tiles.mbtiles").unwrap();
+        let pyramid = read_tile_pyramid(&connection).unwrap();
+        let metadata = default_metadata("basemap", &pyramid);
+        assert_eq!("1", metadata["maxzoom"]);
+
+        let geopackage = Connection::open_in_memory().unwrap();
+        tiles::write_tile_pyramid(&geopackage, "basemap", &pyramid).unwrap();
+        let round_trip = tiles::read_tile_pyramid(&geopackage, "basemap").unwrap();
+        assert_eq!(pyramid, round_trip);
+
+        let mbtiles = Connection::open_in_memory().unwrap();
+        write_tile_pyramid(&mbtiles, &metadata, &round_trip).unwrap();
+        assert_eq!(pyramid, read_tile_pyramid(&mbtiles).unwrap());
+        assert_eq!(metadata, get_metadata(&mbtiles).unwrap());
+
+        let geopackage = Connection::open("Data/tiles.gpkg").unwrap();
+        let pyramid = tiles::read_tile_pyramid(&geopackage, "basemap").unwrap();
+        let mbtiles = Connection::open_in_memory().unwrap();
+        assert!(write_tile_pyramid(&mbtiles, &metadata, &pyramid).is_err());
+    }
+}
@@ -0,0 +1,647 @@
+use std::{io::Read, path::Path, sync::Arc};
+
+use anyhow::Context;
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray, Float64Array, Int64Array, UInt64Array},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use flate2::read::GzDecoder;
+use rusqlite::Connection;
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver,
+    geoarrow::{self, ExtensionMetadata},
+    geojson, gpkg, mbtiles,
+    tiles::{self, TileQuery},
+    wkb::{
+        Coordinate, LinearRing, WkbGeometry, WkbLineString, WkbMultiLineString, WkbMultiPoint,
+        WkbMultiPolygon, WkbPoint, WkbPolygon,
+    },
+};
+
+const GEOMETRY_COLUMN: &str = "geometry";
+
+/// The columns tagging each feature with its id and tile, prefixed so that
+/// they do not clash with the feature's tags.
+const ID_COLUMN: &str = "mvt_id";
+const TILE_COLUMNS: [(&str, &str); 3] = [
+    ("zoom_level", "mvt_zoom_level"),
+    ("tile_column", "mvt_tile_column"),
+    ("tile_row", "mvt_tile_row"),
+];
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+/// A value of a protobuf field, by wire type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WireValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+fn read_varint(data: &mut &[u8]) -> anyhow::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first().context("Truncated varint")?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    anyhow::bail!("Varint is too long")
+}
+
+fn read_bytes<'a>(data: &mut &'a [u8], length: usize) -> anyhow::Result<&'a [u8]> {
+    anyhow::ensure!(data.len() >= length, "Truncated field");
+    let (bytes, rest) = data.split_at(length);
+    *data = rest;
+    Ok(bytes)
+}
+
+/// The fields of a protobuf message, as `(field number, value)` pairs.
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn read_field(&mut self) -> anyhow::Result<(u64, WireValue<'a>)> {
+        let key = read_varint(&mut self.data)?;
+        let value = match key & 0x7 {
+            0 => WireValue::Varint(read_varint(&mut self.data)?),
+            1 => WireValue::Fixed64(u64::from_le_bytes(
+                read_bytes(&mut self.data, 8)?.try_into()?,
+            )),
+            2 => {
+                let length = read_varint(&mut self.data)? as usize;
+                WireValue::Bytes(read_bytes(&mut self.data, length)?)
+            }
+            5 => WireValue::Fixed32(u32::from_le_bytes(
+                read_bytes(&mut self.data, 4)?.try_into()?,
+            )),
+            wire_type => anyhow::bail!("Unsupported wire type {}", wire_type),
+        };
+        Ok((key >> 3, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = anyhow::Result<(u64, WireValue<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.data.is_empty() {
+            true => None,
+            false => Some(self.read_field()),
+        }
+    }
+}
+
+fn fields(data: &[u8]) -> Fields<'_> {
+    Fields { data }
+}
+
+/// Append a repeated `uint32` field, which is usually packed.
+fn extend_repeated(values: &mut Vec<u32>, value: WireValue) -> anyhow::Result<()> {
+    match value {
+        WireValue::Varint(value) => values.push(value as u32),
+        WireValue::Bytes(mut data) => {
+            while !data.is_empty() {
+                values.push(read_varint(&mut data)? as u32);
+            }
+        }
+        value => anyhow::bail!("Expected repeated uint32, got {:?}", value),
+    }
+    Ok(())
+}
+
+fn zigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+fn to_str<'a>(value: WireValue<'a>) -> anyhow::Result<&'a str> {
+    match value {
+        WireValue::Bytes(bytes) => Ok(std::str::from_utf8(bytes)?),
+        value => anyhow::bail!("Expected string, got {:?}", value),
+    }
+}
+
+fn to_number(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// Decode a `Value` message of a layer's value table.
+fn decode_value(data: &[u8]) -> anyhow::Result<Value> {
+    let mut decoded = Value::Null;
+    for field in fields(data) {
+        decoded = match field? {
+            (1, value) => Value::String(to_str(value)?.to_string()),
+            (2, WireValue::Fixed32(bits)) => to_number(f64::from(f32::from_bits(bits))),
+            (3, WireValue::Fixed64(bits)) => to_number(f64::from_bits(bits)),
+            (4, WireValue::Varint(value)) => Value::from(value as i64),
+            (5, WireValue::Varint(value)) => Value::from(value),
+            (6, WireValue::Varint(value)) => Value::from(zigzag(value)),
+            (7, WireValue::Varint(value)) => Value::Bool(value != 0),
+            _ => continue,
+        };
+    }
+    Ok(decoded)
+}
+
+/// The geometry type of a feature, from the `GeomType` enum.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MvtGeometryType {
+    #[default]
+    Unknown,
+    Point,
+    LineString,
+    Polygon,
+}
+
+impl MvtGeometryType {
+    fn from_code(code: u64) -> Self {
+        match code {
+            1 => MvtGeometryType::Point,
+            2 => MvtGeometryType::LineString,
+            3 => MvtGeometryType::Polygon,
+            _ => MvtGeometryType::Unknown,
+        }
+    }
+}
+
+/// A feature of a vector tile layer, with its geometry still encoded as
+/// drawing commands in tile coordinates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MvtFeature {
+    pub id: Option<u64>,
+    pub properties: Map<String, Value>,
+    pub geometry_type: MvtGeometryType,
+    commands: Vec<u32>,
+}
+
+/// A layer of a vector tile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MvtLayer {
+    pub name: String,
+    /// The width and height of the tile in tile coordinates.
+    pub extent: u32,
+    pub features: Vec<MvtFeature>,
+}
+
+fn decode_layer(data: &[u8]) -> anyhow::Result<MvtLayer> {
+    let mut name = String::new();
+    let mut extent = 4096;
+    let mut keys = Vec::new();
+    let mut values = Vec::new();
+    let mut features = Vec::new();
+    for field in fields(data) {
+        match field? {
+            (1, value) => name = to_str(value)?.to_string(),
+            (2, WireValue::Bytes(feature)) => features.push(feature),
+            (3, value) => keys.push(to_str(value)?.to_string()),
+            (4, WireValue::Bytes(value)) => values.push(decode_value(value)?),
+            (5, WireValue::Varint(value)) => extent = value as u32,
+            _ => {}
+        }
+    }
+
+    let features = features
+        .into_iter()
+        .map(|data| {
+            let mut feature = MvtFeature::default();
+            let mut tags = Vec::new();
+            for field in fields(data) {
+                match field? {
+                    (1, WireValue::Varint(id)) => feature.id = Some(id),
+                    (2, value) => extend_repeated(&mut tags, value)?,
+                    (3, WireValue::Varint(code)) => {
+                        feature.geometry_type = MvtGeometryType::from_code(code)
+                    }
+                    (4, value) => extend_repeated(&mut feature.commands, value)?,
+                    _ => {}
+                }
+            }
+            for tag in tags.chunks(2) {
+                let (key, value) = match tag {
+                    [key, value] => (key, value),
+                    _ => anyhow::bail!("Odd number of tags in layer {}", name),
+                };
+                let key = keys.get(*key as usize).context("Tag key out of range")?;
+                let value = values
+                    .get(*value as usize)
+                    .context("Tag value out of range")?;
+                feature.properties.insert(key.clone(), value.clone());
+            }
+            Ok(feature)
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(MvtLayer {
+        name,
+        extent,
+        features,
+    })
+}
+
+/// Decode the layers of a vector tile, which may be gzip compressed.
+pub fn decode_tile(tile_data: &[u8]) -> anyhow::Result<Vec<MvtLayer>> {
+    let mut decompressed = Vec::new();
+    let data = match tile_data.starts_with(GZIP_MAGIC) {
+        true => {
+            GzDecoder::new(tile_data).read_to_end(&mut decompressed)?;
+            &decompressed
+        }
+        false => tile_data,
+    };
+    fields(data)
+        .filter_map(|field| match field {
+            Ok((3, WireValue::Bytes(layer))) => Some(decode_layer(layer)),
+            Ok(_) => None,
+            Err(error) => Some(Err(error)),
+        })
+        .collect()
+}
+
+/// Twice the area of a ring by the surveyor's formula, positive for
+/// exterior rings in tile coordinates, whose y axis points down.
+fn ring_area(ring: &[[i64; 2]]) -> i64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a[0] * b[1] - b[0] * a[1])
+        .sum()
+}
+
+impl MvtFeature {
+    /// Run the drawing commands, returning each part in tile coordinates and
+    /// whether it was closed.
+    fn parts(&self) -> anyhow::Result<Vec<(Vec<[i64; 2]>, bool)>> {
+        let mut parts: Vec<(Vec<[i64; 2]>, bool)> = Vec::new();
+        let mut cursor = [0i64, 0];
+        let mut commands = self.commands.iter();
+        while let Some(command) = commands.next() {
+            let (id, count) = (command & 0x7, command >> 3);
+            if id == CLOSE_PATH {
+                let part = parts.last_mut().context("ClosePath without a MoveTo")?;
+                part.1 = true;
+                continue;
+            }
+            anyhow::ensure!(id == MOVE_TO || id == LINE_TO, "Unknown command {}", id);
+            for _ in 0..count {
+                let mut parameter = || -> anyhow::Result<i64> {
+                    let value = commands.next().context("Truncated geometry")?;
+                    Ok(zigzag(u64::from(*value)))
+                };
+                cursor = [cursor[0] + parameter()?, cursor[1] + parameter()?];
+                match id {
+                    MOVE_TO => parts.push((vec![cursor], false)),
+                    _ => parts
+                        .last_mut()
+                        .context("LineTo without a MoveTo")?
+                        .0
+                        .push(cursor),
+                }
+            }
+        }
+        Ok(parts)
+    }
+
+    /// The geometry in the CRS of a tile covering `bounds`, whose `extent`
+    /// tile coordinates span its width and height. `None` if it has no
+    /// coordinates.
+    pub fn geometry(&self, extent: u32, bounds: &[f64; 4]) -> anyhow::Result<Option<WkbGeometry>> {
+        let scale = [
+            (bounds[2] - bounds[0]) / f64::from(extent),
+            (bounds[3] - bounds[1]) / f64::from(extent),
+        ];
+        let coordinate = |point: &[i64; 2]| {
+            Coordinate::new(
+                bounds[0] + point[0] as f64 * scale[0],
+                bounds[3] - point[1] as f64 * scale[1],
+            )
+        };
+        let coordinates = |points: &[[i64; 2]]| points.iter().map(coordinate).collect::<Vec<_>>();
+        let parts = self.parts()?;
+
+        let geometry = match self.geometry_type {
+            MvtGeometryType::Point => {
+                let mut points: Vec<WkbPoint> = parts
+                    .iter()
+                    .flat_map(|(points, _closed)| points)
+                    .map(|point| WkbPoint::new(coordinate(point)))
+                    .collect();
+                match points.len() {
+                    0 => return Ok(None),
+                    1 => WkbGeometry::Point(points.remove(0)),
+                    _ => WkbGeometry::MultiPoint(WkbMultiPoint::new(points)),
+                }
+            }
+            MvtGeometryType::LineString => {
+                let mut line_strings: Vec<WkbLineString> = parts
+                    .iter()
+                    .filter(|(points, _closed)| points.len() >= 2)
+                    .map(|(points, _closed)| WkbLineString::new(coordinates(points)))
+                    .collect();
+                match line_strings.len() {
+                    0 => return Ok(None),
+                    1 => WkbGeometry::LineString(line_strings.remove(0)),
+                    _ => WkbGeometry::MultiLineString(WkbMultiLineString::new(line_strings)),
+                }
+            }
+            MvtGeometryType::Polygon => {
+                let mut polygons: Vec<Vec<LinearRing>> = Vec::new();
+                for (points, _closed) in &parts {
+                    let area = ring_area(points);
+                    let mut ring = coordinates(points);
+                    ring.extend(ring.first().copied());
+                    match (area > 0, polygons.last_mut()) {
+                        _ if area == 0 => continue,
+                        (false, Some(polygon)) => polygon.push(LinearRing::new(ring)),
+                        _ => polygons.push(vec![LinearRing::new(ring)]),
+                    }
+                }
+                let mut polygons: Vec<WkbPolygon> =
+                    polygons.into_iter().map(WkbPolygon::new).collect();
+                match polygons.len() {
+                    0 => return Ok(None),
+                    1 => WkbGeometry::Polygon(polygons.remove(0)),
+                    _ => WkbGeometry::MultiPolygon(WkbMultiPolygon::new(polygons)),
+                }
+            }
+            MvtGeometryType::Unknown => return Ok(None),
+        };
+        Ok(Some(geometry))
+    }
+}
+
+/// The names of the vector tile layers in a record batch of tiles, in the
+/// order they first appear in.
+pub fn layer_names(tiles: &RecordBatch) -> anyhow::Result<Vec<String>> {
+    let tile_data = get_column::<BinaryArray>(tiles, "tile_data")?;
+    let mut names: Vec<String> = Vec::new();
+    for index in 0..tiles.num_rows() {
+        for layer in decode_tile(tile_data.value(index))? {
+            if !names.contains(&layer.name) {
+                names.push(layer.name);
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn get_column<'a, T: 'static>(record_batch: &'a RecordBatch, name: &str) -> anyhow::Result<&'a T> {
+    let index = record_batch.schema().index_of(name)?;
+    record_batch
+        .column(index)
+        .as_any()
+        .downcast_ref::<T>()
+        .context(format!("Unexpected type for column {}", name))
+}
+
+/// Decode the features of the vector tile layer `layer_name` from a record
+/// batch of tiles read from a tile table. The features have `mvt_` prefixed
+/// columns for their id and tile, a column per tag key and a WKB geometry in
+/// the CRS described by `metadata`. Tags named like those columns are an
+/// error.
+pub fn tiles_to_features(
+    tiles: &RecordBatch,
+    layer_name: &str,
+    metadata: &ExtensionMetadata,
+) -> anyhow::Result<RecordBatch> {
+    let zoom_levels = get_column::<Int64Array>(tiles, "zoom_level")?;
+    let tile_columns = get_column::<Int64Array>(tiles, "tile_column")?;
+    let tile_rows = get_column::<Int64Array>(tiles, "tile_row")?;
+    let tile_data = get_column::<BinaryArray>(tiles, "tile_data")?;
+    let bounds = ["min_x", "min_y", "max_x", "max_y"]
+        .iter()
+        .map(|name| get_column::<Float64Array>(tiles, name))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut tile_indices: Vec<u64> = Vec::new();
+    let mut ids = Vec::new();
+    let mut properties = Vec::new();
+    let mut geometries = Vec::new();
+    for index in 0..tiles.num_rows() {
+        anyhow::ensure!(
+            bounds.iter().all(|bound| bound.is_valid(index)),
+            "Tile {}/{}/{} has no tile matrix",
+            zoom_levels.value(index),
+            tile_columns.value(index),
+            tile_rows.value(index)
+        );
+        let tile_bounds = [
+            bounds[0].value(index),
+            bounds[1].value(index),
+            bounds[2].value(index),
+            bounds[3].value(index),
+        ];
+        let layers = decode_tile(tile_data.value(index)).context(format!(
+            "Failed to decode tile {}/{}/{}",
+            zoom_levels.value(index),
+            tile_columns.value(index),
+            tile_rows.value(index)
+        ))?;
+        for layer in layers.into_iter().filter(|layer| layer.name == layer_name) {
+            for feature in layer.features {
+                tile_indices.push(index as u64);
+                ids.push(feature.id);
+                geometries.push(
+                    feature
+                        .geometry(layer.extent, &tile_bounds)?
+                        .map(|geometry| geometry.to_wkb()),
+                );
+                properties.push(Value::Object(feature.properties));
+            }
+        }
+    }
+
+    let properties_schema = geojson::infer_objects_schema(&properties)?;
+    for field in properties_schema.fields() {
+        let name = field.name().as_str();
+        anyhow::ensure!(
+            name != GEOMETRY_COLUMN
+                && name != ID_COLUMN
+                && TILE_COLUMNS.iter().all(|(_, column)| name != *column),
+            "Layer {} has a tag {}, which clashes with a column",
+            layer_name,
+            name
+        );
+    }
+    let tile_indices = UInt64Array::from(tile_indices);
+    let mut fields: Vec<Field> = TILE_COLUMNS
+        .iter()
+        .map(|(_, name)| Field::new(name, DataType::Int64, false))
+        .collect();
+    fields.push(Field::new(ID_COLUMN, DataType::UInt64, true));
+    let mut columns = TILE_COLUMNS
+        .iter()
+        .map(|(name, _)| {
+            let column = tiles.column(tiles.schema().index_of(name)?);
+            Ok(arrow::compute::take(column.as_ref(), &tile_indices, None)?)
+        })
+        .collect::<anyhow::Result<Vec<ArrayRef>>>()?;
+    columns.push(Arc::new(UInt64Array::from(ids)));
+    fields.extend(properties_schema.fields().iter().cloned());
+    columns.extend(geojson::decode_objects(
+        Arc::new(properties_schema),
+        &properties,
+    )?);
+    fields.push(geoarrow::geometry_field(
+        GEOMETRY_COLUMN,
+        DataType::Binary,
+        metadata,
+    ));
+    columns.push(Arc::new(BinaryArray::from_opt_vec(
+        geometries.iter().map(|wkb| wkb.as_deref()).collect(),
+    )));
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+/// Read the features of the vector tile layer `layer_name` from the tiles of
+/// `zoom_level` in the tile table `tile_layer` of a GeoPackage or MBTiles
+/// file. Only tiles intersecting `options.bbox` and matching the SQL
+/// expression `options.filter` are decoded, and the other options are applied
+/// to the features.
+pub fn read_features(
+    path: &Path,
+    tile_layer: &str,
+    layer_name: &str,
+    zoom_level: i64,
+    options: &ReadOptions,
+) -> anyhow::Result<RecordBatch> {
+    let connection = Connection::open(path)?;
+    let query = TileQuery {
+        zoom_level: Some(zoom_level),
+        bbox: options.bbox,
+    };
+    let filter = options.filter.as_deref();
+    let (tiles, metadata) = match Format::detect(path)? {
+        Format::GeoPackage => (
+            tiles::get_tiles(&connection, tile_layer, &query, filter)?,
+            gpkg::get_spatial_ref_sys(&connection, tile_layer)?.extension_metadata(),
+        ),
+        Format::MbTiles => {
+            driver::ensure_layer(path, tile_layer, mbtiles::LAYER_NAME)?;
+            (
+                mbtiles::get_tiles(&connection, &query, filter)?,
+                ExtensionMetadata::from_authority_code("EPSG", 3857),
+            )
+        }
+        format => anyhow::bail!("{:?} has no tile pyramids", format),
+    };
+    let record_batch = tiles_to_features(&tiles, layer_name, &metadata)?;
+    options.apply_filtered(record_batch)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{convert, dataset::GeometryFormat, test_util::TempPath};
+    use arrow::array::StringArray;
+
+    #[test]
+    fn test_decode_tile() {
+        let connection = Connection::open("Data/vector.mbtiles").unwrap();
+        let tiles = mbtiles::get_tiles(&connection, &TileQuery::default(), None).unwrap();
+        assert_eq!(
+            vec!["places", "roads", "areas"],
+            layer_names(&tiles).unwrap()
+        );
+
+        let tile_data = tiles
+            .column(3)
+            .as_any()
+            .downcast_ref::<BinaryArray>()
+            .unwrap();
+        let layers = decode_tile(tile_data.value(0)).unwrap();
+        let places = &layers[0];
+        assert_eq!(4096, places.extent);
+        assert_eq!(Some(2), places.features[1].id);
+        assert_eq!(Value::from(-1), places.features[1].properties["rank"]);
+
+        let areas = &layers[2];
+        let geometry = areas.features[0]
+            .geometry(areas.extent, &[0.0, 0.0, 4096.0, 4096.0])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            "POLYGON ((1024 3072, 3072 3072, 3072 1024, 1024 1024, 1024 3072), \
+            (1536 2560, 1536 1536, 2560 1536, 2560 2560, 1536 2560))",
+            geometry.to_wkt()
+        );
+    }
+
+    #[test]
+    fn test_read_features() {
+        let options = ReadOptions {
+            geometry_format: GeometryFormat::Wkt { precision: Some(0) },
+            ..Default::default()
+        };
+        let path = Path::new("Data/vector.mbtiles");
+        let places = read_features(path, "tiles", "places", 0, &options).unwrap();
+        let schema = places.schema();
+        let names: Vec<&str> = schema
+            .fields()
+            .iter()
+            .map(|field| field.name().as_str())
+            .collect();
+        assert_eq!(
+            vec![
+                "mvt_zoom_level",
+                "mvt_tile_column",
+                "mvt_tile_row",
+                "mvt_id",
+                "name",
+                "population",
+                "rank",
+                "capital",
+                "geometry"
+            ],
+            names
+        );
+        let geometry_column = geoarrow::primary_geometry_column(&schema).unwrap();
+        let geometry = places
+            .column(geometry_column)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!("POINT (0 0)", geometry.value(0));
+        assert_eq!(
+            "MULTIPOINT ((-10018754 10018754), (10018754 -10018754))",
+            geometry.value(1)
+        );
+
+        let geopackage = TempPath::new("ogr2arrow-mvt.gpkg");
+        convert::convert_tiles(path, "tiles", &geopackage, Format::GeoPackage).unwrap();
+        let options = ReadOptions {
+            bbox: Some([-1.1e7, 1e6, -1e6, 1.1e7]),
+            ..options
+        };
+        let places = read_features(&geopackage, "tiles", "places", 1, &options).unwrap();
+        assert_eq!(1, places.num_rows());
+        let schema = places.schema();
+        let geometry_column = geoarrow::primary_geometry_column(&schema).unwrap();
+        let geometry = places
+            .column(geometry_column)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!("POINT (-10018754 10018754)", geometry.value(0));
+        assert_eq!(
+            Some(("EPSG", 3857)),
+            geoarrow::get_extension_metadata(schema.field(geometry_column))
+                .unwrap()
+                .authority_code()
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Condvar, Mutex,
+    },
+};
+
+use anyhow::Context;
+use arrow::{
+    datatypes::{Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+use crate::{dataset::ReadOptions, driver::RecordBatches, gpkg, spatialite, wkb::WkbGeometry};
+
+/// Decodes a geometry blob of the database's format.
+type Decode = fn(&[u8]) -> anyhow::Result<WkbGeometry>;
+
+/// Options controlling how [`read_layer`] splits a layer across threads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParallelOptions {
+    /// The number of threads, each with its own read-only connection.
+    pub threads: usize,
+    /// The number of rows read into each record batch, before filtering.
+    pub rows_per_batch: usize,
+    /// Return the record batches in FID order, rather than as they are
+    /// decoded. Either way, at most twice `threads` record batches are read
+    /// ahead of the consumer.
+    pub ordered: bool,
+    /// Open the database as `immutable`, skipping all locking. Only safe if
+    /// nothing writes to it while it is read. Otherwise reading on more than
+    /// one thread requires the database to be in WAL mode.
+    pub immutable: bool,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        ParallelOptions {
+            threads: std::thread::available_parallelism().map_or(1, usize::from),
+            rows_per_batch: 64 * 1024,
+            ordered: true,
+            immutable: false,
+        }
+    }
+}
+
+/// Open a read-only connection that does not serialise access, as each
+/// thread has its own.
+fn open_read_only(path: &Path, immutable: bool) -> rusqlite::Result<Connection> {
+    let path = path
+        .to_string_lossy()
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23");
+    let uri = match immutable {
+        true => format!("file:{}?immutable=1", path),
+        false => format!("file:{}", path),
+    };
+    Connection::open_with_flags(
+        uri,
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX
+            | OpenFlags::SQLITE_OPEN_URI,
+    )
+}
+
+/// Fail unless the database allows readers on several connections alongside a
+/// writer.
+fn check_wal(connection: &Connection, path: &Path) -> anyhow::Result<()> {
+    let journal_mode: String = connection.query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+    anyhow::ensure!(
+        journal_mode.eq_ignore_ascii_case("wal"),
+        "{} is in {} journal mode; use WAL mode, or open it as immutable if nothing writes to it",
+        path.display(),
+        journal_mode
+    );
+    Ok(())
+}
+
+/// Hands out the indices of the FID ranges to read, no more than `window`
+/// ahead of the record batches returned, so that an ordered read buffers a
+/// bounded number of record batches behind a slow one.
+struct Claims {
+    state: Mutex<ClaimState>,
+    returned: Condvar,
+    window: usize,
+}
+
+#[derive(Default)]
+struct ClaimState {
+    next: usize,
+    returned: usize,
+    closed: bool,
+}
+
+impl Claims {
+    fn new(window: usize) -> Self {
+        Claims {
+            state: Mutex::new(ClaimState::default()),
+            returned: Condvar::new(),
+            window,
+        }
+    }
+
+    /// The next index to read, waiting while the window is full, or `None`
+    /// once the reader is dropped.
+    fn claim(&self) -> Option<usize> {
+        let mut state = self.state.lock().ok()?;
+        while !state.closed && state.next >= state.returned + self.window {
+            state = self.returned.wait(state).ok()?;
+        }
+        if state.closed {
+            return None;
+        }
+        state.next += 1;
+        Some(state.next - 1)
+    }
+
+    fn release(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.returned += 1;
+        }
+        self.returned.notify_all();
+    }
+
+    fn close(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.closed = true;
+        }
+        self.returned.notify_all();
+    }
+}
+
+/// The inclusive FID ranges covering `layer`, each holding `rows_per_batch`
+/// rows except the last, however sparse its FIDs are.
+fn get_ranges(
+    connection: &Connection,
+    layer: &str,
+    rows_per_batch: usize,
+) -> rusqlite::Result<Vec<(i64, i64)>> {
+    let table = gpkg::quote_identifier(layer);
+    let max: Option<i64> =
+        connection.query_row(&format!("SELECT MAX(rowid) FROM {}", table), [], |row| {
+            row.get(0)
+        })?;
+    let max = match max {
+        Some(max) => max,
+        None => return Ok(Vec::new()),
+    };
+    let mut statement = connection.prepare(&format!(
+        "SELECT rowid FROM {} WHERE rowid >= ? ORDER BY rowid LIMIT 1 OFFSET ?",
+        table
+    ))?;
+    let mut starts = vec![statement.query_row(params![i64::MIN, 0], |row| row.get(0))?];
+    while let Some(start) = statement
+        .query_row(
+            params![starts[starts.len() - 1], rows_per_batch.max(1) as i64],
+            |row| row.get(0),
+        )
+        .optional()?
+    {
+        starts.push(start);
+    }
+    let ends = starts.iter().skip(1).map(|start| start - 1).chain([max]);
+    Ok(starts.iter().copied().zip(ends).collect())
+}
+
+/// The record batches of a layer read by [`read_layer`], in FID order or as
+/// they are decoded.
+pub struct BatchReader {
+    schema: SchemaRef,
+    receiver: Receiver<(usize, anyhow::Result<RecordBatch>)>,
+    ordered: bool,
+    pending: BTreeMap<usize, anyhow::Result<RecordBatch>>,
+    claims: Arc<Claims>,
+    next: usize,
+    total: usize,
+}
+
+impl Drop for BatchReader {
+    fn drop(&mut self) {
+        self.claims.close();
+    }
+}
+
+impl BatchReader {
+    /// The schema of the layer before `ReadOptions` are applied.
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl Iterator for BatchReader {
+    type Item = anyhow::Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.total {
+            return None;
+        }
+        loop {
+            if let Some(result) = self.pending.remove(&self.next) {
+                self.next += 1;
+                self.claims.release();
+                return Some(result);
+            }
+            match self.receiver.recv() {
+                Ok((_index, result)) if !self.ordered => {
+                    self.next += 1;
+                    self.claims.release();
+                    return Some(result);
+                }
+                Ok((index, result)) => {
+                    self.pending.insert(index, result);
+                }
+                Err(_) => {
+                    self.next = self.total;
+                    return Some(Err(anyhow::anyhow!("A reader thread panicked")));
+                }
+            }
+        }
+    }
+}
+
+/// Read a GeoPackage or SpatiaLite layer as record batches of FID ranges,
+/// decoded concurrently on `parallel.threads` threads. `options` are applied
+/// to each record batch.
+pub fn read_layer(
+    path: &Path,
+    layer_name: &str,
+    options: &ReadOptions,
+    parallel: &ParallelOptions,
+) -> anyhow::Result<BatchReader> {
+    let connection = open_read_only(path, parallel.immutable)
+        .context(format!("Failed to open {}", path.display()))?;
+    let (schema, decode): (Schema, Decode) = match spatialite::is_spatialite(&connection)? {
+        true => (
+            spatialite::get_schema(&connection, layer_name)?,
+            spatialite::decode_spatialite_binary,
+        ),
+        false => (
+            gpkg::get_schema(&connection, layer_name)?,
+            gpkg::decode_geopackage_binary,
+        ),
+    };
+    gpkg::check_filter(&connection, layer_name, options.filter.as_deref())?;
+    let ranges = Arc::new(get_ranges(
+        &connection,
+        layer_name,
+        parallel.rows_per_batch,
+    )?);
+    let schema = Arc::new(schema);
+    let threads = parallel.threads.max(1).min(ranges.len());
+    if threads > 1 && !parallel.immutable {
+        check_wal(&connection, path)?;
+    }
+
+    let (sender, receiver) = mpsc::sync_channel(parallel.threads.max(1));
+    let claims = Arc::new(Claims::new(parallel.threads.max(1) * 2));
+    for _ in 0..threads {
+        let path = PathBuf::from(path);
+        let layer_name = layer_name.to_string();
+        let options = options.clone();
+        let immutable = parallel.immutable;
+        let schema = schema.clone();
+        let ranges = ranges.clone();
+        let claims = claims.clone();
+        let sender = sender.clone();
+        std::thread::spawn(move || {
+            let connection = open_read_only(&path, immutable);
+            while let Some(index) = claims.claim() {
+                let (start, end) = match ranges.get(index) {
+                    Some(range) => *range,
+                    None => break,
+                };
+                let result = match &connection {
+                    Ok(connection) => {
+                        let mut filter = format!("rowid BETWEEN {} AND {}", start, end);
+                        if let Some(user_filter) = &options.filter {
+                            filter = format!("{} AND ({})", filter, user_filter);
+                        }
+                        gpkg::get_fields_with(
+                            connection,
+                            &schema,
+                            &layer_name,
+                            Some(&filter),
+                            decode,
+                        )
+                        .and_then(|fields| Ok(RecordBatch::try_new(schema.clone(), fields)?))
+                        .and_then(|record_batch| options.apply_filtered(record_batch))
+                    }
+                    Err(error) => Err(anyhow::anyhow!(
+                        "Failed to open {}: {}",
+                        path.display(),
+                        error
+                    )),
+                };
+                if sender.send((index, result)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    Ok(BatchReader {
+        schema,
+        receiver,
+        ordered: parallel.ordered,
+        pending: BTreeMap::new(),
+        claims,
+        next: 0,
+        total: ranges.len(),
+    })
+}
+
+/// Read a feature table of the database file at `path` a FID range of
+/// `batch_size` rows at a time, on a background thread that reads at most two
+/// record batches ahead.
+pub(crate) fn read_batches(
+    path: &Path,
+    layer_name: &str,
+    options: &ReadOptions,
+    batch_size: usize,
+) -> anyhow::Result<RecordBatches> {
+    let parallel = ParallelOptions {
+        threads: 1,
+        rows_per_batch: batch_size,
+        ..ParallelOptions::default()
+    };
+    Ok(Box::new(read_layer(path, layer_name, options, &parallel)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dataset::Dataset;
+
+    #[test]
+    fn test_read_layer_ordered() {
+        let expected_layer = Dataset::open("Data/point.gpkg")
+            .unwrap()
+            .get_layer("point")
+            .unwrap();
+        let parallel = ParallelOptions {
+            threads: 2,
+            rows_per_batch: 1,
+            ..Default::default()
+        };
+        let error = read_layer(
+            Path::new("Data/point.gpkg"),
+            "point",
+            &ReadOptions::default(),
+            &parallel,
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("journal mode"));
+
+        let parallel = ParallelOptions {
+            immutable: true,
+            ..parallel
+        };
+        let reader = read_layer(
+            Path::new("Data/point.gpkg"),
+            "point",
+            &ReadOptions::default(),
+            &parallel,
+        )
+        .unwrap();
+        let schema = reader.schema();
+        let record_batches = reader.collect::<anyhow::Result<Vec<_>>>().unwrap();
+
+        assert_eq!(2, record_batches.len());
+        assert_eq!(
+            expected_layer,
+            RecordBatch::concat(&schema, &record_batches).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_layer_unordered() {
+        let parallel = ParallelOptions {
+            threads: 4,
+            rows_per_batch: 1,
+            ordered: false,
+            immutable: true,
+        };
+        let options = ReadOptions {
+            filter: Some("geometry IS NOT NULL".to_string()),
+            ..Default::default()
+        };
+
+        let reader = read_layer(
+            Path::new("Data/spatialite.sqlite"),
+            "polygons",
+            &options,
+            &parallel,
+        )
+        .unwrap();
+        let rows: usize = reader
+            .map(|record_batch| record_batch.unwrap().num_rows())
+            .sum();
+
+        assert_eq!(1, rows);
+    }
+
+    #[test]
+    fn test_get_ranges() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                r#"CREATE TABLE "sparse ""points""" (fid INTEGER PRIMARY KEY);
+                INSERT INTO "sparse ""points""" VALUES (1), (2), (3), (1000000000000);"#,
+            )
+            .unwrap();
+
+        let ranges = get_ranges(&connection, "sparse \"points\"", 2).unwrap();
+
+        assert_eq!(vec![(1, 2), (3, 1_000_000_000_000)], ranges);
+        connection
+            .execute_batch(r#"DELETE FROM "sparse ""points""""#)
+            .unwrap();
+        assert!(get_ranges(&connection, "sparse \"points\"", 2)
+            .unwrap()
+            .is_empty());
+    }
+}
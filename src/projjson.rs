@@ -0,0 +1,435 @@
+use anyhow::Context;
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag, take_while},
+    character::complete::{alpha1, char, multispace0},
+    combinator::{all_consuming, map, recognize, value},
+    multi::{fold_many0, separated_list0},
+    number::complete::double,
+    sequence::{delimited, pair, preceded, terminated},
+    IResult,
+};
+use serde_json::{json, Map, Value};
+
+pub const SCHEMA: &str = "https://proj.org/schemas/v0.5/projjson.schema.json";
+
+const DEGREE: f64 = 0.0174532925199433;
+
+/// A keyword and its bracketed values, e.g. `UNIT["metre",1]`.
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    keyword: String,
+    values: Vec<WktValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum WktValue {
+    Text(String),
+    Number(f64),
+    /// An unquoted enumeration value, such as the `NORTH` of an `AXIS`.
+    Word(String),
+    Node(Node),
+}
+
+fn ws<'a, O>(
+    parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(multispace0, parser, multispace0)
+}
+
+fn word(input: &str) -> IResult<&str, String> {
+    map(
+        recognize(pair(
+            alpha1,
+            take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+        )),
+        String::from,
+    )(input)
+}
+
+/// A double-quoted string, in which `""` stands for a quote.
+fn text(input: &str) -> IResult<&str, String> {
+    delimited(
+        char('"'),
+        fold_many0(
+            alt((is_not("\""), value("\"", tag("\"\"")))),
+            String::new,
+            |mut text, part| {
+                text.push_str(part);
+                text
+            },
+        ),
+        char('"'),
+    )(input)
+}
+
+fn node(input: &str) -> IResult<&str, Node> {
+    let (input, keyword) = ws(word)(input)?;
+    let (input, values) = alt((
+        delimited(
+            char('['),
+            separated_list0(char(','), ws(wkt_value)),
+            char(']'),
+        ),
+        delimited(
+            char('('),
+            separated_list0(char(','), ws(wkt_value)),
+            char(')'),
+        ),
+    ))(input)?;
+    Ok((input, Node { keyword, values }))
+}
+
+fn wkt_value(input: &str) -> IResult<&str, WktValue> {
+    alt((
+        map(node, WktValue::Node),
+        map(text, WktValue::Text),
+        map(double, WktValue::Number),
+        map(word, WktValue::Word),
+    ))(input)
+}
+
+impl Node {
+    fn children<'a>(&'a self, keyword: &'a str) -> impl Iterator<Item = &'a Node> {
+        self.values.iter().filter_map(move |value| match value {
+            WktValue::Node(node) if node.keyword.eq_ignore_ascii_case(keyword) => Some(node),
+            _ => None,
+        })
+    }
+
+    fn child<'a>(&'a self, keyword: &'a str) -> anyhow::Result<&'a Node> {
+        self.children(keyword)
+            .next()
+            .context(format!("{} has no {}", self.keyword, keyword))
+    }
+
+    fn text(&self, index: usize) -> anyhow::Result<&str> {
+        match self.values.get(index) {
+            Some(WktValue::Text(text)) => Ok(text),
+            _ => anyhow::bail!("{} is missing text at {}", self.keyword, index),
+        }
+    }
+
+    fn number(&self, index: usize) -> anyhow::Result<f64> {
+        match self.values.get(index) {
+            Some(WktValue::Number(number)) => Ok(*number),
+            _ => anyhow::bail!("{} is missing a number at {}", self.keyword, index),
+        }
+    }
+
+    fn word(&self, index: usize) -> anyhow::Result<&str> {
+        match self.values.get(index) {
+            Some(WktValue::Word(word)) => Ok(word),
+            _ => anyhow::bail!("{} is missing a value at {}", self.keyword, index),
+        }
+    }
+
+    /// The `id` of the node's `AUTHORITY`, if it has one.
+    fn id(&self) -> Option<Value> {
+        let authority = self.children("AUTHORITY").next()?;
+        let name = authority.text(0).ok()?;
+        let code = match authority.values.get(1)? {
+            WktValue::Text(code) => code
+                .parse::<i64>()
+                .map_or_else(|_| json!(code), |code| json!(code)),
+            WktValue::Number(code) => json!(*code as i64),
+            _ => return None,
+        };
+        Some(json!({"authority": name, "code": code}))
+    }
+}
+
+/// Add the node's `id`, if any, to `object`.
+fn with_id(mut object: Value, node: &Node) -> Value {
+    if let (Some(id), Value::Object(map)) = (node.id(), &mut object) {
+        map.insert("id".to_string(), id);
+    }
+    object
+}
+
+#[derive(Clone, Copy)]
+enum UnitKind {
+    Linear,
+    Angular,
+}
+
+fn unit(node: &Node, kind: UnitKind) -> anyhow::Result<Value> {
+    let name = node.text(0)?;
+    let factor = node.number(1)?;
+    let unit = match kind {
+        UnitKind::Linear if factor == 1.0 && matches!(name, "metre" | "meter" | "m") => {
+            json!("metre")
+        }
+        UnitKind::Angular if name == "degree" && (factor - DEGREE).abs() < 1e-15 => {
+            json!("degree")
+        }
+        UnitKind::Linear => {
+            json!({"type": "LinearUnit", "name": name, "conversion_factor": factor})
+        }
+        UnitKind::Angular => {
+            json!({"type": "AngularUnit", "name": name, "conversion_factor": factor})
+        }
+    };
+    Ok(unit)
+}
+
+fn axis_abbreviation(name: &str) -> String {
+    match name.to_ascii_lowercase().as_str() {
+        "latitude" | "geodetic latitude" | "lat" => "Lat".to_string(),
+        "longitude" | "geodetic longitude" | "lon" | "long" => "Lon".to_string(),
+        "easting" => "E".to_string(),
+        "northing" => "N".to_string(),
+        _ => name.chars().take(1).collect::<String>().to_uppercase(),
+    }
+}
+
+/// The `AXIS`es of `node`, or `defaults` as `(name, direction)` pairs if it
+/// has none.
+fn coordinate_system(
+    node: &Node,
+    subtype: &str,
+    unit: &Value,
+    defaults: [(&str, &str); 2],
+) -> anyhow::Result<Value> {
+    let mut axes = node
+        .children("AXIS")
+        .map(|axis| {
+            Ok((
+                axis.text(0)?.to_string(),
+                axis.word(1)?.to_ascii_lowercase(),
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if axes.is_empty() {
+        axes = defaults
+            .iter()
+            .map(|(name, direction)| (name.to_string(), direction.to_string()))
+            .collect();
+    }
+    let axes: Vec<Value> = axes
+        .into_iter()
+        .map(|(name, direction)| {
+            json!({
+                "name": name,
+                "abbreviation": axis_abbreviation(&name),
+                "direction": direction,
+                "unit": unit,
+            })
+        })
+        .collect();
+    Ok(json!({"subtype": subtype, "axis": axes}))
+}
+
+fn ellipsoid(node: &Node) -> anyhow::Result<Value> {
+    let name = node.text(0)?;
+    let semi_major_axis = node.number(1)?;
+    let inverse_flattening = node.number(2)?;
+    let ellipsoid = match inverse_flattening == 0.0 {
+        true => json!({"name": name, "radius": semi_major_axis}),
+        false => json!({
+            "name": name,
+            "semi_major_axis": semi_major_axis,
+            "inverse_flattening": inverse_flattening,
+        }),
+    };
+    Ok(with_id(ellipsoid, node))
+}
+
+fn geographic_crs(node: &Node) -> anyhow::Result<Value> {
+    let datum = node.child("DATUM")?;
+    let mut datum_json = json!({
+        "type": "GeodeticReferenceFrame",
+        "name": datum.text(0)?,
+        "ellipsoid": ellipsoid(datum.child("SPHEROID")?)?,
+    });
+    if let Some(prime_meridian) = node.children("PRIMEM").next() {
+        datum_json["prime_meridian"] = json!({
+            "name": prime_meridian.text(0)?,
+            "longitude": prime_meridian.number(1)?,
+        });
+    }
+    let unit = unit(node.child("UNIT")?, UnitKind::Angular)?;
+    let crs = json!({
+        "type": "GeographicCRS",
+        "name": node.text(0)?,
+        "datum": with_id(datum_json, datum),
+        "coordinate_system": coordinate_system(
+            node,
+            "ellipsoidal",
+            &unit,
+            [("Longitude", "east"), ("Latitude", "north")],
+        )?,
+    });
+    Ok(with_id(crs, node))
+}
+
+/// The EPSG name of a WKT1 projection method.
+fn method_name(name: &str) -> String {
+    let name = match name.to_ascii_lowercase().as_str() {
+        "transverse_mercator" => "Transverse Mercator",
+        "mercator_1sp" => "Mercator (variant A)",
+        "mercator_2sp" => "Mercator (variant B)",
+        "lambert_conformal_conic_1sp" => "Lambert Conic Conformal (1SP)",
+        "lambert_conformal_conic_2sp" => "Lambert Conic Conformal (2SP)",
+        "albers_conic_equal_area" => "Albers Equal Area",
+        "lambert_azimuthal_equal_area" => "Lambert Azimuthal Equal Area",
+        "polar_stereographic" => "Polar Stereographic (variant A)",
+        "oblique_stereographic" => "Oblique Stereographic",
+        _ => return name.replace('_', " "),
+    };
+    name.to_string()
+}
+
+/// The EPSG name of a WKT1 projection parameter. Conic projections name their
+/// origin the false origin rather than the natural one.
+fn parameter_name(name: &str, false_origin: bool) -> String {
+    let name = match (name.to_ascii_lowercase().as_str(), false_origin) {
+        ("latitude_of_origin", false) => "Latitude of natural origin",
+        ("central_meridian", false) => "Longitude of natural origin",
+        ("false_easting", false) => "False easting",
+        ("false_northing", false) => "False northing",
+        ("latitude_of_origin", true) => "Latitude of false origin",
+        ("central_meridian", true) => "Longitude of false origin",
+        ("false_easting", true) => "Easting at false origin",
+        ("false_northing", true) => "Northing at false origin",
+        ("scale_factor", _) => "Scale factor at natural origin",
+        ("standard_parallel_1", _) => "Latitude of 1st standard parallel",
+        ("standard_parallel_2", _) => "Latitude of 2nd standard parallel",
+        _ => return name.replace('_', " "),
+    };
+    name.to_string()
+}
+
+fn parameter_unit(name: &str, linear: &Value, angular: &Value) -> Value {
+    let name = name.to_ascii_lowercase();
+    if name.contains("scale") {
+        json!("unity")
+    } else if [
+        "latitude",
+        "longitude",
+        "meridian",
+        "parallel",
+        "azimuth",
+        "angle",
+    ]
+    .iter()
+    .any(|angle| name.contains(angle))
+    {
+        angular.clone()
+    } else {
+        linear.clone()
+    }
+}
+
+fn projected_crs(node: &Node) -> anyhow::Result<Value> {
+    let base_crs = node.child("GEOGCS")?;
+    let angular = unit(base_crs.child("UNIT")?, UnitKind::Angular)?;
+    let linear = unit(node.child("UNIT")?, UnitKind::Linear)?;
+    let method = method_name(node.child("PROJECTION")?.text(0)?);
+    let false_origin = method == "Lambert Conic Conformal (2SP)" || method == "Albers Equal Area";
+    let parameters = node
+        .children("PARAMETER")
+        .map(|parameter| {
+            let name = parameter.text(0)?;
+            Ok(json!({
+                "name": parameter_name(name, false_origin),
+                "value": parameter.number(1)?,
+                "unit": parameter_unit(name, &linear, &angular),
+            }))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let crs = json!({
+        "type": "ProjectedCRS",
+        "name": node.text(0)?,
+        "base_crs": geographic_crs(base_crs)?,
+        "conversion": {
+            "name": "unknown",
+            "method": {"name": method},
+            "parameters": parameters,
+        },
+        "coordinate_system": coordinate_system(
+            node,
+            "Cartesian",
+            &linear,
+            [("Easting", "east"), ("Northing", "north")],
+        )?,
+    });
+    Ok(with_id(crs, node))
+}
+
+/// Convert an OGC WKT1 `GEOGCS` or `PROJCS`, as found in the `definition` of
+/// `gpkg_spatial_ref_sys` or a `.prj`, to a PROJJSON CRS.
+pub fn from_wkt(wkt: &str) -> anyhow::Result<Value> {
+    let (_rest, node) = all_consuming(terminated(preceded(multispace0, node), multispace0))(wkt)
+        .map_err(|error| anyhow::anyhow!("Invalid WKT CRS: {}", error))?;
+    let crs = match node.keyword.to_ascii_uppercase().as_str() {
+        "GEOGCS" => geographic_crs(&node)?,
+        "PROJCS" => projected_crs(&node)?,
+        keyword => anyhow::bail!("Unsupported WKT CRS {}", keyword),
+    };
+    let mut object = Map::new();
+    object.insert("$schema".to_string(), json!(SCHEMA));
+    if let Value::Object(crs) = crs {
+        object.extend(crs);
+    }
+    Ok(Value::Object(object))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BRITISH_NATIONAL_GRID: &str = r#"PROJCS["OSGB36 / British National Grid",GEOGCS["OSGB36",DATUM["Ordnance_Survey_of_Great_Britain_1936",SPHEROID["Airy 1830",6377563.396,299.3249646],AUTHORITY["EPSG","6277"]],PRIMEM["Greenwich",0],UNIT["degree",0.0174532925199433,AUTHORITY["EPSG","9122"]],AUTHORITY["EPSG","4277"]],PROJECTION["Transverse_Mercator"],PARAMETER["latitude_of_origin",49],PARAMETER["central_meridian",-2],PARAMETER["scale_factor",0.9996012717],PARAMETER["false_easting",400000],PARAMETER["false_northing",-100000],UNIT["metre",1],AXIS["Easting",EAST],AXIS["Northing",NORTH],AUTHORITY["EPSG","27700"]]"#;
+
+    #[test]
+    fn test_projected_crs() {
+        let crs = from_wkt(BRITISH_NATIONAL_GRID).unwrap();
+
+        assert_eq!(SCHEMA, crs["$schema"]);
+        assert_eq!("ProjectedCRS", crs["type"]);
+        assert_eq!("OSGB36 / British National Grid", crs["name"]);
+        assert_eq!(json!({"authority": "EPSG", "code": 27700}), crs["id"]);
+        assert_eq!("GeographicCRS", crs["base_crs"]["type"]);
+        assert_eq!(
+            json!({"name": "Airy 1830", "semi_major_axis": 6377563.396, "inverse_flattening": 299.3249646}),
+            crs["base_crs"]["datum"]["ellipsoid"]
+        );
+        assert_eq!("Transverse Mercator", crs["conversion"]["method"]["name"]);
+        assert_eq!(
+            json!({"name": "Scale factor at natural origin", "value": 0.9996012717, "unit": "unity"}),
+            crs["conversion"]["parameters"][2]
+        );
+        assert_eq!(
+            json!({"name": "Longitude of natural origin", "value": -2.0, "unit": "degree"}),
+            crs["conversion"]["parameters"][1]
+        );
+        assert_eq!(
+            json!({"name": "Northing", "abbreviation": "N", "direction": "north", "unit": "metre"}),
+            crs["coordinate_system"]["axis"][1]
+        );
+    }
+
+    #[test]
+    fn test_geographic_crs() {
+        let crs = from_wkt(r#"GEOGCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563]],PRIMEM["Greenwich",0],UNIT["degree",0.0174532925199433],AXIS["Latitude",NORTH],AXIS["Longitude",EAST],AUTHORITY["EPSG","4326"]]"#).unwrap();
+
+        assert_eq!("GeographicCRS", crs["type"]);
+        assert_eq!("ellipsoidal", crs["coordinate_system"]["subtype"]);
+        assert_eq!("Lat", crs["coordinate_system"]["axis"][0]["abbreviation"]);
+        assert_eq!(
+            json!({"name": "Greenwich", "longitude": 0.0}),
+            crs["datum"]["prime_meridian"]
+        );
+        assert_eq!(json!({"authority": "EPSG", "code": 4326}), crs["id"]);
+    }
+
+    #[test]
+    fn test_unsupported_wkt() {
+        assert!(from_wkt(r#"GEOGCS["WGS 84"]"#).is_err());
+        assert!(from_wkt("undefined").is_err());
+        assert!(from_wkt(
+            r#"GEOCCS["WGS 84",DATUM["WGS_1984",SPHEROID["WGS 84",6378137,298.257223563]]]"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,255 @@
+use arrow::{pyarrow::PyArrowConvert, record_batch::RecordBatch};
+use pyo3::{
+    exceptions::PyIOError,
+    prelude::*,
+    types::{PyDict, PyList},
+};
+
+use crate::{
+    dataset::{Dataset, GeometryFormat, ReadOptions},
+    geoarrow,
+};
+
+fn to_py_err(error: anyhow::Error) -> PyErr {
+    PyIOError::new_err(format!("{:#}", error))
+}
+
+/// A dataset opened with `ogr2arrow.open`.
+#[pyclass(name = "Dataset", unsendable)]
+struct PyDataset {
+    dataset: Dataset,
+}
+
+fn to_table(py: Python, record_batch: &RecordBatch) -> PyResult<PyObject> {
+    let record_batch = record_batch.to_pyarrow(py)?;
+    let table = py
+        .import("pyarrow")?
+        .getattr("Table")?
+        .call_method1("from_batches", (vec![record_batch],))?;
+    Ok(table.into())
+}
+
+impl PyDataset {
+    fn read(
+        &self,
+        name: &str,
+        columns: Option<Vec<String>>,
+        bbox: Option<[f64; 4]>,
+        filter: Option<String>,
+    ) -> PyResult<RecordBatch> {
+        let options = ReadOptions {
+            bbox,
+            columns,
+            geometry_format: GeometryFormat::Native,
+            filter,
+        };
+        self.dataset
+            .get_layer_with_options(name, &options)
+            .map_err(to_py_err)
+    }
+}
+
+/// Convert a layer to a `geopandas.GeoDataFrame`, with the first geometry
+/// column as the active geometry.
+fn to_dataframe(
+    py: Python,
+    geopandas: &PyModule,
+    record_batch: &RecordBatch,
+) -> PyResult<PyObject> {
+    // GeoPandas parses the geometries from WKB, which keeps the coordinates
+    // and dimensions exactly.
+    let record_batch = geoarrow::geometries_to_wkb(record_batch).map_err(to_py_err)?;
+    let frame = to_table(py, &record_batch)?.call_method0(py, "to_pandas")?;
+    let frame = frame.as_ref(py);
+
+    let schema = record_batch.schema();
+    let mut geometry = None;
+    let mut crs = None;
+    for field in schema
+        .fields()
+        .iter()
+        .filter(|field| geoarrow::is_geometry_field(field))
+    {
+        let field_crs = geoarrow::get_extension_metadata(field).and_then(|metadata| metadata.crs);
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("crs", field_crs.clone())?;
+        let series = geopandas.getattr("GeoSeries")?.call_method(
+            "from_wkb",
+            (frame.get_item(field.name())?,),
+            Some(kwargs),
+        )?;
+        frame.set_item(field.name(), series)?;
+        if geometry.is_none() {
+            geometry = Some(field.name().clone());
+            crs = field_crs;
+        }
+    }
+
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("geometry", geometry)?;
+    kwargs.set_item("crs", crs)?;
+    let frame = geopandas
+        .getattr("GeoDataFrame")?
+        .call((frame,), Some(kwargs))?;
+    Ok(frame.into())
+}
+
+#[pymethods]
+impl PyDataset {
+    fn list_layers(&self) -> PyResult<Vec<String>> {
+        self.dataset.list_layers().map_err(to_py_err)
+    }
+
+    /// Read a layer as a `geopandas.GeoDataFrame` if GeoPandas is installed,
+    /// and otherwise as a `pyarrow.Table`, optionally only `columns`, the
+    /// features intersecting `bbox` and the rows matching the SQL `where`.
+    #[args(columns = "None", bbox = "None", r#where = "None")]
+    fn read_layer(
+        &self,
+        py: Python,
+        name: &str,
+        columns: Option<Vec<String>>,
+        bbox: Option<[f64; 4]>,
+        r#where: Option<String>,
+    ) -> PyResult<PyObject> {
+        let record_batch = self.read(name, columns, bbox, r#where)?;
+        match py.import("geopandas") {
+            Ok(geopandas) => to_dataframe(py, geopandas, &record_batch),
+            Err(_) => to_table(py, &record_batch),
+        }
+    }
+
+    /// Read a layer as a `pyarrow.Table`, with GeoArrow geometry columns.
+    #[args(columns = "None", bbox = "None", r#where = "None")]
+    fn read_table(
+        &self,
+        py: Python,
+        name: &str,
+        columns: Option<Vec<String>>,
+        bbox: Option<[f64; 4]>,
+        r#where: Option<String>,
+    ) -> PyResult<PyObject> {
+        let record_batch = self.read(name, columns, bbox, r#where)?;
+        to_table(py, &record_batch)
+    }
+
+    /// Read a layer as a `geopandas.GeoDataFrame`, which requires GeoPandas.
+    #[args(columns = "None", bbox = "None", r#where = "None")]
+    fn read_dataframe(
+        &self,
+        py: Python,
+        name: &str,
+        columns: Option<Vec<String>>,
+        bbox: Option<[f64; 4]>,
+        r#where: Option<String>,
+    ) -> PyResult<PyObject> {
+        let record_batch = self.read(name, columns, bbox, r#where)?;
+        to_dataframe(py, py.import("geopandas")?, &record_batch)
+    }
+
+    /// The name, geometry column, CRS and field types of a layer, as a dict.
+    fn layer_info(&self, py: Python, name: &str) -> PyResult<PyObject> {
+        let layer = self.dataset.layer(name).map_err(to_py_err)?;
+        let info = PyDict::new(py);
+        info.set_item("name", layer.name())?;
+        info.set_item(
+            "geometry_column",
+            layer.geometry_field().map(|field| field.name().clone()),
+        )?;
+        info.set_item(
+            "crs",
+            layer.extension_metadata().and_then(|metadata| metadata.crs),
+        )?;
+        let fields = PyList::empty(py);
+        for field in layer.schema().fields() {
+            fields.append((field.name(), field.data_type().to_pyarrow(py)?))?;
+        }
+        info.set_item("fields", fields)?;
+        Ok(info.into())
+    }
+}
+
+/// Open the dataset at `path`, detecting its format.
+#[pyfunction]
+fn open(path: &str) -> PyResult<PyDataset> {
+    let dataset = Dataset::open(path).map_err(to_py_err)?;
+    Ok(PyDataset { dataset })
+}
+
+#[pymodule]
+fn ogr2arrow(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyDataset>()?;
+    module.add_function(wrap_pyfunction!(open, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_open() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "ogr2arrow").unwrap();
+            ogr2arrow(py, module).unwrap();
+
+            let dataset = module
+                .getattr("open")
+                .unwrap()
+                .call1(("Data/spatialite.sqlite",))
+                .unwrap();
+            let layers: Vec<String> = dataset
+                .call_method0("list_layers")
+                .unwrap()
+                .extract()
+                .unwrap();
+            assert_eq!(vec!["point", "polygons"], layers);
+
+            let error = module
+                .getattr("open")
+                .unwrap()
+                .call1(("Data/missing.gpkg",))
+                .unwrap_err();
+            assert!(error.is_instance_of::<PyIOError>(py));
+        });
+    }
+
+    #[test]
+    fn test_read_layer() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            // The conversions need pyarrow, which is not always installed
+            // alongside the tests.
+            if py.import("pyarrow").is_err() {
+                return;
+            }
+            let module = PyModule::new(py, "ogr2arrow").unwrap();
+            ogr2arrow(py, module).unwrap();
+            let dataset = module
+                .getattr("open")
+                .unwrap()
+                .call1(("Data/point.gpkg",))
+                .unwrap();
+
+            let table = dataset.call_method1("read_table", ("point",)).unwrap();
+            let num_rows: usize = table.getattr("num_rows").unwrap().extract().unwrap();
+            assert_eq!(2, num_rows);
+            let names: Vec<String> = table.getattr("column_names").unwrap().extract().unwrap();
+            assert_eq!(vec!["fid", "geom", "name"], names);
+
+            let layer = dataset.call_method1("read_layer", ("point",)).unwrap();
+            assert_eq!(2, layer.len().unwrap());
+            if py.import("geopandas").is_ok() {
+                let geometry_name: String = layer
+                    .getattr("geometry")
+                    .unwrap()
+                    .getattr("name")
+                    .unwrap()
+                    .extract()
+                    .unwrap();
+                assert_eq!("geom", geometry_name);
+            }
+        });
+    }
+}
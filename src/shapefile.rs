@@ -0,0 +1,733 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use arrow::{
+    array::{
+        ArrayRef, BinaryArray, BooleanArray, Date32Array, Float64Array, Int32Array, Int64Array,
+        StringArray,
+    },
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use binread::{io::Cursor, BinReaderExt};
+use chrono::NaiveDate;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver},
+    geoarrow::{self, ExtensionMetadata},
+    wkb::{
+        Coordinate, Dimension, LinearRing, WkbGeometry, WkbLineString, WkbMultiLineString,
+        WkbMultiPoint, WkbMultiPolygon, WkbPoint, WkbPolygon,
+    },
+};
+
+/// The name given to the geometry column of a shapefile layer.
+const GEOMETRY_COLUMN: &str = "geom";
+
+/// The `.shp` and `.shx` file code.
+const FILE_CODE: i32 = 9994;
+
+/// Measures below this are "no data" in a shapefile.
+const NO_DATA: f64 = -1e38;
+
+/// A field descriptor of a `.dbf` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DbfField {
+    name: String,
+    field_type: u8,
+    length: usize,
+    decimal_count: u8,
+}
+
+impl DbfField {
+    /// Map the field type, following GDAL for `N` fields without decimals.
+    fn data_type(&self) -> DataType {
+        match self.field_type {
+            b'N' if self.decimal_count == 0 && self.length < 10 => DataType::Int32,
+            b'N' if self.decimal_count == 0 && self.length < 19 => DataType::Int64,
+            b'N' | b'F' => DataType::Float64,
+            b'L' => DataType::Boolean,
+            b'D' => DataType::Date32,
+            _ => DataType::Utf8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DbfHeader {
+    num_records: usize,
+    header_length: usize,
+    record_length: usize,
+    language_driver: u8,
+    fields: Vec<DbfField>,
+}
+
+fn read_dbf_header(bytes: &[u8]) -> anyhow::Result<DbfHeader> {
+    let mut cursor = Cursor::new(bytes);
+    cursor.set_position(4);
+    let num_records: u32 = cursor.read_le()?;
+    let header_length: u16 = cursor.read_le()?;
+    let record_length: u16 = cursor.read_le()?;
+    let language_driver = *bytes.get(29).context("Truncated dBASE header")?;
+
+    let mut fields = Vec::new();
+    let descriptors = bytes
+        .get(32..header_length as usize)
+        .context("Truncated dBASE header")?;
+    for descriptor in descriptors.chunks_exact(32) {
+        if descriptor[0] == 0x0d {
+            break;
+        }
+        let name = &descriptor[..11];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(11)];
+        fields.push(DbfField {
+            name: String::from_utf8_lossy(name).into_owned(),
+            field_type: descriptor[11],
+            length: descriptor[16] as usize,
+            decimal_count: descriptor[17],
+        });
+    }
+    // Records start with a deletion flag, followed by the fields.
+    let fields_length: usize = fields.iter().map(|field| field.length).sum();
+    anyhow::ensure!(
+        fields_length < record_length as usize,
+        "dBASE fields of {} bytes do not fit in records of {}",
+        fields_length,
+        record_length
+    );
+    Ok(DbfHeader {
+        num_records: num_records as usize,
+        header_length: header_length as usize,
+        record_length: record_length as usize,
+        language_driver,
+        fields,
+    })
+}
+
+/// The encoding of the `.dbf` strings, taken from the `.cpg` file if there is
+/// one and otherwise from the language driver id, defaulting to Latin-1.
+fn get_encoding(cpg_path: &Path, language_driver: u8) -> &'static Encoding {
+    if let Ok(code_page) = std::fs::read_to_string(cpg_path) {
+        let code_page = code_page.trim();
+        let label = match code_page.parse::<u32>() {
+            Ok(88591) => "iso-8859-1".to_string(),
+            Ok(number) => format!("windows-{}", number),
+            Err(_) => code_page.to_string(),
+        };
+        if let Some(encoding) = Encoding::for_label(label.as_bytes()) {
+            return encoding;
+        }
+    }
+    let label: &[u8] = match language_driver {
+        0x13 => b"shift_jis",
+        0x4d => b"gbk",
+        0x4e => b"euc-kr",
+        0x4f => b"big5",
+        0x57 | 0x03 => b"windows-1252",
+        0x65 => b"ibm866",
+        0xc8 => b"windows-1250",
+        0xc9 => b"windows-1251",
+        0xca => b"windows-1254",
+        0xcb => b"windows-1253",
+        _ => return WINDOWS_1252,
+    };
+    Encoding::for_label(label).unwrap_or(UTF_8)
+}
+
+/// The `(authority, code)` of the outermost `AUTHORITY` or `ID` of a WKT CRS.
+fn get_authority_code(wkt: &str) -> Option<(String, i32)> {
+    let mut depth = 0;
+    let mut authority = None;
+    for (index, character) in wkt.char_indices() {
+        match character {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            ',' if depth == 1 => {
+                let rest = wkt[index + 1..].trim_start();
+                let arguments = rest
+                    .strip_prefix("AUTHORITY[")
+                    .or_else(|| rest.strip_prefix("ID["));
+                if let Some(arguments) = arguments {
+                    let mut arguments = arguments.split([',', ']']);
+                    let name = arguments.next()?.trim().trim_matches('"');
+                    let code = arguments.next()?.trim().trim_matches('"');
+                    authority = code.parse().ok().map(|code| (name.to_string(), code));
+                }
+            }
+            _ => {}
+        }
+    }
+    authority
+}
+
+fn read_prj(prj_path: &Path) -> ExtensionMetadata {
+    match std::fs::read_to_string(prj_path) {
+        Ok(wkt) => match get_authority_code(&wkt) {
+            Some((authority, code)) => ExtensionMetadata::from_authority_code(&authority, code),
+            None => ExtensionMetadata {
+                crs: Some(wkt.trim().to_string()),
+                crs_type: None,
+            },
+        },
+        Err(_) => ExtensionMetadata::default(),
+    }
+}
+
+fn get_dimension(shape_type: i32) -> Dimension {
+    match shape_type {
+        11 | 13 | 15 | 18 | 31 => Dimension::Xyz,
+        21 | 23 | 25 | 28 => Dimension::Xym,
+        _ => Dimension::Xy,
+    }
+}
+
+fn read_values(cursor: &mut Cursor<&[u8]>, count: usize) -> anyhow::Result<Vec<f64>> {
+    (0..count).map(|_| Ok(cursor.read_le::<f64>()?)).collect()
+}
+
+/// Read `num_points` XY pairs followed by the Z and M arrays of the shape
+/// type. The M array of Z shapes is optional and only read when the record
+/// is long enough to hold it.
+fn read_coordinates(
+    cursor: &mut Cursor<&[u8]>,
+    shape_type: i32,
+    num_points: usize,
+) -> anyhow::Result<Vec<Coordinate>> {
+    let xy = read_values(cursor, num_points * 2)?;
+    let mut coordinates: Vec<Coordinate> = xy
+        .chunks_exact(2)
+        .map(|xy| Coordinate::new(xy[0], xy[1]))
+        .collect();
+    let dimension = get_dimension(shape_type);
+    // Single points have no range before their Z and M values.
+    let range = match shape_type {
+        11 | 21 => 0,
+        _ => 2,
+    };
+    if dimension.has_z() {
+        let z = read_values(cursor, range + num_points)?;
+        coordinates
+            .iter_mut()
+            .zip(&z[range..])
+            .for_each(|(coordinate, z)| coordinate.z = Some(*z));
+    }
+    let remaining = cursor.get_ref().len() - cursor.position() as usize;
+    if dimension.has_m() || (dimension.has_z() && remaining >= (range + num_points) * 8) {
+        let m = read_values(cursor, range + num_points)?;
+        coordinates
+            .iter_mut()
+            .zip(&m[range..])
+            .for_each(|(coordinate, m)| {
+                coordinate.m = Some(if *m < NO_DATA { f64::NAN } else { *m })
+            });
+    }
+    Ok(coordinates)
+}
+
+/// Read the parts of a multi-part shape, whose content starts after its box.
+fn read_parts(cursor: &mut Cursor<&[u8]>, shape_type: i32) -> anyhow::Result<Vec<Vec<Coordinate>>> {
+    let num_parts: i32 = cursor.read_le()?;
+    let num_points: i32 = cursor.read_le()?;
+    let mut starts = (0..num_parts)
+        .map(|_| Ok(cursor.read_le::<i32>()? as usize))
+        .collect::<anyhow::Result<Vec<usize>>>()?;
+    let coordinates = read_coordinates(cursor, shape_type, num_points as usize)?;
+    starts.push(coordinates.len());
+    starts
+        .windows(2)
+        .map(|part| {
+            coordinates
+                .get(part[0]..part[1])
+                .map(<[Coordinate]>::to_vec)
+                .context("Shape part is out of bounds")
+        })
+        .collect()
+}
+
+/// Twice the signed area of a ring, negative when it is clockwise.
+fn signed_area(ring: &[Coordinate]) -> f64 {
+    ring.windows(2)
+        .map(|pair| pair[0].x * pair[1].y - pair[1].x * pair[0].y)
+        .sum()
+}
+
+fn contains(ring: &[Coordinate], point: &Coordinate) -> bool {
+    let mut inside = false;
+    for pair in ring.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Group shapefile rings into polygons: clockwise rings are shells and
+/// counter-clockwise rings are holes of the shell that contains them.
+fn build_polygons(rings: Vec<Vec<Coordinate>>) -> WkbGeometry {
+    let mut polygons: Vec<Vec<Vec<Coordinate>>> = Vec::new();
+    let mut holes = Vec::new();
+    for ring in rings {
+        match signed_area(&ring) <= 0.0 {
+            true => polygons.push(vec![ring]),
+            false => holes.push(ring),
+        }
+    }
+    for hole in holes {
+        let shell = polygons.iter_mut().rev().find(|polygon| {
+            hole.first()
+                .is_some_and(|point| contains(&polygon[0], point))
+        });
+        match shell {
+            Some(polygon) => polygon.push(hole),
+            None => polygons.push(vec![hole]),
+        }
+    }
+    let mut polygons: Vec<WkbPolygon> = polygons
+        .into_iter()
+        .map(|rings| WkbPolygon::new(rings.into_iter().map(LinearRing::new).collect()))
+        .collect();
+    match polygons.len() {
+        1 => WkbGeometry::Polygon(polygons.remove(0)),
+        _ => WkbGeometry::MultiPolygon(WkbMultiPolygon::new(polygons)),
+    }
+}
+
+/// Decode the content of a shape record, `None` for a null shape or a
+/// MultiPatch, whose triangle strips and fans have no simple features
+/// equivalent.
+fn read_shape(content: &[u8]) -> anyhow::Result<Option<WkbGeometry>> {
+    let mut cursor = Cursor::new(content);
+    let shape_type: i32 = cursor.read_le()?;
+    let geometry = match shape_type {
+        0 | 31 => return Ok(None),
+        1 | 11 | 21 => {
+            let mut coordinates = read_coordinates(&mut cursor, shape_type, 1)?;
+            WkbGeometry::Point(WkbPoint::new(coordinates.remove(0)))
+        }
+        8 | 18 | 28 => {
+            cursor.set_position(cursor.position() + 32);
+            let num_points: i32 = cursor.read_le()?;
+            let coordinates = read_coordinates(&mut cursor, shape_type, num_points as usize)?;
+            WkbGeometry::MultiPoint(WkbMultiPoint::new(
+                coordinates.into_iter().map(WkbPoint::new).collect(),
+            ))
+        }
+        3 | 13 | 23 => {
+            cursor.set_position(cursor.position() + 32);
+            let mut line_strings: Vec<WkbLineString> = read_parts(&mut cursor, shape_type)?
+                .into_iter()
+                .map(WkbLineString::new)
+                .collect();
+            match line_strings.len() {
+                1 => WkbGeometry::LineString(line_strings.remove(0)),
+                _ => WkbGeometry::MultiLineString(WkbMultiLineString::new(line_strings)),
+            }
+        }
+        5 | 15 | 25 => {
+            cursor.set_position(cursor.position() + 32);
+            build_polygons(read_parts(&mut cursor, shape_type)?)
+        }
+        shape_type => anyhow::bail!("Unsupported shape type {}", shape_type),
+    };
+    Ok(Some(geometry))
+}
+
+/// The content of every record of a `.shp` file, located through the `.shx`
+/// index when there is one.
+fn read_records<'a>(shp: &'a [u8], shx: Option<&[u8]>) -> anyhow::Result<Vec<&'a [u8]>> {
+    let mut offsets = Vec::new();
+    match shx {
+        Some(shx) => {
+            let mut cursor = Cursor::new(shx);
+            cursor.set_position(100);
+            while (cursor.position() as usize) < shx.len() {
+                let offset: i32 = cursor.read_be()?;
+                let length: i32 = cursor.read_be()?;
+                offsets.push((offset as usize * 2, length as usize * 2));
+            }
+        }
+        None => {
+            let mut cursor = Cursor::new(shp);
+            cursor.set_position(100);
+            while cursor.position() as usize + 8 <= shp.len() {
+                let offset = cursor.position() as usize;
+                let _record_number: i32 = cursor.read_be()?;
+                let length: i32 = cursor.read_be()?;
+                offsets.push((offset, length as usize * 2));
+                cursor.set_position((offset + 8 + length as usize * 2) as u64);
+            }
+        }
+    }
+    offsets
+        .into_iter()
+        .map(|(offset, length)| {
+            shp.get(offset + 8..offset + 8 + length)
+                .context("Shape record is out of bounds")
+        })
+        .collect()
+}
+
+/// An ESRI Shapefile, the `.shp` with its sibling `.shx`, `.dbf`, `.prj` and
+/// `.cpg` files, exposed as a single layer named after the file stem.
+pub struct Shapefile {
+    path: PathBuf,
+    schema: SchemaRef,
+    dbf_header: Option<DbfHeader>,
+    encoding: &'static Encoding,
+}
+
+impl Shapefile {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let shp = std::fs::read(path)?;
+        let mut cursor = Cursor::new(&shp);
+        let file_code: i32 = cursor.read_be()?;
+        anyhow::ensure!(file_code == FILE_CODE, "Not a shapefile");
+        cursor.set_position(32);
+        let shape_type: i32 = cursor.read_le()?;
+
+        let dbf_header = match std::fs::read(path.with_extension("dbf")) {
+            Ok(dbf) => Some(read_dbf_header(&dbf)?),
+            Err(_) => None,
+        };
+        let encoding = get_encoding(
+            &path.with_extension("cpg"),
+            dbf_header
+                .as_ref()
+                .map_or(0, |header| header.language_driver),
+        );
+
+        let mut fields: Vec<Field> = dbf_header
+            .iter()
+            .flat_map(|header| &header.fields)
+            .map(|field| Field::new(&field.name, field.data_type(), true))
+            .collect();
+        let data_type = match shape_type {
+            1 => geoarrow::point_data_type(),
+            _ => DataType::Binary,
+        };
+        fields.push(geoarrow::geometry_field(
+            GEOMETRY_COLUMN,
+            data_type,
+            &read_prj(&path.with_extension("prj")),
+        ));
+
+        Ok(Shapefile {
+            path: path.to_path_buf(),
+            schema: Arc::new(Schema::new(fields)),
+            dbf_header,
+            encoding,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    pub fn layer_name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn get_attribute_column<'a>(
+        &self,
+        field: &DbfField,
+        values: impl Iterator<Item = &'a [u8]>,
+    ) -> ArrayRef {
+        let text = values.map(|value| {
+            let (text, _, _) = self.encoding.decode(value);
+            let text = text
+                .trim_matches(|c: char| c == ' ' || c == '\0')
+                .to_string();
+            match text.is_empty() || text.starts_with('*') {
+                true => None,
+                false => Some(text),
+            }
+        });
+        match field.data_type() {
+            DataType::Int32 => Arc::new(
+                text.map(|text| text.and_then(|text| text.parse().ok()))
+                    .collect::<Int32Array>(),
+            ),
+            DataType::Int64 => Arc::new(
+                text.map(|text| text.and_then(|text| text.parse().ok()))
+                    .collect::<Int64Array>(),
+            ),
+            DataType::Float64 => Arc::new(
+                text.map(|text| text.and_then(|text| text.parse().ok()))
+                    .collect::<Float64Array>(),
+            ),
+            DataType::Boolean => Arc::new(
+                text.map(|text| match text.as_deref() {
+                    Some("T" | "t" | "Y" | "y") => Some(true),
+                    Some("F" | "f" | "N" | "n") => Some(false),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+            ),
+            DataType::Date32 => {
+                let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+                Arc::new(
+                    text.map(|text| {
+                        let date = NaiveDate::parse_from_str(&text?, "%Y%m%d").ok()?;
+                        Some(date.signed_duration_since(epoch).num_days() as i32)
+                    })
+                    .collect::<Date32Array>(),
+                )
+            }
+            _ => Arc::new(text.collect::<StringArray>()),
+        }
+    }
+
+    fn get_geometry_column(&self, geometries: Vec<Option<WkbGeometry>>) -> ArrayRef {
+        match self.schema.fields().last().map(Field::data_type) {
+            Some(DataType::FixedSizeList(_field, 2)) => geoarrow::point_array(
+                geometries
+                    .iter()
+                    .map(|geometry| match geometry {
+                        Some(WkbGeometry::Point(point)) => Some([point.point.x, point.point.y]),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            _ => {
+                let wkb: Vec<Option<Vec<u8>>> = geometries
+                    .iter()
+                    .map(|geometry| geometry.as_ref().map(WkbGeometry::to_wkb))
+                    .collect();
+                Arc::new(BinaryArray::from_opt_vec(
+                    wkb.iter().map(|wkb| wkb.as_deref()).collect(),
+                ))
+            }
+        }
+    }
+
+    /// Read the layer, skipping the records marked as deleted in the `.dbf`.
+    pub fn read(&self, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        let shp = std::fs::read(&self.path)?;
+        let shx = std::fs::read(self.path.with_extension("shx")).ok();
+        let shapes = read_records(&shp, shx.as_deref())?;
+
+        let dbf = match &self.dbf_header {
+            Some(_) => std::fs::read(self.path.with_extension("dbf"))?,
+            None => Vec::new(),
+        };
+        let records: Vec<Option<&[u8]>> = match &self.dbf_header {
+            Some(header) => (0..header.num_records)
+                .map(|index| {
+                    let start = header.header_length + index * header.record_length;
+                    dbf.get(start..start + header.record_length)
+                        .context("dBASE record is out of bounds")
+                        .map(|record| (record[0] != b'*').then_some(record))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            None => vec![Some(&[][..]); shapes.len()],
+        };
+        anyhow::ensure!(
+            records.len() == shapes.len(),
+            "The .shp has {} records but the .dbf has {}",
+            shapes.len(),
+            records.len()
+        );
+
+        let mut geometries = Vec::new();
+        let mut attributes = Vec::new();
+        for (shape, record) in shapes.into_iter().zip(records) {
+            if let Some(record) = record {
+                geometries.push(read_shape(shape)?);
+                attributes.push(record);
+            }
+        }
+
+        let mut columns = Vec::new();
+        let mut offset = 1;
+        for field in self.dbf_header.iter().flat_map(|header| &header.fields) {
+            let values = attributes
+                .iter()
+                .map(|record| &record[offset..offset + field.length]);
+            columns.push(self.get_attribute_column(field, values));
+            offset += field.length;
+        }
+        columns.push(self.get_geometry_column(geometries));
+
+        let record_batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        options.apply(record_batch)
+    }
+}
+
+impl Driver for Shapefile {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| *format == Format::Shapefile)
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        Shapefile::open(path)
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(vec![self.layer_name()])
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        Ok(self.schema())
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        driver::ensure_layer(self.path(), layer_name, &self.layer_name())?;
+        self.read(options)
+    }
+
+    /// The `.prj` WKT, if it is the definition of `authority:code`.
+    fn crs_definition(&self, authority: &str, code: i32) -> anyhow::Result<Option<String>> {
+        let wkt = match std::fs::read_to_string(self.path.with_extension("prj")) {
+            Ok(wkt) => wkt,
+            Err(_) => return Ok(None),
+        };
+        Ok(match get_authority_code(&wkt) {
+            Some((prj_authority, prj_code)) if prj_authority == authority && prj_code == code => {
+                Some(wkt.trim().to_string())
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dataset::Dataset;
+    use arrow::array::{as_boolean_array, as_primitive_array, as_string_array};
+    use arrow::datatypes::{Date32Type, Float64Type, Int32Type};
+
+    #[test]
+    fn test_read_point_layer() {
+        let expected_layer = Dataset::open("Data/point.gpkg")
+            .unwrap()
+            .get_layer("point")
+            .unwrap();
+
+        let layer = Dataset::open("Data/point.shp")
+            .unwrap()
+            .get_layer("point")
+            .unwrap();
+
+        let geometry_field = layer.schema().field(1).clone();
+        assert_eq!(
+            expected_layer.schema().field(1).data_type(),
+            geometry_field.data_type()
+        );
+        assert_eq!(
+            Some(("EPSG", 27700)),
+            geoarrow::get_extension_metadata(&geometry_field)
+                .unwrap()
+                .authority_code()
+        );
+        assert_eq!(expected_layer.column(1), layer.column(1));
+        assert_eq!(expected_layer.column(2), layer.column(0));
+
+        let file = Shapefile::open(Path::new("Data/point.shp")).unwrap();
+        let definition = file.crs_definition("EPSG", 27700).unwrap().unwrap();
+        assert!(definition.starts_with(r#"PROJCS["OSGB 1936 / British National Grid""#));
+        assert_eq!(None, file.crs_definition("EPSG", 4326).unwrap());
+    }
+
+    #[test]
+    fn test_read_polygon_z_layer() {
+        let file = Shapefile::open(Path::new("Data/polygon_z.shp")).unwrap();
+        let layer = file.read(&ReadOptions::default()).unwrap();
+
+        let schema = layer.schema();
+        let data_types: Vec<&DataType> = schema.fields().iter().map(Field::data_type).collect();
+        assert_eq!(
+            vec![
+                &DataType::Utf8,
+                &DataType::Int32,
+                &DataType::Float64,
+                &DataType::Float64,
+                &DataType::Boolean,
+                &DataType::Date32,
+                &DataType::Binary
+            ],
+            data_types
+        );
+        assert_eq!("Café", as_string_array(layer.column(0)).value(0));
+        assert!(layer.column(1).is_null(1));
+        assert_eq!(
+            12,
+            as_primitive_array::<Int32Type>(layer.column(1)).value(2)
+        );
+        assert_eq!(
+            -1.25,
+            as_primitive_array::<Float64Type>(layer.column(3)).value(2)
+        );
+        assert!(as_boolean_array(layer.column(4)).value(0));
+        assert!(layer.column(4).is_null(1));
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2022, 8, 1),
+            as_primitive_array::<Date32Type>(layer.column(5)).value_as_date(0)
+        );
+
+        let geometries: Vec<Option<WkbGeometry>> = (0..3)
+            .map(|row| geoarrow::get_geometry(layer.column(6).as_ref(), row).unwrap())
+            .collect();
+        match &geometries[0] {
+            Some(WkbGeometry::Polygon(polygon)) => {
+                assert_eq!(2, polygon.rings.len());
+                assert_eq!(Dimension::Xyzm, polygon.dimension);
+                assert_eq!(Some(1.0), polygon.rings[1].coordinates()[0].z);
+            }
+            geometry => panic!("Expected a polygon, got {:?}", geometry),
+        }
+        assert_eq!(None, geometries[1]);
+        match &geometries[2] {
+            Some(WkbGeometry::MultiPolygon(multi_polygon)) => {
+                assert_eq!(2, multi_polygon.polygons.len())
+            }
+            geometry => panic!("Expected a multipolygon, got {:?}", geometry),
+        }
+    }
+
+    #[test]
+    fn test_read_multipatch_and_truncated_header() {
+        let mut content = 31i32.to_le_bytes().to_vec();
+        content.extend_from_slice(&[0; 32]);
+        assert_eq!(None, read_shape(&content).unwrap());
+
+        let mut header = vec![0u8; 32];
+        header[8..10].copy_from_slice(&97u16.to_le_bytes());
+        assert!(read_dbf_header(&header).is_err());
+
+        let mut descriptor = [0u8; 32];
+        descriptor[..4].copy_from_slice(b"name");
+        descriptor[11] = b'C';
+        descriptor[16] = 10;
+        header.extend_from_slice(&descriptor);
+        header.push(0x0d);
+        header[8..10].copy_from_slice(&65u16.to_le_bytes());
+        header[10..12].copy_from_slice(&10u16.to_le_bytes());
+        assert!(read_dbf_header(&header).is_err());
+        header[10..12].copy_from_slice(&11u16.to_le_bytes());
+        assert_eq!(1, read_dbf_header(&header).unwrap().fields.len());
+    }
+
+    #[test]
+    fn test_get_authority_code() {
+        let wkt = r#"PROJCS["x",GEOGCS["y",AUTHORITY["EPSG","4277"]],AUTHORITY["EPSG","27700"]]"#;
+        assert_eq!(Some(("EPSG".to_string(), 27700)), get_authority_code(wkt));
+        assert_eq!(None, get_authority_code(r#"GEOGCS["GCS_WGS_1984"]"#));
+    }
+}
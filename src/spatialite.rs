@@ -0,0 +1,338 @@
+use std::{path::Path, sync::Arc};
+
+use anyhow::Context;
+use arrow::{
+    datatypes::{Schema, SchemaRef},
+    record_batch::RecordBatch,
+};
+use binread::{
+    io::{Cursor, Read},
+    BinReaderExt, Endian,
+};
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Connection, OptionalExtension};
+
+use crate::{
+    dataset::{Format, ReadOptions},
+    driver::{self, Driver, RecordBatches},
+    geoarrow::ExtensionMetadata,
+    gpkg, parallel,
+    wkb::{Dimension, WkbGeometry, WkbGeometryType},
+};
+
+const START: u8 = 0x00;
+const MBR_END: u8 = 0x7C;
+const ENTITY: u8 = 0x69;
+const END: u8 = 0xFE;
+
+/// Whether `connection` holds the SpatiaLite metadata tables.
+pub fn is_spatialite(connection: &Connection) -> rusqlite::Result<bool> {
+    let count: i64 = connection.query_row(
+        "SELECT COUNT(*) FROM sqlite_master
+        WHERE type = 'table' AND name IN ('geometry_columns', 'spatial_ref_sys')",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count == 2)
+}
+
+/// The tables registered in `geometry_columns`.
+pub fn list_layers(connection: &Connection) -> rusqlite::Result<Vec<String>> {
+    let mut statement = connection.prepare("SELECT DISTINCT f_table_name FROM geometry_columns")?;
+    let rows = statement.query([])?;
+    rows.map(|row| row.get(0)).collect()
+}
+
+fn get_geometry_columns(
+    connection: &Connection,
+    layer: &str,
+) -> rusqlite::Result<Vec<(String, i32)>> {
+    let mut statement = connection.prepare(
+        "SELECT f_geometry_column, srid FROM geometry_columns
+        WHERE f_table_name = :layer COLLATE NOCASE",
+    )?;
+    let rows = statement.query(named_params! {":layer": layer})?;
+    rows.map(|row| Ok((row.get(0)?, row.get(1)?))).collect()
+}
+
+/// The GeoArrow extension metadata of an SRID, as recorded in
+/// `spatial_ref_sys`.
+fn get_extension_metadata(
+    connection: &Connection,
+    srid: i32,
+) -> rusqlite::Result<ExtensionMetadata> {
+    let authority: Option<(String, i32)> = connection
+        .query_row(
+            "SELECT auth_name, auth_srid FROM spatial_ref_sys WHERE srid = :srid",
+            named_params! {":srid": srid},
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let metadata = match authority {
+        Some((auth_name, auth_srid)) if srid > 0 => {
+            ExtensionMetadata::from_authority_code(&auth_name.to_uppercase(), auth_srid)
+        }
+        _ => ExtensionMetadata::default(),
+    };
+    Ok(metadata)
+}
+
+pub fn get_schema(connection: &Connection, layer: &str) -> rusqlite::Result<Schema> {
+    let geometry_columns = get_geometry_columns(connection, layer)?;
+    let extension_metadata = match geometry_columns.first() {
+        Some((_column, srid)) => get_extension_metadata(connection, *srid)?,
+        None => ExtensionMetadata::default(),
+    };
+    let names: Vec<String> = geometry_columns
+        .into_iter()
+        .map(|(column, _srid)| column)
+        .collect();
+    gpkg::get_table_schema(connection, layer, &names, &extension_metadata)
+}
+
+pub fn get_layer(connection: &Connection, layer_name: &str) -> anyhow::Result<RecordBatch> {
+    get_filtered_layer(connection, layer_name, None)
+}
+
+/// Read the rows of a layer matching the SQL expression `filter`.
+pub fn get_filtered_layer(
+    connection: &Connection,
+    layer_name: &str,
+    filter: Option<&str>,
+) -> anyhow::Result<RecordBatch> {
+    let schema = get_schema(connection, layer_name)?;
+    gpkg::check_filter(connection, layer_name, filter)?;
+    let fields = gpkg::get_fields_with(
+        connection,
+        &schema,
+        layer_name,
+        filter,
+        decode_spatialite_binary,
+    )?;
+    let record_batch = RecordBatch::try_new(Arc::new(schema), fields)?;
+    Ok(record_batch)
+}
+
+/// A SpatiaLite database, whose tables in `geometry_columns` are its layers.
+pub struct Spatialite {
+    connection: Connection,
+}
+
+impl Spatialite {
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Driver for Spatialite {
+    fn probe(path: &Path, header: &[u8]) -> bool {
+        driver::probe_format(path, header, |format| *format == Format::Sqlite)
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let connection = Connection::open(path)?;
+        anyhow::ensure!(
+            is_spatialite(&connection)?,
+            "{} is not a SpatiaLite database",
+            path.display()
+        );
+        Ok(Spatialite { connection })
+    }
+
+    fn list_layers(&self) -> anyhow::Result<Vec<String>> {
+        Ok(list_layers(&self.connection)?)
+    }
+
+    fn layer_schema(&self, layer_name: &str) -> anyhow::Result<SchemaRef> {
+        Ok(Arc::new(get_schema(&self.connection, layer_name)?))
+    }
+
+    fn read_layer(&self, layer_name: &str, options: &ReadOptions) -> anyhow::Result<RecordBatch> {
+        let record_batch =
+            get_filtered_layer(&self.connection, layer_name, options.filter.as_deref())?;
+        options.apply_filtered(record_batch)
+    }
+
+    /// Tables of a database file are read a FID range at a time.
+    fn read_layer_batches(
+        &self,
+        layer_name: &str,
+        options: &ReadOptions,
+        batch_size: usize,
+    ) -> anyhow::Result<RecordBatches> {
+        match self.connection.path().filter(|path| path.is_file()) {
+            Some(path) => parallel::read_batches(path, layer_name, options, batch_size),
+            None => Ok(driver::split_record_batch(
+                self.read_layer(layer_name, options)?,
+                batch_size,
+            )),
+        }
+    }
+}
+
+/// A geometry in the SpatiaLite BLOB format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpatialiteBinary {
+    pub srid: i32,
+    /// The `[min_x, min_y, max_x, max_y]` minimum bounding rectangle.
+    pub mbr: [f64; 4],
+    pub geometry: WkbGeometry,
+}
+
+impl SpatialiteBinary {
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        anyhow::ensure!(bytes.len() > 43, "SpatiaLite geometry is too short");
+        anyhow::ensure!(bytes[0] == START, "Missing SpatiaLite start marker");
+        anyhow::ensure!(bytes[38] == MBR_END, "Missing SpatiaLite MBR end marker");
+        anyhow::ensure!(
+            bytes[bytes.len() - 1] == END,
+            "Missing SpatiaLite end marker"
+        );
+        let endian = match bytes[1] {
+            0 => Endian::Big,
+            1 => Endian::Little,
+            byte => anyhow::bail!("Invalid SpatiaLite byte order {}", byte),
+        };
+
+        let mut reader = Cursor::new(&bytes[2..bytes.len() - 1]);
+        let srid: i32 = reader.read_type(endian)?;
+        let mbr: [f64; 4] = reader.read_type(endian)?;
+        reader.read_type::<u8>(endian)?;
+        let class_type: u32 = reader.read_type(endian)?;
+
+        // Re-encode the geometry as little-endian WKB, which differs only in
+        // its headers.
+        let mut wkb = Vec::new();
+        transcode_geometry(&mut reader, endian, class_type, &mut wkb)?;
+        let geometry = Cursor::new(wkb)
+            .read_ne()
+            .context("Failed to parse SpatiaLite geometry")?;
+
+        Ok(SpatialiteBinary {
+            srid,
+            mbr,
+            geometry,
+        })
+    }
+}
+
+pub(crate) fn decode_spatialite_binary(blob: &[u8]) -> anyhow::Result<WkbGeometry> {
+    Ok(SpatialiteBinary::from_bytes(blob)?.geometry)
+}
+
+/// Copy the body of a SpatiaLite geometry of `class_type` to `wkb`, replacing
+/// the entity markers of collection members with WKB headers.
+fn transcode_geometry<R: Read + binread::io::Seek>(
+    reader: &mut R,
+    endian: Endian,
+    class_type: u32,
+    wkb: &mut Vec<u8>,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        class_type < 1_000_000,
+        "Compressed SpatiaLite geometries are not supported"
+    );
+    let geometry_type = WkbGeometryType::from_code(class_type)
+        .context(format!("Unknown SpatiaLite class type {}", class_type))?;
+    let dimension = Dimension::from_code(class_type);
+    let ordinates = 2 + dimension.has_z() as u32 + dimension.has_m() as u32;
+
+    wkb.push(1);
+    wkb.extend_from_slice(&dimension.code(geometry_type).to_le_bytes());
+
+    let copy_u32 = |reader: &mut R, wkb: &mut Vec<u8>| -> anyhow::Result<u32> {
+        let value: u32 = reader.read_type(endian)?;
+        wkb.extend_from_slice(&value.to_le_bytes());
+        Ok(value)
+    };
+    let copy_coordinates = |reader: &mut R, wkb: &mut Vec<u8>, count: u32| -> anyhow::Result<()> {
+        for _ in 0..count * ordinates {
+            let value: f64 = reader.read_type(endian)?;
+            wkb.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(())
+    };
+
+    match geometry_type {
+        WkbGeometryType::Point => copy_coordinates(reader, wkb, 1)?,
+        WkbGeometryType::LineString => {
+            let count = copy_u32(reader, wkb)?;
+            copy_coordinates(reader, wkb, count)?;
+        }
+        WkbGeometryType::Polygon => {
+            for _ in 0..copy_u32(reader, wkb)? {
+                let count = copy_u32(reader, wkb)?;
+                copy_coordinates(reader, wkb, count)?;
+            }
+        }
+        WkbGeometryType::MultiPoint
+        | WkbGeometryType::MultiLineString
+        | WkbGeometryType::MultiPolygon
+        | WkbGeometryType::GeometryCollection => {
+            for _ in 0..copy_u32(reader, wkb)? {
+                let marker: u8 = reader.read_type(endian)?;
+                anyhow::ensure!(marker == ENTITY, "Missing SpatiaLite entity marker");
+                let class_type: u32 = reader.read_type(endian)?;
+                transcode_geometry(reader, endian, class_type, wkb)?;
+            }
+        }
+        geometry_type => anyhow::bail!("Unsupported SpatiaLite geometry {:?}", geometry_type),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{dataset::Dataset, geoarrow};
+
+    #[test]
+    fn test_list_layers() {
+        let connection = Connection::open("Data/spatialite.sqlite").unwrap();
+
+        assert!(is_spatialite(&connection).unwrap());
+        assert!(!is_spatialite(&Connection::open("Data/point.gpkg").unwrap()).unwrap());
+        assert_eq!(
+            vec!["point".to_string(), "polygons".to_string()],
+            list_layers(&connection).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_point_layer() {
+        let expected_layer = Dataset::open("Data/point.gpkg")
+            .unwrap()
+            .get_layer("point")
+            .unwrap();
+
+        let layer = Dataset::open("Data/spatialite.sqlite")
+            .unwrap()
+            .get_layer("point")
+            .unwrap();
+
+        assert_eq!(expected_layer, layer);
+    }
+
+    #[test]
+    fn test_read_multipolygon_layer() {
+        let connection = Connection::open("Data/spatialite.sqlite").unwrap();
+        let layer = get_layer(&connection, "polygons").unwrap();
+
+        let schema = layer.schema();
+        assert_eq!(Some(2), geoarrow::primary_geometry_column(&schema));
+        assert_eq!(
+            Some(("EPSG", 4326)),
+            geoarrow::get_extension_metadata(schema.field(2))
+                .unwrap()
+                .authority_code()
+        );
+        let geometry = geoarrow::get_geometry(layer.column(2).as_ref(), 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            "MULTIPOLYGON Z (((0 0 1, 0 1 1, 1 1 1, 1 0 1, 0 0 1)), ((2 2 2, 3 2 2, 2 3 2, 2 2 2)))",
+            geometry.to_wkt()
+        );
+        assert!(layer.column(2).is_null(1));
+    }
+}
@@ -0,0 +1,456 @@
+use std::{
+    any::Any,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use arrow::{
+    array::{as_primitive_array, ArrayRef, BooleanArray},
+    compute::cast,
+    datatypes::{DataType, Float64Type, SchemaRef},
+    record_batch::{RecordBatch, RecordBatchOptions},
+};
+use async_trait::async_trait;
+use datafusion::{
+    catalog::schema::{MemorySchemaProvider, SchemaProvider},
+    datasource::{TableProvider, TableType},
+    error::{DataFusionError, Result},
+    execution::context::{SessionContext, SessionState},
+    logical_expr::{
+        Expr, Operator, ReturnTypeFunction, ScalarUDF, Signature, TableProviderFilterPushDown,
+        Volatility,
+    },
+    physical_expr::functions::make_scalar_function,
+    physical_plan::{memory::MemoryExec, ExecutionPlan},
+    scalar::ScalarValue,
+};
+use rusqlite::{named_params, Connection, OpenFlags};
+
+use crate::{
+    geoarrow,
+    gpkg::{self, quote_identifier},
+};
+
+/// The name of the function registered by [`register_geopackage`]:
+/// `bbox_intersects(geometry, min_x, min_y, max_x, max_y)` is true when the
+/// envelope of `geometry` intersects the box.
+pub const BBOX_INTERSECTS: &str = "bbox_intersects";
+
+/// A GeoPackage layer queried through DataFusion. Projections, limits and
+/// filters on attribute columns are pushed down into the SQLite query, and
+/// `bbox_intersects` filters on the geometry column into its RTree.
+#[derive(Debug, Clone)]
+pub struct GeoPackageTable {
+    path: PathBuf,
+    layer_name: String,
+    schema: SchemaRef,
+    geometry_column: Option<String>,
+    rtree: Option<String>,
+}
+
+fn open_read_only(path: &Path) -> rusqlite::Result<Connection> {
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+}
+
+fn literal_to_sql(value: &ScalarValue) -> Option<String> {
+    let sql = match value {
+        value if value.is_null() => "NULL".to_string(),
+        ScalarValue::Boolean(Some(value)) => (*value as u8).to_string(),
+        ScalarValue::Int8(Some(value)) => value.to_string(),
+        ScalarValue::Int16(Some(value)) => value.to_string(),
+        ScalarValue::Int32(Some(value)) => value.to_string(),
+        ScalarValue::Int64(Some(value)) => value.to_string(),
+        ScalarValue::UInt8(Some(value)) => value.to_string(),
+        ScalarValue::UInt16(Some(value)) => value.to_string(),
+        ScalarValue::UInt32(Some(value)) => value.to_string(),
+        ScalarValue::UInt64(Some(value)) => value.to_string(),
+        ScalarValue::Float32(Some(value)) if value.is_finite() => format!("{:?}", value),
+        ScalarValue::Float64(Some(value)) if value.is_finite() => format!("{:?}", value),
+        ScalarValue::Utf8(Some(value)) | ScalarValue::LargeUtf8(Some(value)) => {
+            format!("'{}'", value.replace('\'', "''"))
+        }
+        _ => return None,
+    };
+    Some(sql)
+}
+
+fn literal_to_f64(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Literal(value) => match value {
+            ScalarValue::Float64(Some(value)) => Some(*value),
+            ScalarValue::Float32(Some(value)) => Some(*value as f64),
+            ScalarValue::Int64(Some(value)) => Some(*value as f64),
+            ScalarValue::Int32(Some(value)) => Some(*value as f64),
+            _ => None,
+        },
+        Expr::Negative(expr) => literal_to_f64(expr).map(|value| -value),
+        _ => None,
+    }
+}
+
+impl GeoPackageTable {
+    pub fn open(path: &Path, layer_name: &str) -> anyhow::Result<Self> {
+        let connection = open_read_only(path)?;
+        let schema = gpkg::get_schema(&connection, layer_name)
+            .context(format!("Failed to get {}", layer_name))?;
+        let geometry_column = geoarrow::primary_geometry_column(&schema)
+            .map(|index| schema.field(index).name().clone());
+        let rtree = match &geometry_column {
+            Some(geometry_column) => {
+                let rtree = format!("rtree_{}_{}", layer_name, geometry_column);
+                let count: i64 = connection.query_row(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE name = :rtree",
+                    named_params! {":rtree": rtree},
+                    |row| row.get(0),
+                )?;
+                (count > 0).then_some(rtree)
+            }
+            None => None,
+        };
+        Ok(GeoPackageTable {
+            path: path.to_path_buf(),
+            layer_name: layer_name.to_string(),
+            schema: Arc::new(schema),
+            geometry_column,
+            rtree,
+        })
+    }
+
+    /// The SQL equivalent of a filter, and whether it is exact, if it can be
+    /// pushed down.
+    fn filter_to_sql(&self, expr: &Expr) -> Option<(String, bool)> {
+        let sql = match expr {
+            Expr::Column(column) => {
+                let field = self.schema.field_with_name(&column.name).ok()?;
+                if geoarrow::is_geometry_field(field) {
+                    return None;
+                }
+                quote_identifier(&column.name)
+            }
+            Expr::Literal(value) => literal_to_sql(value)?,
+            Expr::BinaryExpr { left, op, right } => {
+                let op = match op {
+                    Operator::Eq => "=",
+                    Operator::NotEq => "<>",
+                    Operator::Lt => "<",
+                    Operator::LtEq => "<=",
+                    Operator::Gt => ">",
+                    Operator::GtEq => ">=",
+                    Operator::And => "AND",
+                    Operator::Or => "OR",
+                    _ => return None,
+                };
+                let (left, left_exact) = self.filter_to_sql(left)?;
+                let (right, right_exact) = self.filter_to_sql(right)?;
+                return Some((
+                    format!("({} {} {})", left, op, right),
+                    left_exact && right_exact,
+                ));
+            }
+            Expr::Not(expr) => {
+                let (sql, exact) = self.filter_to_sql(expr)?;
+                return Some((format!("(NOT {})", sql), exact));
+            }
+            Expr::IsNull(expr) => format!("({} IS NULL)", self.filter_to_sql(expr)?.0),
+            Expr::IsNotNull(expr) => format!("({} IS NOT NULL)", self.filter_to_sql(expr)?.0),
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => format!(
+                "({} {}BETWEEN {} AND {})",
+                self.filter_to_sql(expr)?.0,
+                if *negated { "NOT " } else { "" },
+                self.filter_to_sql(low)?.0,
+                self.filter_to_sql(high)?.0
+            ),
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let list = list
+                    .iter()
+                    .map(|expr| self.filter_to_sql(expr).map(|(sql, _exact)| sql))
+                    .collect::<Option<Vec<String>>>()?;
+                format!(
+                    "({} {}IN ({}))",
+                    self.filter_to_sql(expr)?.0,
+                    if *negated { "NOT " } else { "" },
+                    list.join(", ")
+                )
+            }
+            Expr::ScalarUDF { fun, args } if fun.name == BBOX_INTERSECTS => {
+                // The RTree stores envelopes rounded outwards to 32 bit
+                // floats, so the function still has to be evaluated.
+                let rtree = self.rtree.as_ref()?;
+                match args.first() {
+                    Some(Expr::Column(column))
+                        if Some(&column.name) == self.geometry_column.as_ref() => {}
+                    _ => return None,
+                }
+                let bbox = args[1..]
+                    .iter()
+                    .map(literal_to_f64)
+                    .collect::<Option<Vec<f64>>>()?;
+                let [min_x, min_y, max_x, max_y]: [f64; 4] = bbox.try_into().ok()?;
+                return Some((
+                    format!(
+                        "(rowid IN (SELECT id FROM {} WHERE minx <= {:?} AND maxx >= {:?} AND miny <= {:?} AND maxy >= {:?}))",
+                        quote_identifier(rtree),
+                        max_x,
+                        min_x,
+                        max_y,
+                        min_y
+                    ),
+                    false,
+                ));
+            }
+            _ => return None,
+        };
+        Some((sql, true))
+    }
+
+    /// Read the projected columns of the rows matching `filters`, up to
+    /// `limit`.
+    fn read(
+        &self,
+        projection: &Option<Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> anyhow::Result<RecordBatch> {
+        let connection = open_read_only(&self.path)?;
+        let layer = quote_identifier(&self.layer_name);
+        let filters: Vec<String> = filters
+            .iter()
+            .filter_map(|filter| self.filter_to_sql(filter))
+            .map(|(sql, _exact)| sql)
+            .collect();
+        let mut filter = match filters.is_empty() {
+            true => None,
+            false => Some(filters.join(" AND ")),
+        };
+        if let Some(limit) = limit {
+            filter = Some(format!(
+                "rowid IN (SELECT rowid FROM {} WHERE {} LIMIT {})",
+                layer,
+                filter.as_deref().unwrap_or("1"),
+                limit
+            ));
+        }
+        gpkg::check_filter(&connection, &self.layer_name, filter.as_deref())?;
+
+        let schema = match projection {
+            Some(indices) => Arc::new(self.schema.project(indices)?),
+            None => self.schema.clone(),
+        };
+        if schema.fields().is_empty() {
+            let count: usize = connection.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {} WHERE {}",
+                    layer,
+                    filter.as_deref().unwrap_or("1")
+                ),
+                [],
+                |row| row.get(0),
+            )?;
+            let mut options = RecordBatchOptions::default();
+            options.row_count = Some(count);
+            return Ok(RecordBatch::try_new_with_options(
+                schema,
+                Vec::new(),
+                &options,
+            )?);
+        }
+        let fields = gpkg::get_fields_with(
+            &connection,
+            &schema,
+            &self.layer_name,
+            filter.as_deref(),
+            gpkg::decode_geopackage_binary,
+        )?;
+        Ok(RecordBatch::try_new(schema, fields)?)
+    }
+}
+
+#[async_trait]
+impl TableProvider for GeoPackageTable {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    /// Read the matching rows whole, with `filters` and `limit` pushed down
+    /// to SQLite, into a single record batch served by a `MemoryExec`. The
+    /// result is held in memory rather than streamed.
+    async fn scan(
+        &self,
+        _ctx: &SessionState,
+        projection: &Option<Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let table = self.clone();
+        let projection = projection.clone();
+        let filters = filters.to_vec();
+        let record_batch =
+            tokio::task::spawn_blocking(move || table.read(&projection, &filters, limit))
+                .await
+                .map_err(|error| DataFusionError::External(error.into()))?
+                .map_err(|error| DataFusionError::External(error.into()))?;
+        let schema = record_batch.schema();
+        Ok(Arc::new(MemoryExec::try_new(
+            &[vec![record_batch]],
+            schema,
+            None,
+        )?))
+    }
+
+    fn supports_filter_pushdown(&self, filter: &Expr) -> Result<TableProviderFilterPushDown> {
+        let pushdown = match self.filter_to_sql(filter) {
+            Some((_sql, true)) => TableProviderFilterPushDown::Exact,
+            Some((_sql, false)) => TableProviderFilterPushDown::Inexact,
+            None => TableProviderFilterPushDown::Unsupported,
+        };
+        Ok(pushdown)
+    }
+}
+
+/// The `bbox_intersects` function, over any geometry column the
+/// [`geoarrow`] module can decode.
+pub fn bbox_intersects_udf() -> ScalarUDF {
+    let return_type: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(DataType::Boolean)));
+    let fun = make_scalar_function(|args: &[ArrayRef]| {
+        let bbox = args[1..]
+            .iter()
+            .map(|array| cast(array, &DataType::Float64))
+            .collect::<std::result::Result<Vec<ArrayRef>, _>>()?;
+        let bbox: Vec<_> = bbox
+            .iter()
+            .map(|array| as_primitive_array::<Float64Type>(array))
+            .collect();
+        let intersects = (0..args[0].len())
+            .map(|index| {
+                let geometry = geoarrow::get_geometry(args[0].as_ref(), index)
+                    .map_err(|error| DataFusionError::External(error.into()))?;
+                let envelope = geometry.and_then(|geometry| geometry.envelope());
+                let query = [0, 1, 2, 3].map(|column| bbox[column].value(index));
+                Ok(Some(envelope.is_some_and(|envelope| {
+                    geoarrow::intersects(&envelope, &query)
+                })))
+            })
+            .collect::<Result<BooleanArray>>()?;
+        Ok(Arc::new(intersects) as ArrayRef)
+    });
+    ScalarUDF::new(
+        BBOX_INTERSECTS,
+        &Signature::any(5, Volatility::Immutable),
+        &return_type,
+        &fun,
+    )
+}
+
+/// Register every feature layer of the GeoPackage at `path` as a table in the
+/// schema `schema_name`, so that they can be queried as
+/// `SELECT ... FROM schema_name.layer`, along with `bbox_intersects`.
+pub fn register_geopackage(
+    ctx: &mut SessionContext,
+    schema_name: &str,
+    path: &Path,
+) -> anyhow::Result<()> {
+    let connection = open_read_only(path)?;
+    let schema = Arc::new(MemorySchemaProvider::new());
+    let mut statement =
+        connection.prepare("SELECT table_name FROM gpkg_contents WHERE data_type = 'features'")?;
+    let layer_names = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    for layer_name in layer_names {
+        let table = GeoPackageTable::open(path, &layer_name)?;
+        schema.register_table(layer_name, Arc::new(table))?;
+    }
+    ctx.catalog("datafusion")
+        .context("Missing default catalog")?
+        .register_schema(schema_name, schema)?;
+    ctx.register_udf(bbox_intersects_udf());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow::array::{as_string_array, Int64Array};
+    use datafusion::prelude::{col, lit};
+
+    #[test]
+    fn test_filter_to_sql() {
+        let table = GeoPackageTable::open(Path::new("Data/point.gpkg"), "point").unwrap();
+
+        let filter = col("name").eq(lit("point_2")).and(col("fid").gt(lit(1i64)));
+        assert_eq!(
+            Some((
+                r#"(("name" = 'point_2') AND ("fid" > 1))"#.to_string(),
+                true
+            )),
+            table.filter_to_sql(&filter)
+        );
+        assert_eq!(None, table.filter_to_sql(&col("geom").is_null()));
+        assert_eq!(None, table.filter_to_sql(&col("name").like(lit("p%"))));
+    }
+
+    #[tokio::test]
+    async fn test_query_geopackage() {
+        let mut ctx = SessionContext::new();
+        register_geopackage(&mut ctx, "points", Path::new("Data/point.gpkg")).unwrap();
+
+        let record_batches = ctx
+            .sql("SELECT name FROM points.point WHERE name <> 'point_1'")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let names = as_string_array(record_batches[0].column(0));
+        assert_eq!(vec![Some("point_2")], names.iter().collect::<Vec<_>>());
+
+        let record_batches = ctx
+            .sql("SELECT fid FROM points.point WHERE bbox_intersects(geom, 0.5, 0.5, 2, 2)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let fids = record_batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(&[2], fids.values());
+
+        let record_batches = ctx
+            .sql("SELECT COUNT(*) FROM (SELECT * FROM points.point LIMIT 1)")
+            .await
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+        let count = record_batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(&[1], count.values());
+    }
+}
@@ -0,0 +1,64 @@
+//! Fixtures shared by the tests of the format modules.
+
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use arrow::record_batch::RecordBatch;
+
+use crate::dataset::Dataset;
+
+/// The `point` layer of `Data/point.gpkg`: two points in EPSG:27700 with a
+/// `fid` and a `name`.
+pub fn point_layer() -> RecordBatch {
+    Dataset::open("Data/point.gpkg")
+        .unwrap()
+        .get_layer("point")
+        .unwrap()
+}
+
+/// A path to `file_name` in a new temporary directory of its own, so that
+/// tests running in parallel never share files. The directory and everything
+/// written to it are removed when this is dropped.
+pub struct TempPath {
+    directory: PathBuf,
+    path: PathBuf,
+}
+
+impl TempPath {
+    pub fn new(file_name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let directory = std::env::temp_dir().join(format!(
+            "ogr2arrow-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+        TempPath {
+            path: directory.join(file_name),
+            directory,
+        }
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
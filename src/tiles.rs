@@ -0,0 +1,570 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use arrow::{
+    array::{ArrayRef, BinaryArray, Float64Array, Int64Array},
+    datatypes::{DataType, Field, Schema},
+    record_batch::RecordBatch,
+};
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Connection};
+
+use crate::gpkg;
+
+/// The extent and SRS of a tile pyramid, from `gpkg_tile_matrix_set`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMatrixSet {
+    pub srs_id: i32,
+    /// The WKT definition of `srs_id`, if the source records one, which new
+    /// GeoPackages need unless `srs_id` is EPSG:4326 or EPSG:3857.
+    pub srs_definition: Option<String>,
+    /// `[min_x, min_y, max_x, max_y]`, which the tiles of every zoom level
+    /// cover from the top left.
+    pub bounds: [f64; 4],
+}
+
+/// One zoom level of a tile pyramid, from `gpkg_tile_matrix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMatrix {
+    pub zoom_level: i64,
+    pub matrix_width: i64,
+    pub matrix_height: i64,
+    pub tile_width: i64,
+    pub tile_height: i64,
+    pub pixel_x_size: f64,
+    pub pixel_y_size: f64,
+}
+
+/// The inclusive range of tile columns and rows of a zoom level, with rows
+/// numbered from the top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRange {
+    pub zoom_level: i64,
+    pub min_column: i64,
+    pub max_column: i64,
+    pub min_row: i64,
+    pub max_row: i64,
+}
+
+impl TileMatrix {
+    /// The width and height of a tile in CRS units.
+    fn tile_size(&self) -> (f64, f64) {
+        (
+            self.tile_width as f64 * self.pixel_x_size,
+            self.tile_height as f64 * self.pixel_y_size,
+        )
+    }
+
+    /// The bounds of a tile of a pyramid covering `extent`.
+    pub fn tile_bounds(&self, extent: &[f64; 4], column: i64, row: i64) -> [f64; 4] {
+        let (width, height) = self.tile_size();
+        [
+            extent[0] + column as f64 * width,
+            extent[3] - (row + 1) as f64 * height,
+            extent[0] + (column + 1) as f64 * width,
+            extent[3] - row as f64 * height,
+        ]
+    }
+
+    /// The tiles of a pyramid covering `extent` that intersect `bbox`, or
+    /// `None` if it lies outside this matrix.
+    pub fn tiles_covering(&self, extent: &[f64; 4], bbox: &[f64; 4]) -> Option<TileRange> {
+        let (width, height) = self.tile_size();
+        let min_column = ((bbox[0] - extent[0]) / width).floor() as i64;
+        let max_column = ((bbox[2] - extent[0]) / width).floor() as i64;
+        let min_row = ((extent[3] - bbox[3]) / height).floor() as i64;
+        let max_row = ((extent[3] - bbox[1]) / height).floor() as i64;
+        if max_column < 0
+            || max_row < 0
+            || min_column >= self.matrix_width
+            || min_row >= self.matrix_height
+        {
+            return None;
+        }
+        Some(TileRange {
+            zoom_level: self.zoom_level,
+            min_column: min_column.max(0),
+            max_column: max_column.min(self.matrix_width - 1),
+            min_row: min_row.max(0),
+            max_row: max_row.min(self.matrix_height - 1),
+        })
+    }
+}
+
+/// A row of a tile table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tile {
+    pub zoom_level: i64,
+    pub tile_column: i64,
+    pub tile_row: i64,
+    pub tile_data: Vec<u8>,
+}
+
+/// A whole tile pyramid, as moved between tile containers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilePyramid {
+    pub matrix_set: TileMatrixSet,
+    /// The zoom levels, from the lowest.
+    pub matrices: Vec<TileMatrix>,
+    /// The tiles, with rows numbered from the top.
+    pub tiles: Vec<Tile>,
+}
+
+/// Which tiles of a pyramid to read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileQuery {
+    /// Only read tiles of this zoom level.
+    pub zoom_level: Option<i64>,
+    /// Only read tiles intersecting `[min_x, min_y, max_x, max_y]`.
+    pub bbox: Option<[f64; 4]>,
+}
+
+impl TileQuery {
+    /// The ranges of tiles to read from each matching zoom level.
+    pub fn tile_ranges(
+        &self,
+        matrix_set: &TileMatrixSet,
+        matrices: &[TileMatrix],
+    ) -> Vec<TileRange> {
+        let bbox = self.bbox.unwrap_or(matrix_set.bounds);
+        matrices
+            .iter()
+            .filter(|matrix| {
+                self.zoom_level
+                    .is_none_or(|zoom_level| zoom_level == matrix.zoom_level)
+            })
+            .filter_map(|matrix| matrix.tiles_covering(&matrix_set.bounds, &bbox))
+            .collect()
+    }
+}
+
+/// The schema of the tiles of a pyramid, with the bounds of each tile.
+pub fn tile_schema() -> Schema {
+    Schema::new(vec![
+        Field::new("zoom_level", DataType::Int64, false),
+        Field::new("tile_column", DataType::Int64, false),
+        Field::new("tile_row", DataType::Int64, false),
+        Field::new("tile_data", DataType::Binary, false),
+        Field::new("min_x", DataType::Float64, true),
+        Field::new("min_y", DataType::Float64, true),
+        Field::new("max_x", DataType::Float64, true),
+        Field::new("max_y", DataType::Float64, true),
+    ])
+}
+
+/// Collect tiles into a record batch of [`tile_schema`]. Tiles of a zoom
+/// level missing from `matrices` have null bounds.
+pub fn tiles_to_record_batch(
+    matrix_set: &TileMatrixSet,
+    matrices: &[TileMatrix],
+    tiles: &[Tile],
+) -> anyhow::Result<RecordBatch> {
+    let bounds: Vec<Option<[f64; 4]>> = tiles
+        .iter()
+        .map(|tile| {
+            matrices
+                .iter()
+                .find(|matrix| matrix.zoom_level == tile.zoom_level)
+                .map(|matrix| {
+                    matrix.tile_bounds(&matrix_set.bounds, tile.tile_column, tile.tile_row)
+                })
+        })
+        .collect();
+    let bound = |index: usize| -> ArrayRef {
+        Arc::new(Float64Array::from_iter(
+            bounds
+                .iter()
+                .map(|bounds| bounds.map(|bounds| bounds[index])),
+        ))
+    };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Int64Array::from_iter_values(
+            tiles.iter().map(|tile| tile.zoom_level),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            tiles.iter().map(|tile| tile.tile_column),
+        )),
+        Arc::new(Int64Array::from_iter_values(
+            tiles.iter().map(|tile| tile.tile_row),
+        )),
+        Arc::new(BinaryArray::from_iter_values(
+            tiles.iter().map(|tile| tile.tile_data.as_slice()),
+        )),
+        bound(0),
+        bound(1),
+        bound(2),
+        bound(3),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(tile_schema()), columns)?)
+}
+
+/// The SQL expression selecting the tiles in `ranges` that match `filter`.
+pub(crate) fn tiles_filter(ranges: &[TileRange], filter: Option<&str>) -> String {
+    if ranges.is_empty() {
+        return "0".to_string();
+    }
+    let ranges = ranges
+        .iter()
+        .map(|range| {
+            format!(
+                "(zoom_level = {} AND tile_column BETWEEN {} AND {} AND tile_row BETWEEN {} AND {})",
+                range.zoom_level, range.min_column, range.max_column, range.min_row, range.max_row
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    match filter {
+        Some(filter) => format!("({}) AND ({})", ranges, filter),
+        None => ranges,
+    }
+}
+
+/// Whether `table` is a tile pyramid according to `gpkg_contents`.
+pub fn is_tile_table(connection: &Connection, table: &str) -> rusqlite::Result<bool> {
+    let count: i64 = connection.query_row(
+        "SELECT COUNT(*) FROM gpkg_contents WHERE table_name = :table AND data_type = 'tiles'",
+        named_params! {":table": table},
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+pub fn get_tile_matrix_set(
+    connection: &Connection,
+    table: &str,
+) -> rusqlite::Result<TileMatrixSet> {
+    connection.query_row(
+        "SELECT t.srs_id, s.definition, t.min_x, t.min_y, t.max_x, t.max_y
+        FROM gpkg_tile_matrix_set t
+        LEFT JOIN gpkg_spatial_ref_sys s ON s.srs_id = t.srs_id
+        WHERE t.table_name = :table",
+        named_params! {":table": table},
+        |row| {
+            Ok(TileMatrixSet {
+                srs_id: row.get(0)?,
+                srs_definition: row
+                    .get::<_, Option<String>>(1)?
+                    .filter(|definition| !definition.eq_ignore_ascii_case("undefined")),
+                bounds: [row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?],
+            })
+        },
+    )
+}
+
+/// The zoom levels of `table`, from the lowest.
+pub fn get_tile_matrices(
+    connection: &Connection,
+    table: &str,
+) -> rusqlite::Result<Vec<TileMatrix>> {
+    let mut statement = connection.prepare(
+        "SELECT zoom_level, matrix_width, matrix_height, tile_width, tile_height,
+            pixel_x_size, pixel_y_size
+        FROM gpkg_tile_matrix WHERE table_name = :table ORDER BY zoom_level",
+    )?;
+    let rows = statement.query(named_params! {":table": table})?;
+    rows.map(|row| {
+        Ok(TileMatrix {
+            zoom_level: row.get(0)?,
+            matrix_width: row.get(1)?,
+            matrix_height: row.get(2)?,
+            tile_width: row.get(3)?,
+            tile_height: row.get(4)?,
+            pixel_x_size: row.get(5)?,
+            pixel_y_size: row.get(6)?,
+        })
+    })
+    .collect()
+}
+
+/// Read the tiles of a tile table matching the SQL expression `filter`.
+pub(crate) fn select_tiles(
+    connection: &Connection,
+    table: &str,
+    filter: &str,
+) -> anyhow::Result<Vec<Tile>> {
+    let sql = format!(
+        "SELECT zoom_level, tile_column, tile_row, tile_data FROM {} WHERE {}
+        ORDER BY zoom_level, tile_row, tile_column",
+        gpkg::quote_identifier(table),
+        filter
+    );
+    let mut statement = connection
+        .prepare(&sql)
+        .context(format!("Invalid filter {}", filter))?;
+    let rows = statement.query([])?;
+    Ok(rows
+        .map(|row| {
+            Ok(Tile {
+                zoom_level: row.get(0)?,
+                tile_column: row.get(1)?,
+                tile_row: row.get(2)?,
+                tile_data: row.get(3)?,
+            })
+        })
+        .collect()?)
+}
+
+/// Read the tiles of a GeoPackage tile table selected by `query` and
+/// matching the SQL expression `filter`.
+pub fn get_tiles(
+    connection: &Connection,
+    table: &str,
+    query: &TileQuery,
+    filter: Option<&str>,
+) -> anyhow::Result<RecordBatch> {
+    let matrix_set = get_tile_matrix_set(connection, table)?;
+    let matrices = get_tile_matrices(connection, table)?;
+    let sql_filter = tiles_filter(&query.tile_ranges(&matrix_set, &matrices), filter);
+    let tiles = select_tiles(connection, table, &sql_filter)?;
+    tiles_to_record_batch(&matrix_set, &matrices, &tiles)
+}
+
+/// Read a whole GeoPackage tile table.
+pub fn read_tile_pyramid(connection: &Connection, table: &str) -> anyhow::Result<TilePyramid> {
+    Ok(TilePyramid {
+        matrix_set: get_tile_matrix_set(connection, table)?,
+        matrices: get_tile_matrices(connection, table)?,
+        tiles: select_tiles(connection, table, "1")?,
+    })
+}
+
+/// Write a tile pyramid to a new tile table called `table`, creating the
+/// GeoPackage core and tile matrix tables if `connection` does not have them
+/// yet.
+pub fn write_tile_pyramid(
+    connection: &Connection,
+    table: &str,
+    pyramid: &TilePyramid,
+) -> anyhow::Result<()> {
+    let matrix_set = &pyramid.matrix_set;
+    let transaction = connection.unchecked_transaction()?;
+    gpkg::create_core_tables(&transaction)?;
+    gpkg::insert_spatial_ref_sys(
+        &transaction,
+        matrix_set.srs_id,
+        matrix_set.srs_definition.as_deref(),
+    )?;
+    transaction.execute_batch(&format!(
+        r#"CREATE TABLE IF NOT EXISTS gpkg_tile_matrix_set (
+            table_name TEXT NOT NULL PRIMARY KEY,
+            srs_id INTEGER NOT NULL,
+            min_x DOUBLE NOT NULL,
+            min_y DOUBLE NOT NULL,
+            max_x DOUBLE NOT NULL,
+            max_y DOUBLE NOT NULL,
+            CONSTRAINT fk_gtms_table_name FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name),
+            CONSTRAINT fk_gtms_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id)
+        );
+        CREATE TABLE IF NOT EXISTS gpkg_tile_matrix (
+            table_name TEXT NOT NULL,
+            zoom_level INTEGER NOT NULL,
+            matrix_width INTEGER NOT NULL,
+            matrix_height INTEGER NOT NULL,
+            tile_width INTEGER NOT NULL,
+            tile_height INTEGER NOT NULL,
+            pixel_x_size DOUBLE NOT NULL,
+            pixel_y_size DOUBLE NOT NULL,
+            CONSTRAINT pk_ttm PRIMARY KEY (table_name, zoom_level),
+            CONSTRAINT fk_tmm_table_name FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name)
+        );
+        CREATE TABLE {} (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            zoom_level INTEGER NOT NULL,
+            tile_column INTEGER NOT NULL,
+            tile_row INTEGER NOT NULL,
+            tile_data BLOB NOT NULL,
+            UNIQUE (zoom_level, tile_column, tile_row)
+        );"#,
+        gpkg::quote_identifier(table)
+    ))?;
+
+    let [min_x, min_y, max_x, max_y] = matrix_set.bounds;
+    let bounds = named_params! {
+        ":table": table,
+        ":min_x": min_x,
+        ":min_y": min_y,
+        ":max_x": max_x,
+        ":max_y": max_y,
+        ":srs_id": matrix_set.srs_id,
+    };
+    transaction.execute(
+        "INSERT INTO gpkg_contents (table_name, data_type, identifier, min_x, min_y, max_x, max_y, srs_id)
+        VALUES (:table, 'tiles', :table, :min_x, :min_y, :max_x, :max_y, :srs_id)",
+        bounds,
+    )?;
+    transaction.execute(
+        "INSERT INTO gpkg_tile_matrix_set VALUES (:table, :srs_id, :min_x, :min_y, :max_x, :max_y)",
+        bounds,
+    )?;
+    for matrix in &pyramid.matrices {
+        transaction.execute(
+            "INSERT INTO gpkg_tile_matrix VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            rusqlite::params![
+                table,
+                matrix.zoom_level,
+                matrix.matrix_width,
+                matrix.matrix_height,
+                matrix.tile_width,
+                matrix.tile_height,
+                matrix.pixel_x_size,
+                matrix.pixel_y_size,
+            ],
+        )?;
+    }
+    insert_tiles(&transaction, table, &pyramid.tiles)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Insert tiles into a tile table as they are.
+pub(crate) fn insert_tiles(
+    connection: &Connection,
+    table: &str,
+    tiles: &[Tile],
+) -> rusqlite::Result<()> {
+    let mut statement = connection.prepare(&format!(
+        "INSERT INTO {} (zoom_level, tile_column, tile_row, tile_data) VALUES (?, ?, ?, ?)",
+        gpkg::quote_identifier(table)
+    ))?;
+    for tile in tiles {
+        statement.execute(rusqlite::params![
+            tile.zoom_level,
+            tile.tile_column,
+            tile.tile_row,
+            tile.tile_data,
+        ])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow::{array::as_primitive_array, datatypes::Int64Type};
+
+    #[test]
+    fn test_tiles_covering() {
+        let extent = [0.0, 0.0, 1024.0, 1024.0];
+        let matrix = TileMatrix {
+            zoom_level: 1,
+            matrix_width: 2,
+            matrix_height: 2,
+            tile_width: 256,
+            tile_height: 256,
+            pixel_x_size: 2.0,
+            pixel_y_size: 2.0,
+        };
+
+        assert_eq!(
+            [512.0, 0.0, 1024.0, 512.0],
+            matrix.tile_bounds(&extent, 1, 1)
+        );
+        assert_eq!(
+            Some(TileRange {
+                zoom_level: 1,
+                min_column: 0,
+                max_column: 0,
+                min_row: 1,
+                max_row: 1,
+            }),
+            matrix.tiles_covering(&extent, &[10.0, 10.0, 20.0, 20.0])
+        );
+        assert_eq!(
+            Some(TileRange {
+                zoom_level: 1,
+                min_column: 0,
+                max_column: 1,
+                min_row: 0,
+                max_row: 1,
+            }),
+            matrix.tiles_covering(&extent, &[-100.0, -100.0, 2000.0, 2000.0])
+        );
+        assert_eq!(
+            None,
+            matrix.tiles_covering(&extent, &[2000.0, 0.0, 3000.0, 10.0])
+        );
+    }
+
+    #[test]
+    fn test_get_tiles() {
+        let connection = Connection::open("Data/tiles.gpkg").unwrap();
+        assert!(is_tile_table(&connection, "basemap").unwrap());
+        assert_eq!(2, get_tile_matrices(&connection, "basemap").unwrap().len());
+
+        let tiles = get_tiles(&connection, "basemap", &TileQuery::default(), None).unwrap();
+        assert_eq!(5, tiles.num_rows());
+
+        let query = TileQuery {
+            zoom_level: Some(1),
+            bbox: Some([600.0, 600.0, 700.0, 700.0]),
+        };
+        let tiles = get_tiles(&connection, "basemap", &query, None).unwrap();
+        assert_eq!(1, tiles.num_rows());
+        let tile_column = as_primitive_array::<Int64Type>(tiles.column(1));
+        let min_x = tiles
+            .column(4)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(1, tile_column.value(0));
+        assert_eq!(512.0, min_x.value(0));
+        let tile_data = tiles
+            .column(3)
+            .as_any()
+            .downcast_ref::<BinaryArray>()
+            .unwrap();
+        assert_eq!(b"tile 1/1/0", tile_data.value(0));
+
+        let tiles = get_tiles(
+            &connection,
+            "basemap",
+            &TileQuery::default(),
+            Some("zoom_level = 0"),
+        )
+        .unwrap();
+        assert_eq!(1, tiles.num_rows());
+    }
+
+    #[test]
+    fn test_select_tiles_quoted_table() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                r#"CREATE TABLE "base ""map""" (zoom_level, tile_column, tile_row, tile_data);
+                INSERT INTO "base ""map""" VALUES (0, 0, 0, X'00');"#,
+            )
+            .unwrap();
+
+        let tiles = select_tiles(&connection, r#"base "map""#, "1").unwrap();
+
+        assert_eq!(1, tiles.len());
+    }
+
+    #[test]
+    fn test_write_tile_pyramid_quoted_table() {
+        let source = Connection::open("Data/tiles.mbtiles").unwrap();
+        let pyramid = crate::mbtiles::read_tile_pyramid(&source).unwrap();
+        let connection = Connection::open_in_memory().unwrap();
+        let table = r#"base "map""#;
+
+        write_tile_pyramid(&connection, table, &pyramid).unwrap();
+
+        assert_eq!(pyramid, read_tile_pyramid(&connection, table).unwrap());
+    }
+
+    #[test]
+    fn test_write_tile_pyramid_srs_definition() {
+        let source = Connection::open("Data/tiles.gpkg").unwrap();
+        let mut pyramid = read_tile_pyramid(&source, "basemap").unwrap();
+        assert_eq!(None, pyramid.matrix_set.srs_definition);
+        let connection = Connection::open_in_memory().unwrap();
+        assert!(write_tile_pyramid(&connection, "basemap", &pyramid).is_err());
+
+        let points = Connection::open("Data/point.gpkg").unwrap();
+        pyramid.matrix_set.srs_definition =
+            gpkg::get_crs_definition(&points, "EPSG", 27700).unwrap();
+        write_tile_pyramid(&connection, "basemap", &pyramid).unwrap();
+
+        assert_eq!(pyramid, read_tile_pyramid(&connection, "basemap").unwrap());
+    }
+}
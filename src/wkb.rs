@@ -1,15 +1,18 @@
-use binread::BinRead;
+use binread::{derive_binread, BinRead};
 use std::convert::TryFrom;
 use std::convert::TryInto;
+use std::fmt;
 
-#[derive(BinRead, Debug, PartialEq, Eq, PartialOrd, Ord)]
+use crate::wkt;
+
+#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[br(repr = u8)]
 pub enum WkbByteOrder {
     Xdr = 0,
     Ndr = 1,
 }
 
-#[derive(BinRead, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[br(repr = u32)]
 pub enum WkbGeometryType {
     Point = 1,
@@ -24,48 +27,325 @@ pub enum WkbGeometryType {
     Tin = 16,
 }
 
-#[derive(Debug, PartialEq, BinRead)]
-#[br(little)]
+impl WkbGeometryType {
+    /// The geometry type of an ISO (`1001`) or extended (`0x80000001`) WKB
+    /// type code, ignoring its dimension.
+    pub fn from_code(code: u32) -> Option<Self> {
+        let geometry_type = match (code & 0x0fff_ffff) % 1000 {
+            1 => WkbGeometryType::Point,
+            2 => WkbGeometryType::LineString,
+            3 => WkbGeometryType::Polygon,
+            4 => WkbGeometryType::MultiPoint,
+            5 => WkbGeometryType::MultiLineString,
+            6 => WkbGeometryType::MultiPolygon,
+            7 => WkbGeometryType::GeometryCollection,
+            15 => WkbGeometryType::PolyhedralSurface,
+            16 => WkbGeometryType::Tin,
+            17 => WkbGeometryType::Triangle,
+            _ => return None,
+        };
+        Some(geometry_type)
+    }
+}
+
+/// The coordinate dimension of a geometry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Dimension {
+    #[default]
+    Xy,
+    Xyz,
+    Xym,
+    Xyzm,
+}
+
+impl Dimension {
+    /// The dimension of an ISO (`1001`) or extended (`0x80000001`) WKB type
+    /// code.
+    pub fn from_code(code: u32) -> Self {
+        let has_z = code & 0x8000_0000 != 0 || matches!((code & 0x0fff_ffff) / 1000, 1 | 3);
+        let has_m = code & 0x4000_0000 != 0 || matches!((code & 0x0fff_ffff) / 1000, 2 | 3);
+        Dimension::new(has_z, has_m)
+    }
+
+    pub fn new(has_z: bool, has_m: bool) -> Self {
+        match (has_z, has_m) {
+            (false, false) => Dimension::Xy,
+            (true, false) => Dimension::Xyz,
+            (false, true) => Dimension::Xym,
+            (true, true) => Dimension::Xyzm,
+        }
+    }
+
+    pub fn has_z(self) -> bool {
+        matches!(self, Dimension::Xyz | Dimension::Xyzm)
+    }
+
+    pub fn has_m(self) -> bool {
+        matches!(self, Dimension::Xym | Dimension::Xyzm)
+    }
+
+    /// The ISO WKB type code of `geometry_type` in this dimension.
+    pub fn code(self, geometry_type: WkbGeometryType) -> u32 {
+        let offset = match self {
+            Dimension::Xy => 0,
+            Dimension::Xyz => 1000,
+            Dimension::Xym => 2000,
+            Dimension::Xyzm => 3000,
+        };
+        geometry_type as u32 + offset
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, BinRead)]
+#[br(little, import(dimension: Dimension))]
 pub struct Coordinate {
     pub x: f64,
     pub y: f64,
+    #[br(if(dimension.has_z()))]
+    pub z: Option<f64>,
+    #[br(if(dimension.has_m()))]
+    pub m: Option<f64>,
 }
 
-#[derive(Debug, PartialEq, BinRead)]
-#[br(little)]
+impl Coordinate {
+    /// A two dimensional coordinate.
+    pub fn new(x: f64, y: f64) -> Self {
+        Coordinate {
+            x,
+            y,
+            z: None,
+            m: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, BinRead)]
+#[br(little, import(dimension: Dimension))]
 pub struct LinearRing {
     num_coordinates: u32,
-    #[br(count = num_coordinates)]
+    #[br(count = num_coordinates, args(dimension))]
     coordinates: Vec<Coordinate>,
 }
 
+impl LinearRing {
+    pub fn new(coordinates: Vec<Coordinate>) -> Self {
+        LinearRing {
+            num_coordinates: coordinates.len() as u32,
+            coordinates,
+        }
+    }
+
+    pub fn coordinates(&self) -> &[Coordinate] {
+        &self.coordinates
+    }
+}
+
+/// Shared behaviour of the building blocks of a WKB geometry, used to write
+/// geometries back out and to compute their envelopes.
+pub trait WkbComponent {
+    /// Append the little-endian WKB encoding of `self` to `buffer`.
+    fn write_wkb(&self, buffer: &mut Vec<u8>);
+    /// Call `visitor` with every coordinate contained in `self`.
+    fn visit_coordinates(&self, visitor: &mut dyn FnMut(&Coordinate));
+    /// The coordinate dimension of `self`.
+    fn dimension(&self) -> Dimension;
+}
+
+impl WkbComponent for Coordinate {
+    fn write_wkb(&self, buffer: &mut Vec<u8>) {
+        [Some(self.x), Some(self.y), self.z, self.m]
+            .into_iter()
+            .flatten()
+            .for_each(|value| buffer.extend_from_slice(&value.to_le_bytes()));
+    }
+
+    fn visit_coordinates(&self, visitor: &mut dyn FnMut(&Coordinate)) {
+        visitor(self)
+    }
+
+    fn dimension(&self) -> Dimension {
+        Dimension::new(self.z.is_some(), self.m.is_some())
+    }
+}
+
+impl WkbComponent for LinearRing {
+    fn write_wkb(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&(self.coordinates.len() as u32).to_le_bytes());
+        self.coordinates
+            .iter()
+            .for_each(|coordinate| coordinate.write_wkb(buffer));
+    }
+
+    fn visit_coordinates(&self, visitor: &mut dyn FnMut(&Coordinate)) {
+        self.coordinates.iter().for_each(visitor)
+    }
+
+    fn dimension(&self) -> Dimension {
+        self.coordinates
+            .first()
+            .map(Coordinate::dimension)
+            .unwrap_or_default()
+    }
+}
+
+/// The binread arguments a geometry passes to its children: coordinates need
+/// the parent's dimension, while nested geometries read their own.
+trait WkbChild: BinRead {
+    fn args(dimension: Dimension) -> Self::Args;
+}
+
+impl WkbChild for Coordinate {
+    fn args(dimension: Dimension) -> Self::Args {
+        (dimension,)
+    }
+}
+
+impl WkbChild for LinearRing {
+    fn args(dimension: Dimension) -> Self::Args {
+        (dimension,)
+    }
+}
+
 macro_rules! derive_wkb_struct {
     ($name:ident, $geometry_type:ident, $count_field_name:ident, $field_name:ident, $child_geometry_type:ty) => {
-        #[derive(Debug, PartialEq, BinRead)]
+        // The type code is only read to check the type and find the
+        // dimension, which is what geometries are compared and written by.
+        #[derive_binread]
+        #[derive(Debug, Clone, PartialEq)]
         #[br(little)]
         pub struct $name {
             pub byte_order: WkbByteOrder,
-            #[br(is_big = (byte_order == WkbByteOrder::Xdr))]
-            #[br(assert(wkb_type == WkbGeometryType::$geometry_type))]
+            #[br(temp, is_big = (byte_order == WkbByteOrder::Xdr))]
+            #[br(assert(WkbGeometryType::from_code(code) == Some(WkbGeometryType::$geometry_type)))]
+            code: u32,
+            #[br(calc = WkbGeometryType::$geometry_type)]
             pub wkb_type: WkbGeometryType,
+            #[br(calc = Dimension::from_code(code))]
+            pub dimension: Dimension,
             #[br(is_big = (byte_order == WkbByteOrder::Xdr))]
             pub $count_field_name: u32,
             #[br(is_big = (byte_order == WkbByteOrder::Xdr))]
             #[br(count = $count_field_name)]
+            #[br(args_tuple = <$child_geometry_type as WkbChild>::args(dimension))]
             pub $field_name: Vec<$child_geometry_type>,
         }
+
+        impl WkbChild for $name {
+            fn args(_dimension: Dimension) -> Self::Args {}
+        }
+
+        impl $name {
+            /// Build a little-endian geometry taking its dimension from the
+            /// first child.
+            pub fn new($field_name: Vec<$child_geometry_type>) -> Self {
+                let dimension = $field_name
+                    .first()
+                    .map(WkbComponent::dimension)
+                    .unwrap_or_default();
+                $name {
+                    byte_order: WkbByteOrder::Ndr,
+                    wkb_type: WkbGeometryType::$geometry_type,
+                    dimension,
+                    $count_field_name: $field_name.len() as u32,
+                    $field_name,
+                }
+            }
+
+            /// Set the dimension, for geometries such as `POINT Z EMPTY` that
+            /// have no coordinates to take it from.
+            pub fn with_dimension(self, dimension: Dimension) -> Self {
+                $name { dimension, ..self }
+            }
+        }
+
+        impl WkbComponent for $name {
+            fn write_wkb(&self, buffer: &mut Vec<u8>) {
+                buffer.push(WkbByteOrder::Ndr as u8);
+                buffer.extend_from_slice(
+                    &self
+                        .dimension
+                        .code(WkbGeometryType::$geometry_type)
+                        .to_le_bytes(),
+                );
+                buffer.extend_from_slice(&(self.$field_name.len() as u32).to_le_bytes());
+                self.$field_name
+                    .iter()
+                    .for_each(|child| child.write_wkb(buffer));
+            }
+
+            fn visit_coordinates(&self, visitor: &mut dyn FnMut(&Coordinate)) {
+                self.$field_name
+                    .iter()
+                    .for_each(|child| child.visit_coordinates(visitor));
+            }
+
+            fn dimension(&self) -> Dimension {
+                self.dimension
+            }
+        }
     };
     ($name:ident, $geometry_type:ident, $field_name:ident, $field_geometry_type:ty) => {
-        #[derive(Debug, PartialEq, BinRead)]
+        #[derive_binread]
+        #[derive(Debug, Clone, PartialEq)]
         #[br(little)]
         pub struct $name {
             pub byte_order: WkbByteOrder,
-            #[br(is_big = (byte_order == WkbByteOrder::Xdr))]
-            #[br(assert(wkb_type == WkbGeometryType::$geometry_type))]
+            #[br(temp, is_big = (byte_order == WkbByteOrder::Xdr))]
+            #[br(assert(WkbGeometryType::from_code(code) == Some(WkbGeometryType::$geometry_type)))]
+            code: u32,
+            #[br(calc = WkbGeometryType::$geometry_type)]
             pub wkb_type: WkbGeometryType,
+            #[br(calc = Dimension::from_code(code))]
+            pub dimension: Dimension,
             #[br(is_big = (byte_order == WkbByteOrder::Xdr))]
+            #[br(args_tuple = <$field_geometry_type as WkbChild>::args(dimension))]
             pub $field_name: $field_geometry_type,
         }
+
+        impl WkbChild for $name {
+            fn args(_dimension: Dimension) -> Self::Args {}
+        }
+
+        impl $name {
+            /// Build a little-endian geometry taking its dimension from
+            /// `$field_name`.
+            pub fn new($field_name: $field_geometry_type) -> Self {
+                let dimension = $field_name.dimension();
+                $name {
+                    byte_order: WkbByteOrder::Ndr,
+                    wkb_type: WkbGeometryType::$geometry_type,
+                    dimension,
+                    $field_name,
+                }
+            }
+
+            /// Set the dimension, for geometries such as `POINT Z EMPTY` that
+            /// have no coordinates to take it from.
+            pub fn with_dimension(self, dimension: Dimension) -> Self {
+                $name { dimension, ..self }
+            }
+        }
+
+        impl WkbComponent for $name {
+            fn write_wkb(&self, buffer: &mut Vec<u8>) {
+                buffer.push(WkbByteOrder::Ndr as u8);
+                buffer.extend_from_slice(
+                    &self
+                        .dimension
+                        .code(WkbGeometryType::$geometry_type)
+                        .to_le_bytes(),
+                );
+                self.$field_name.write_wkb(buffer);
+            }
+
+            fn visit_coordinates(&self, visitor: &mut dyn FnMut(&Coordinate)) {
+                self.$field_name.visit_coordinates(visitor);
+            }
+
+            fn dimension(&self) -> Dimension {
+                self.dimension
+            }
+        }
     };
 }
 
@@ -104,7 +384,7 @@ derive_wkb_struct!(
     WkbGeometry
 );
 
-#[derive(BinRead, Debug, PartialEq)]
+#[derive(BinRead, Debug, Clone, PartialEq)]
 #[br(little)]
 pub enum WkbGeometry {
     Point(WkbPoint),
@@ -119,6 +399,97 @@ pub enum WkbGeometry {
     Tin(WkbTin),
 }
 
+impl WkbGeometry {
+    fn as_component(&self) -> &dyn WkbComponent {
+        match self {
+            WkbGeometry::Point(geometry) => geometry,
+            WkbGeometry::LineString(geometry) => geometry,
+            WkbGeometry::Polygon(geometry) => geometry,
+            WkbGeometry::Triangle(geometry) => geometry,
+            WkbGeometry::MultiPoint(geometry) => geometry,
+            WkbGeometry::MultiLineString(geometry) => geometry,
+            WkbGeometry::MultiPolygon(geometry) => geometry,
+            WkbGeometry::GeometryCollection(geometry) => geometry,
+            WkbGeometry::PolyhedralSurface(geometry) => geometry,
+            WkbGeometry::Tin(geometry) => geometry,
+        }
+    }
+
+    pub fn geometry_type(&self) -> WkbGeometryType {
+        match self {
+            WkbGeometry::Point(geometry) => geometry.wkb_type,
+            WkbGeometry::LineString(geometry) => geometry.wkb_type,
+            WkbGeometry::Polygon(geometry) => geometry.wkb_type,
+            WkbGeometry::Triangle(geometry) => geometry.wkb_type,
+            WkbGeometry::MultiPoint(geometry) => geometry.wkb_type,
+            WkbGeometry::MultiLineString(geometry) => geometry.wkb_type,
+            WkbGeometry::MultiPolygon(geometry) => geometry.wkb_type,
+            WkbGeometry::GeometryCollection(geometry) => geometry.wkb_type,
+            WkbGeometry::PolyhedralSurface(geometry) => geometry.wkb_type,
+            WkbGeometry::Tin(geometry) => geometry.wkb_type,
+        }
+    }
+
+    /// Parse a Well-Known Text geometry, such as `POINT Z (1 2 3)`.
+    pub fn from_wkt(wkt: &str) -> anyhow::Result<Self> {
+        wkt::parse_wkt(wkt)
+    }
+
+    /// Encode the geometry as Well-Known Text.
+    pub fn to_wkt(&self) -> String {
+        wkt::write_wkt(self, None)
+    }
+
+    /// Encode the geometry as little-endian WKB.
+    pub fn to_wkb(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        self.write_wkb(&mut buffer);
+        buffer
+    }
+
+    /// The `[min_x, min_y, max_x, max_y]` bounds of the geometry, or `None`
+    /// if it is empty. NaN coordinates, used for empty points, are ignored.
+    pub fn envelope(&self) -> Option<[f64; 4]> {
+        let mut envelope: Option<[f64; 4]> = None;
+        self.visit_coordinates(&mut |coordinate| {
+            if coordinate.x.is_nan() || coordinate.y.is_nan() {
+                return;
+            }
+            let bounds =
+                envelope.get_or_insert([coordinate.x, coordinate.y, coordinate.x, coordinate.y]);
+            bounds[0] = bounds[0].min(coordinate.x);
+            bounds[1] = bounds[1].min(coordinate.y);
+            bounds[2] = bounds[2].max(coordinate.x);
+            bounds[3] = bounds[3].max(coordinate.y);
+        });
+        envelope
+    }
+}
+
+impl fmt::Display for WkbGeometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_wkt())
+    }
+}
+
+impl WkbChild for WkbGeometry {
+    fn args(_dimension: Dimension) -> Self::Args {}
+}
+
+impl WkbComponent for WkbGeometry {
+    fn write_wkb(&self, buffer: &mut Vec<u8>) {
+        self.as_component().write_wkb(buffer)
+    }
+
+    fn visit_coordinates(&self, visitor: &mut dyn FnMut(&Coordinate)) {
+        self.as_component().visit_coordinates(visitor)
+    }
+
+    fn dimension(&self) -> Dimension {
+        self.as_component().dimension()
+    }
+}
+
 impl TryInto<[f64; 2]> for Coordinate {
     type Error = ();
 
@@ -240,14 +611,7 @@ impl TryFrom<[f64; 2]> for WkbPoint {
     type Error = ();
 
     fn try_from(value: [f64; 2]) -> Result<Self, Self::Error> {
-        Ok(WkbPoint {
-            byte_order: WkbByteOrder::Ndr,
-            wkb_type: WkbGeometryType::Point,
-            point: Coordinate {
-                x: value[0],
-                y: value[1],
-            },
-        })
+        Ok(WkbPoint::new(Coordinate::new(value[0], value[1])))
     }
 }
 
@@ -255,32 +619,27 @@ impl TryFrom<Vec<Vec<Vec<[f64; 2]>>>> for WkbMultiPolygon {
     type Error = ();
 
     fn try_from(value: Vec<Vec<Vec<[f64; 2]>>>) -> Result<Self, Self::Error> {
-        Ok(WkbMultiPolygon {
-            byte_order: WkbByteOrder::Ndr,
-            wkb_type: WkbGeometryType::MultiPolygon,
-            num_polygons: value.len() as u32,
-            polygons: value
+        Ok(WkbMultiPolygon::new(
+            value
                 .iter()
-                .map(|polygon| WkbPolygon {
-                    byte_order: WkbByteOrder::Ndr,
-                    wkb_type: WkbGeometryType::Polygon,
-                    num_rings: polygon.len() as u32,
-                    rings: polygon
-                        .iter()
-                        .map(|ring| LinearRing {
-                            num_coordinates: ring.len() as u32,
-                            coordinates: ring
-                                .iter()
-                                .map(|coordinate| Coordinate {
-                                    x: coordinate[0],
-                                    y: coordinate[1],
-                                })
-                                .collect(),
-                        })
-                        .collect(),
+                .map(|polygon| {
+                    WkbPolygon::new(
+                        polygon
+                            .iter()
+                            .map(|ring| {
+                                LinearRing::new(
+                                    ring.iter()
+                                        .map(|coordinate| {
+                                            Coordinate::new(coordinate[0], coordinate[1])
+                                        })
+                                        .collect(),
+                                )
+                            })
+                            .collect(),
+                    )
                 })
                 .collect(),
-        })
+        ))
     }
 }
 
@@ -292,14 +651,7 @@ mod test {
 
     #[test]
     fn read_wkb_point() {
-        let expected_geometry = WkbGeometry::Point(WkbPoint {
-            byte_order: WkbByteOrder::Ndr,
-            wkb_type: WkbGeometryType::Point,
-            point: Coordinate {
-                x: 0.0f64,
-                y: 0.0f64,
-            },
-        });
+        let expected_geometry = WkbGeometry::Point(WkbPoint::new(Coordinate::new(0.0f64, 0.0f64)));
 
         let mut reader = Cursor::new(
             b"\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00",
@@ -312,21 +664,10 @@ mod test {
 
     #[test]
     fn read_wkb_linestring() {
-        let expected_geometry = WkbGeometry::LineString(WkbLineString {
-            byte_order: WkbByteOrder::Ndr,
-            wkb_type: WkbGeometryType::LineString,
-            num_points: 2u32,
-            points: vec![
-                Coordinate {
-                    x: 0.0f64,
-                    y: 0.0f64,
-                },
-                Coordinate {
-                    x: 1.0f64,
-                    y: 1.0f64,
-                },
-            ],
-        });
+        let expected_geometry = WkbGeometry::LineString(WkbLineString::new(vec![
+            Coordinate::new(0.0f64, 0.0f64),
+            Coordinate::new(1.0f64, 1.0f64),
+        ]));
 
         let mut reader = Cursor::new(
             b"\x01\x02\x00\x00\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xf0?\x00\x00\x00\x00\x00\x00\xf0?",
@@ -336,4 +677,63 @@ mod test {
 
         assert_eq!(expected_geometry, recieved_geometry);
     }
+
+    #[test]
+    fn write_wkb_linestring() {
+        let expected_wkb = b"\x01\x02\x00\x00\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xf0?\x00\x00\x00\x00\x00\x00\xf0?";
+
+        let geometry: WkbGeometry = Cursor::new(expected_wkb).read_ne().unwrap();
+
+        assert_eq!(expected_wkb.to_vec(), geometry.to_wkb());
+    }
+
+    #[test]
+    fn read_wkb_point_zm() {
+        let mut wkb = vec![1u8];
+        wkb.extend_from_slice(&3001u32.to_le_bytes());
+        [1.0f64, 2.0, 3.0, 4.0]
+            .iter()
+            .for_each(|value| wkb.extend_from_slice(&value.to_le_bytes()));
+
+        let geometry: WkbGeometry = Cursor::new(&wkb).read_ne().unwrap();
+
+        let expected_point = Coordinate {
+            z: Some(3.0),
+            m: Some(4.0),
+            ..Coordinate::new(1.0, 2.0)
+        };
+        assert_eq!(WkbGeometry::Point(WkbPoint::new(expected_point)), geometry);
+        assert_eq!(Dimension::Xyzm, geometry.dimension());
+        assert_eq!(wkb, geometry.to_wkb());
+    }
+
+    #[test]
+    fn read_ewkb_point_z() {
+        let mut wkb = vec![1u8];
+        wkb.extend_from_slice(&0x8000_0001u32.to_le_bytes());
+        [1.0f64, 2.0, 3.0]
+            .iter()
+            .for_each(|value| wkb.extend_from_slice(&value.to_le_bytes()));
+
+        let geometry: WkbGeometry = Cursor::new(&wkb).read_ne().unwrap();
+
+        let expected_point = Coordinate {
+            z: Some(3.0),
+            ..Coordinate::new(1.0, 2.0)
+        };
+        assert_eq!(WkbGeometry::Point(WkbPoint::new(expected_point)), geometry);
+    }
+
+    #[test]
+    fn wkb_multipolygon_envelope() {
+        let geometry = WkbGeometry::MultiPolygon(
+            WkbMultiPolygon::try_from(vec![
+                vec![vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [0.0, 0.0]]],
+                vec![vec![[-1.0, 3.0], [0.0, 4.0], [-1.0, 4.0], [-1.0, 3.0]]],
+            ])
+            .unwrap(),
+        );
+
+        assert_eq!(Some([-1.0, 0.0, 2.0, 4.0]), geometry.envelope());
+    }
 }
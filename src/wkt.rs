@@ -0,0 +1,458 @@
+use std::fmt::Write;
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag_no_case,
+    character::complete::{char, multispace0},
+    combinator::{all_consuming, map, map_opt, opt, value},
+    multi::{many_m_n, separated_list1},
+    number::complete::double,
+    sequence::{delimited, preceded, terminated},
+    IResult,
+};
+
+use crate::wkb::{
+    Coordinate, Dimension, LinearRing, WkbComponent, WkbGeometry, WkbGeometryCollection,
+    WkbGeometryType, WkbLineString, WkbMultiLineString, WkbMultiPoint, WkbMultiPolygon, WkbPoint,
+    WkbPolygon, WkbPolyhedralSurface, WkbTin, WkbTriangle,
+};
+
+/// The dimension named after a geometry's tag, as in `POINT Z`. Untagged
+/// coordinates take their dimension from the number of values.
+type Tag = Option<Dimension>;
+
+/// Surround `parser` with optional whitespace.
+fn ws<'a, O>(
+    parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(multispace0, parser, multispace0)
+}
+
+/// A parenthesised, comma separated list of `parser`.
+fn list<'a, O>(
+    parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>> {
+    delimited(
+        ws(char('(')),
+        separated_list1(ws(char(',')), parser),
+        ws(char(')')),
+    )
+}
+
+/// A parenthesised list of `parser`, or `EMPTY`.
+fn list_or_empty<'a, O>(
+    parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>> {
+    alt((map(ws(tag_no_case("EMPTY")), |_| Vec::new()), list(parser)))
+}
+
+fn dimension_tag(input: &str) -> IResult<&str, Tag> {
+    opt(alt((
+        value(Dimension::Xyzm, ws(tag_no_case("ZM"))),
+        value(Dimension::Xyz, ws(tag_no_case("Z"))),
+        value(Dimension::Xym, ws(tag_no_case("M"))),
+    )))(input)
+}
+
+/// Give `geometry` the tagged dimension, so that empty geometries keep it.
+fn apply_tag<G>(geometry: G, tag: Tag, with_dimension: impl FnOnce(G, Dimension) -> G) -> G {
+    match tag {
+        Some(dimension) => with_dimension(geometry, dimension),
+        None => geometry,
+    }
+}
+
+fn coordinate<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, Coordinate> {
+    map_opt(many_m_n(2, 4, ws(double)), move |values: Vec<f64>| {
+        let dimension = match (tag, values.len()) {
+            (None, 2) => Dimension::Xy,
+            (None, 3) => Dimension::Xyz,
+            (None, _) => Dimension::Xyzm,
+            (Some(dimension), length)
+                if length == 2 + dimension.has_z() as usize + dimension.has_m() as usize =>
+            {
+                dimension
+            }
+            (Some(_), _) => return None,
+        };
+        let mut ordinates = values[2..].iter().copied();
+        Some(Coordinate {
+            z: dimension.has_z().then(|| ordinates.next()).flatten(),
+            m: dimension.has_m().then(|| ordinates.next()).flatten(),
+            ..Coordinate::new(values[0], values[1])
+        })
+    })
+}
+
+/// An empty point, whose coordinates are NaN as in WKB.
+fn empty_coordinate(dimension: Dimension) -> Coordinate {
+    Coordinate {
+        z: dimension.has_z().then_some(f64::NAN),
+        m: dimension.has_m().then_some(f64::NAN),
+        ..Coordinate::new(f64::NAN, f64::NAN)
+    }
+}
+
+fn point_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, WkbPoint> {
+    map(
+        alt((
+            map(ws(tag_no_case("EMPTY")), move |_| {
+                empty_coordinate(tag.unwrap_or_default())
+            }),
+            delimited(ws(char('(')), coordinate(tag), ws(char(')'))),
+        )),
+        WkbPoint::new,
+    )
+}
+
+fn line_string_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, WkbLineString> {
+    map(list_or_empty(coordinate(tag)), move |points| {
+        apply_tag(
+            WkbLineString::new(points),
+            tag,
+            WkbLineString::with_dimension,
+        )
+    })
+}
+
+fn rings_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<LinearRing>> {
+    list_or_empty(map(list(coordinate(tag)), LinearRing::new))
+}
+
+fn polygon_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, WkbPolygon> {
+    map(rings_text(tag), move |rings| {
+        apply_tag(WkbPolygon::new(rings), tag, WkbPolygon::with_dimension)
+    })
+}
+
+fn triangle_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, WkbTriangle> {
+    map(rings_text(tag), move |rings| {
+        apply_tag(WkbTriangle::new(rings), tag, WkbTriangle::with_dimension)
+    })
+}
+
+/// The points of a multipoint, with or without parentheses around each one.
+fn multi_point_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, WkbMultiPoint> {
+    map(
+        list_or_empty(alt((point_text(tag), map(coordinate(tag), WkbPoint::new)))),
+        move |points| {
+            apply_tag(
+                WkbMultiPoint::new(points),
+                tag,
+                WkbMultiPoint::with_dimension,
+            )
+        },
+    )
+}
+
+fn multi_line_string_text<'a>(
+    tag: Tag,
+) -> impl FnMut(&'a str) -> IResult<&'a str, WkbMultiLineString> {
+    map(list_or_empty(line_string_text(tag)), move |line_strings| {
+        apply_tag(
+            WkbMultiLineString::new(line_strings),
+            tag,
+            WkbMultiLineString::with_dimension,
+        )
+    })
+}
+
+fn multi_polygon_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, WkbMultiPolygon> {
+    map(list_or_empty(polygon_text(tag)), move |polygons| {
+        apply_tag(
+            WkbMultiPolygon::new(polygons),
+            tag,
+            WkbMultiPolygon::with_dimension,
+        )
+    })
+}
+
+fn polyhedral_surface_text<'a>(
+    tag: Tag,
+) -> impl FnMut(&'a str) -> IResult<&'a str, WkbPolyhedralSurface> {
+    map(list_or_empty(polygon_text(tag)), move |polygons| {
+        apply_tag(
+            WkbPolyhedralSurface::new(polygons),
+            tag,
+            WkbPolyhedralSurface::with_dimension,
+        )
+    })
+}
+
+fn tin_text<'a>(tag: Tag) -> impl FnMut(&'a str) -> IResult<&'a str, WkbTin> {
+    map(list_or_empty(polygon_text(tag)), move |polygons| {
+        apply_tag(WkbTin::new(polygons), tag, WkbTin::with_dimension)
+    })
+}
+
+/// The members of a geometry collection, which carry their own tags.
+fn geometry_collection_text<'a>(
+    tag: Tag,
+) -> impl FnMut(&'a str) -> IResult<&'a str, WkbGeometryCollection> {
+    map(list_or_empty(geometry), move |geometries| {
+        apply_tag(
+            WkbGeometryCollection::new(geometries),
+            tag,
+            WkbGeometryCollection::with_dimension,
+        )
+    })
+}
+
+/// A geometry `name` and dimension tag, followed by the `text` of the
+/// geometry.
+fn tagged<'a, G, P>(
+    name: &'static str,
+    text: impl Fn(Tag) -> P,
+    variant: impl Fn(G) -> WkbGeometry,
+) -> impl FnMut(&'a str) -> IResult<&'a str, WkbGeometry>
+where
+    P: FnMut(&'a str) -> IResult<&'a str, G>,
+{
+    move |input| {
+        let (input, tag) = preceded(ws(tag_no_case(name)), dimension_tag)(input)?;
+        map(text(tag), &variant)(input)
+    }
+}
+
+fn geometry(input: &str) -> IResult<&str, WkbGeometry> {
+    // Multi types come first, as their names start with the single types'.
+    alt((
+        tagged("MULTIPOINT", multi_point_text, WkbGeometry::MultiPoint),
+        tagged(
+            "MULTILINESTRING",
+            multi_line_string_text,
+            WkbGeometry::MultiLineString,
+        ),
+        tagged(
+            "MULTIPOLYGON",
+            multi_polygon_text,
+            WkbGeometry::MultiPolygon,
+        ),
+        tagged(
+            "GEOMETRYCOLLECTION",
+            geometry_collection_text,
+            WkbGeometry::GeometryCollection,
+        ),
+        tagged("POINT", point_text, WkbGeometry::Point),
+        tagged("LINESTRING", line_string_text, WkbGeometry::LineString),
+        tagged("POLYGON", polygon_text, WkbGeometry::Polygon),
+        tagged(
+            "POLYHEDRALSURFACE",
+            polyhedral_surface_text,
+            WkbGeometry::PolyhedralSurface,
+        ),
+        tagged("TRIANGLE", triangle_text, WkbGeometry::Triangle),
+        tagged("TIN", tin_text, WkbGeometry::Tin),
+    ))(input)
+}
+
+/// Parse a Well-Known Text geometry.
+pub fn parse_wkt(wkt: &str) -> anyhow::Result<WkbGeometry> {
+    let (_rest, geometry) = all_consuming(terminated(geometry, multispace0))(wkt)
+        .map_err(|error| anyhow::anyhow!("Failed to parse WKT: {}", error))?;
+    Ok(geometry)
+}
+
+fn geometry_name(geometry_type: WkbGeometryType) -> &'static str {
+    match geometry_type {
+        WkbGeometryType::Point => "POINT",
+        WkbGeometryType::LineString => "LINESTRING",
+        WkbGeometryType::Polygon => "POLYGON",
+        WkbGeometryType::Triangle => "TRIANGLE",
+        WkbGeometryType::MultiPoint => "MULTIPOINT",
+        WkbGeometryType::MultiLineString => "MULTILINESTRING",
+        WkbGeometryType::MultiPolygon => "MULTIPOLYGON",
+        WkbGeometryType::GeometryCollection => "GEOMETRYCOLLECTION",
+        WkbGeometryType::PolyhedralSurface => "POLYHEDRALSURFACE",
+        WkbGeometryType::Tin => "TIN",
+    }
+}
+
+struct WktWriter {
+    wkt: String,
+    precision: Option<usize>,
+}
+
+impl WktWriter {
+    fn number(&mut self, value: f64) {
+        match self.precision {
+            None => write!(self.wkt, "{}", value).unwrap(),
+            Some(precision) => {
+                let number = format!("{:.*}", precision, value);
+                let number = match number.contains('.') {
+                    true => number.trim_end_matches('0').trim_end_matches('.'),
+                    false => &number,
+                };
+                match number {
+                    "-0" => self.wkt.push('0'),
+                    number => self.wkt.push_str(number),
+                }
+            }
+        }
+    }
+
+    fn coordinate(&mut self, coordinate: &Coordinate) {
+        [
+            Some(coordinate.x),
+            Some(coordinate.y),
+            coordinate.z,
+            coordinate.m,
+        ]
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .for_each(|(index, value)| {
+            if index > 0 {
+                self.wkt.push(' ');
+            }
+            self.number(value);
+        });
+    }
+
+    /// Write `items` in parentheses, or `EMPTY` if there are none.
+    fn list<T>(&mut self, items: &[T], mut item: impl FnMut(&mut Self, &T)) {
+        if items.is_empty() {
+            self.wkt.push_str("EMPTY");
+            return;
+        }
+        self.wkt.push('(');
+        for (index, value) in items.iter().enumerate() {
+            if index > 0 {
+                self.wkt.push_str(", ");
+            }
+            item(self, value);
+        }
+        self.wkt.push(')');
+    }
+
+    fn point(&mut self, point: &WkbPoint) {
+        if point.point.x.is_nan() && point.point.y.is_nan() {
+            self.wkt.push_str("EMPTY");
+        } else {
+            self.wkt.push('(');
+            self.coordinate(&point.point);
+            self.wkt.push(')');
+        }
+    }
+
+    fn line_string(&mut self, line_string: &WkbLineString) {
+        self.list(&line_string.points, Self::coordinate)
+    }
+
+    fn rings(&mut self, rings: &[LinearRing]) {
+        self.list(rings, |writer, ring| {
+            writer.list(ring.coordinates(), Self::coordinate)
+        })
+    }
+
+    fn polygons(&mut self, polygons: &[WkbPolygon]) {
+        self.list(polygons, |writer, polygon| writer.rings(&polygon.rings))
+    }
+
+    fn geometry(&mut self, geometry: &WkbGeometry) {
+        self.wkt.push_str(geometry_name(geometry.geometry_type()));
+        let tag = match geometry.dimension() {
+            Dimension::Xy => " ",
+            Dimension::Xyz => " Z ",
+            Dimension::Xym => " M ",
+            Dimension::Xyzm => " ZM ",
+        };
+        self.wkt.push_str(tag);
+        match geometry {
+            WkbGeometry::Point(point) => self.point(point),
+            WkbGeometry::LineString(line_string) => self.line_string(line_string),
+            WkbGeometry::Polygon(polygon) => self.rings(&polygon.rings),
+            WkbGeometry::Triangle(triangle) => self.rings(&triangle.rings),
+            WkbGeometry::MultiPoint(multi_point) => self.list(&multi_point.points, Self::point),
+            WkbGeometry::MultiLineString(multi_line_string) => {
+                self.list(&multi_line_string.line_strings, Self::line_string)
+            }
+            WkbGeometry::MultiPolygon(multi_polygon) => self.polygons(&multi_polygon.polygons),
+            WkbGeometry::GeometryCollection(collection) => {
+                self.list(&collection.geometries, Self::geometry)
+            }
+            WkbGeometry::PolyhedralSurface(surface) => self.polygons(&surface.polygons),
+            WkbGeometry::Tin(tin) => self.polygons(&tin.polygons),
+        }
+    }
+}
+
+/// Write `geometry` as Well-Known Text, rounding coordinates to `precision`
+/// decimal places if given.
+pub fn write_wkt(geometry: &WkbGeometry, precision: Option<usize>) -> String {
+    let mut writer = WktWriter {
+        wkt: String::new(),
+        precision,
+    };
+    writer.geometry(geometry);
+    writer.wkt
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_wkt() {
+        assert_eq!(
+            WkbGeometry::Point(WkbPoint::new(Coordinate::new(1.5, -2.0))),
+            parse_wkt("POINT (1.5 -2)").unwrap()
+        );
+        assert_eq!(
+            parse_wkt("MULTIPOINT ((0 0), (1 1))").unwrap(),
+            parse_wkt("multipoint(0 0,1 1)").unwrap()
+        );
+        assert_eq!(
+            Some([-1.0, 0.0, 2.0, 4.0]),
+            parse_wkt("MULTIPOLYGON (((0 0, 2 0, 2 1, 0 0)), ((-1 3, 0 4, -1 4, -1 3)))")
+                .unwrap()
+                .envelope()
+        );
+        assert!(parse_wkt("GEOMETRYCOLLECTION (POINT (0 0), LINESTRING (0 0, 1 1))").is_ok());
+        assert!(parse_wkt("POINT (0)").is_err());
+        assert!(parse_wkt("POINT (0 0) trailing").is_err());
+    }
+
+    #[test]
+    fn test_parse_wkt_dimensions() {
+        let point = parse_wkt("POINT M (1 2 3)").unwrap();
+        assert_eq!(Dimension::Xym, point.dimension());
+        assert_eq!(point, parse_wkt(&write_wkt(&point, None)).unwrap());
+        assert_eq!(
+            Dimension::Xyzm,
+            parse_wkt("LINESTRING (0 0 1 2, 1 1 1 2)")
+                .unwrap()
+                .dimension()
+        );
+        assert_eq!(
+            Dimension::Xyz,
+            parse_wkt("LINESTRING Z EMPTY").unwrap().dimension()
+        );
+        assert!(parse_wkt("POINT Z (1 2)").is_err());
+    }
+
+    #[test]
+    fn test_wkt_round_trip() {
+        let wkts = [
+            "POINT EMPTY",
+            "POINT ZM (1 2 3 4)",
+            "LINESTRING (0 0, 1.5 1)",
+            "POLYGON ((0 0, 1 0, 0 1, 0 0), (0.1 0.1, 0.2 0.1, 0.1 0.2, 0.1 0.1))",
+            "TRIANGLE Z ((0 0 1, 1 0 1, 0 1 1, 0 0 1))",
+            "MULTIPOINT ((0 0), EMPTY)",
+            "MULTILINESTRING M ((0 0 1, 1 1 2), EMPTY)",
+            "MULTIPOLYGON EMPTY",
+            "GEOMETRYCOLLECTION Z (POINT Z (0 0 0), GEOMETRYCOLLECTION Z EMPTY)",
+            "POLYHEDRALSURFACE Z (((0 0 0, 0 1 0, 1 1 0, 0 0 0)))",
+            "TIN (((0 0, 1 0, 0 1, 0 0)), ((1 0, 1 1, 0 1, 1 0)))",
+        ];
+        for wkt in wkts {
+            let geometry = WkbGeometry::from_wkt(wkt).unwrap();
+            assert_eq!(wkt, geometry.to_wkt());
+        }
+        assert_eq!(
+            "POINT (0.12 -3)",
+            write_wkt(&parse_wkt("POINT (0.1249 -3.0001)").unwrap(), Some(2))
+        );
+    }
+}